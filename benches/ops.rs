@@ -0,0 +1,190 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use flamer::backends::{CPUBackend, VulkanBackend};
+use flamer::lazybuffer::{Activation, Backend, BufferHandle, UnaryOp, get_next_buffer_id};
+use flamer::tensor::Tensor;
+
+const SIZES: [usize; 3] = [1_000, 100_000, 1_000_000];
+// side length of the square matrices
+const MATMUL_SIZES: [usize; 3] = [64, 256, 512];
+const BACKWARD_SIZES: [usize; 2] = [1_000, 100_000];
+
+// the CPU backend, and Vulkan if a device can be set up on this machine
+fn backends() -> Vec<Box<dyn Backend>> {
+    let mut backends: Vec<Box<dyn Backend>> = vec![Box::new(CPUBackend::new())];
+    match std::panic::catch_unwind(|| VulkanBackend::new("FlameR bench")) {
+        Ok(vulkan) => backends.push(Box::new(vulkan)),
+        Err(_) => println!("no Vulkan device, only the CPU backend is benchmarked"),
+    }
+    backends
+}
+
+fn device_buffer(backend: &dyn Backend, size: usize, value: f32) -> BufferHandle {
+    let handle = backend.allocate_buffer(get_next_buffer_id(), size);
+    backend.to_device(&vec![value; size], &handle);
+    handle
+}
+
+// the ops are run on the backend directly with buffers uploaded beforehand, so only the op
+// itself is timed and no graph is built
+fn bench_binary(
+    c: &mut Criterion,
+    name: &str,
+    op: fn(&dyn Backend, &BufferHandle, &BufferHandle, &BufferHandle, usize),
+) {
+    let mut group = c.benchmark_group(name);
+    for backend in backends() {
+        let backend = backend.as_ref();
+        for size in SIZES {
+            let a = device_buffer(backend, size, 1.0);
+            let b = device_buffer(backend, size, 2.0);
+            let result = backend.allocate_buffer(get_next_buffer_id(), size);
+            group.throughput(Throughput::Elements(size as u64));
+            group.bench_with_input(
+                BenchmarkId::new(backend.name(), size),
+                &size,
+                |bench, &size| bench.iter(|| op(backend, &a, &b, &result, size)),
+            );
+            backend.free_buffer(&a);
+            backend.free_buffer(&b);
+            backend.free_buffer(&result);
+        }
+    }
+    group.finish();
+}
+
+fn add(c: &mut Criterion) {
+    bench_binary(c, "add", |backend, a, b, result, size| {
+        backend.add(a, b, result, size)
+    });
+}
+
+fn multiply(c: &mut Criterion) {
+    bench_binary(c, "multiply", |backend, a, b, result, size| {
+        backend.multiply(a, b, result, size)
+    });
+}
+
+fn matmul(c: &mut Criterion) {
+    let mut group = c.benchmark_group("matmul");
+    for backend in backends() {
+        let backend = backend.as_ref();
+        for size in MATMUL_SIZES {
+            let a = device_buffer(backend, size * size, 1.0);
+            let b = device_buffer(backend, size * size, 2.0);
+            let result = backend.allocate_buffer(get_next_buffer_id(), size * size);
+            // one multiply-add per element of a row times a column
+            group.throughput(Throughput::Elements((size * size * size) as u64));
+            group.bench_with_input(
+                BenchmarkId::new(backend.name(), size),
+                &size,
+                |bench, &size| bench.iter(|| backend.matmul(&a, &b, &result, size, size, size)),
+            );
+            backend.free_buffer(&a);
+            backend.free_buffer(&b);
+            backend.free_buffer(&result);
+        }
+    }
+    group.finish();
+}
+
+fn sum(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sum");
+    for backend in backends() {
+        let backend = backend.as_ref();
+        for size in SIZES {
+            let a = device_buffer(backend, size, 1.0);
+            let result = backend.allocate_buffer(get_next_buffer_id(), 1);
+            group.throughput(Throughput::Elements(size as u64));
+            group.bench_with_input(
+                BenchmarkId::new(backend.name(), size),
+                &size,
+                |bench, &size| bench.iter(|| backend.sum(&a, &result, size)),
+            );
+            backend.free_buffer(&a);
+            backend.free_buffer(&result);
+        }
+    }
+    group.finish();
+}
+
+// gradients of a squared error through a weight and a bias. the forward pass is realized once
+// and only backward is timed. every call adds its gradient nodes to the registry, which never
+// shrinks, so the sizes stay smaller than for the single ops
+fn backward(c: &mut Criterion) {
+    let mut group = c.benchmark_group("backward");
+    for backend in backends() {
+        let backend = backend.as_ref();
+        for size in BACKWARD_SIZES {
+            let input = Tensor::without_grad(vec![1.0; size]);
+            let target = Tensor::without_grad(vec![2.0; size]);
+            let weight = Tensor::new(vec![0.5; size]);
+            let bias = Tensor::new(vec![0.0; size]);
+            let error = target - (input * weight + bias);
+            let mut loss = (error * error).sum();
+            loss.realize(backend);
+            group.throughput(Throughput::Elements(size as u64));
+            group.bench_with_input(BenchmarkId::new(backend.name(), size), &size, |bench, _| {
+                bench.iter(|| loss.backward(backend))
+            });
+        }
+    }
+    group.finish();
+}
+
+// a dense layer with relu, the fused bias_activation against the same layer from single ops:
+// the bias broadcast to every row, an add and relu as (x + |x|) / 2
+fn linear_activation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("linear_activation");
+    for backend in backends() {
+        let backend = backend.as_ref();
+        for size in MATMUL_SIZES {
+            let elements = size * size;
+            let x = device_buffer(backend, elements, 1.0);
+            let weight = device_buffer(backend, elements, -0.5);
+            let bias = device_buffer(backend, size, 0.25);
+            let [product, bias_rows, sum, abs, output] =
+                [(); 5].map(|_| backend.allocate_buffer(get_next_buffer_id(), elements));
+            group.throughput(Throughput::Elements(elements as u64));
+            let id = format!("{}/fused", backend.name());
+            group.bench_with_input(BenchmarkId::new(id, size), &size, |bench, &size| {
+                bench.iter(|| {
+                    backend.matmul(&x, &weight, &product, size, size, size);
+                    backend.bias_activation(
+                        &product,
+                        &bias,
+                        &output,
+                        elements,
+                        size,
+                        Activation::Relu,
+                    );
+                })
+            });
+            let id = format!("{}/unfused", backend.name());
+            group.bench_with_input(BenchmarkId::new(id, size), &size, |bench, &size| {
+                bench.iter(|| {
+                    backend.matmul(&x, &weight, &product, size, size, size);
+                    backend.broadcast_to(&bias, &bias_rows, elements, size, size);
+                    backend.add(&product, &bias_rows, &sum, elements);
+                    backend.unary(&sum, &abs, elements, UnaryOp::Abs);
+                    backend.add(&sum, &abs, &output, elements);
+                    backend.affine(&output, &output, elements, 0.5, 0.0);
+                })
+            });
+            for buffer in [x, weight, bias, product, bias_rows, sum, abs, output] {
+                backend.free_buffer(&buffer);
+            }
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    add,
+    multiply,
+    matmul,
+    sum,
+    backward,
+    linear_activation
+);
+criterion_main!(benches);
@@ -0,0 +1,603 @@
+use crate::backends::CPUBackend;
+use crate::lazybuffer::{
+    Activation, Backend, BufferHandle, DivByZero, LazyBufferHandle, UnaryOp,
+    get_next_backend_instance_id,
+};
+use crate::random::Distribution;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Device {
+    Cpu,
+    Gpu,
+}
+
+// rough costs in units of one CPU element op, an op runs wherever its work plus moving the
+// operands there comes out cheaper. the defaults favour the CPU below a few hundred thousand
+// elements, the per-size timings of `--bench` are what to tune them against
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CostModel {
+    // fixed cost of a GPU dispatch, submit and fence wait included
+    pub dispatch_overhead: f32,
+    // how many times faster the GPU gets through the same work
+    pub gpu_speedup: f32,
+    // moving one element between the devices
+    pub transfer_cost: f32,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        CostModel {
+            dispatch_overhead: 50_000.0,
+            gpu_speedup: 20.0,
+            transfer_cost: 2.0,
+        }
+    }
+}
+
+// runs every op on either the CPU or the GPU backend, whichever the cost model predicts to be
+// faster, and moves operands between them as needed. buffers are allocated on the CPU and
+// follow the ops that use them
+pub struct AutoBackend {
+    name: String,
+    instance_id: usize,
+    cpu: CPUBackend,
+    gpu: Box<dyn Backend>,
+    cost_model: Mutex<CostModel>,
+    // where the current values of each buffer are, the other device may hold an outdated copy
+    locations: Mutex<HashMap<LazyBufferHandle, Device>>,
+    // buffers that have storage on the GPU, they are freed there as well
+    on_gpu: Mutex<HashSet<LazyBufferHandle>>,
+}
+
+impl AutoBackend {
+    pub fn new(gpu: impl Backend + 'static) -> Self {
+        AutoBackend {
+            name: "Auto".to_string(),
+            instance_id: get_next_backend_instance_id(),
+            cpu: CPUBackend::new(),
+            gpu: Box::new(gpu),
+            cost_model: Mutex::new(CostModel::default()),
+            locations: Mutex::new(HashMap::new()),
+            on_gpu: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn with_cost_model(self, cost_model: CostModel) -> Self {
+        *self.cost_model.lock().unwrap() = cost_model;
+        self
+    }
+
+    pub fn cost_model(&self) -> CostModel {
+        *self.cost_model.lock().unwrap()
+    }
+
+    fn backend(&self, device: Device) -> &dyn Backend {
+        match device {
+            Device::Cpu => &self.cpu,
+            Device::Gpu => self.gpu.as_ref(),
+        }
+    }
+
+    fn location(&self, handle: &BufferHandle) -> Device {
+        match self.locations.lock().unwrap().get(&handle.id) {
+            Some(device) => *device,
+            None => panic!("Buffer with ID {:?} not found", handle.id),
+        }
+    }
+
+    // work is the number of element ops the op takes, roughly
+    fn choose(&self, work: usize, inputs: &[&BufferHandle]) -> Device {
+        let model = self.cost_model();
+        let (mut to_cpu, mut to_gpu) = (0, 0);
+        for input in inputs {
+            match self.location(input) {
+                Device::Cpu => to_gpu += input.size,
+                Device::Gpu => to_cpu += input.size,
+            }
+        }
+        let cpu_cost = work as f32 + to_cpu as f32 * model.transfer_cost;
+        let gpu_cost = model.dispatch_overhead
+            + work as f32 / model.gpu_speedup
+            + to_gpu as f32 * model.transfer_cost;
+        if gpu_cost < cpu_cost {
+            Device::Gpu
+        } else {
+            Device::Cpu
+        }
+    }
+
+    fn allocate_on(&self, handle: &BufferHandle, device: Device) {
+        self.backend(device).allocate_buffer(handle.id, handle.size);
+        if device == Device::Gpu {
+            self.on_gpu.lock().unwrap().insert(handle.id);
+        }
+    }
+
+    fn move_to(&self, handle: &BufferHandle, device: Device) {
+        let from = self.location(handle);
+        if from == device {
+            return;
+        }
+        let data = self.backend(from).to_host(handle, handle.size);
+        self.allocate_on(handle, device);
+        self.backend(device).to_device(&data, handle);
+        self.locations.lock().unwrap().insert(handle.id, device);
+    }
+
+    // picks a device for the op, brings the inputs there and runs it. the result lives there
+    // afterwards, its values on the other device are outdated
+    fn run(
+        &self,
+        work: usize,
+        inputs: &[&BufferHandle],
+        result: &BufferHandle,
+        op: impl FnOnce(&dyn Backend),
+    ) {
+        let device = self.choose(work, inputs);
+        for input in inputs {
+            self.move_to(input, device);
+        }
+        self.allocate_on(result, device);
+        self.locations.lock().unwrap().insert(result.id, device);
+        op(self.backend(device));
+    }
+}
+
+impl Backend for AutoBackend {
+    fn allocate_buffer(&self, lazy_buffer: LazyBufferHandle, size: usize) -> BufferHandle {
+        let location = self.locations.lock().unwrap().get(&lazy_buffer).copied();
+        match location {
+            Some(device) => self.backend(device).allocate_buffer(lazy_buffer, size),
+            None => {
+                let handle = self.cpu.allocate_buffer(lazy_buffer, size);
+                self.locations
+                    .lock()
+                    .unwrap()
+                    .insert(lazy_buffer, Device::Cpu);
+                handle
+            }
+        }
+    }
+    fn allocate_temporary_buffer(&self, data: &[f32], size: usize) -> BufferHandle {
+        let handle = self.cpu.allocate_temporary_buffer(data, size);
+        self.locations
+            .lock()
+            .unwrap()
+            .insert(handle.id, Device::Cpu);
+        handle
+    }
+    fn read_buffer(&self, handle: &BufferHandle) -> Vec<f32> {
+        self.backend(self.location(handle)).read_buffer(handle)
+    }
+    fn read_element(&self, handle: &BufferHandle, index: usize) -> f32 {
+        self.backend(self.location(handle))
+            .read_element(handle, index)
+    }
+    fn free_buffer(&self, handle: &BufferHandle) {
+        self.cpu.free_buffer(handle);
+        if self.on_gpu.lock().unwrap().remove(&handle.id) {
+            self.gpu.free_buffer(handle);
+        }
+        self.locations.lock().unwrap().remove(&handle.id);
+    }
+    fn drop(&self) {
+        Backend::drop(&self.cpu);
+        Backend::drop(self.gpu.as_ref());
+    }
+    // uploads land on the CPU, the first op that runs on the GPU moves them
+    fn to_device(&self, data: &[f32], handle: &BufferHandle) {
+        self.cpu.allocate_buffer(handle.id, handle.size);
+        self.cpu.to_device(data, handle);
+        self.locations
+            .lock()
+            .unwrap()
+            .insert(handle.id, Device::Cpu);
+    }
+    fn to_host(&self, handle: &BufferHandle, size: usize) -> Vec<f32> {
+        self.backend(self.location(handle)).to_host(handle, size)
+    }
+    fn fill(&self, handle: &BufferHandle, value: f32, size: usize) {
+        self.cpu.allocate_buffer(handle.id, handle.size);
+        self.cpu.fill(handle, value, size);
+        self.locations
+            .lock()
+            .unwrap()
+            .insert(handle.id, Device::Cpu);
+    }
+    fn upload_iter(
+        &self,
+        values: &mut dyn Iterator<Item = f32>,
+        handle: &BufferHandle,
+        size: usize,
+    ) {
+        self.cpu.allocate_buffer(handle.id, handle.size);
+        self.cpu.upload_iter(values, handle, size);
+        self.locations
+            .lock()
+            .unwrap()
+            .insert(handle.id, Device::Cpu);
+    }
+    fn fill_random(
+        &self,
+        handle: &BufferHandle,
+        seed: u64,
+        distribution: Distribution,
+        size: usize,
+    ) -> bool {
+        self.cpu.allocate_buffer(handle.id, handle.size);
+        self.cpu.fill_random(handle, seed, distribution, size);
+        self.locations
+            .lock()
+            .unwrap()
+            .insert(handle.id, Device::Cpu);
+        true
+    }
+
+    fn add(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize) {
+        self.run(size, &[a, b], result, |backend| {
+            backend.add(a, b, result, size)
+        });
+    }
+    fn subtract(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize) {
+        self.run(size, &[a, b], result, |backend| {
+            backend.subtract(a, b, result, size)
+        });
+    }
+    fn multiply(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize) {
+        self.run(size, &[a, b], result, |backend| {
+            backend.multiply(a, b, result, size)
+        });
+    }
+    fn divide(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize) {
+        self.run(size, &[a, b], result, |backend| {
+            backend.divide(a, b, result, size)
+        });
+    }
+    // writes into a, so a has to be where the copy runs
+    fn memset(&self, a: &BufferHandle, b: &BufferHandle, size: usize) {
+        self.run(size, &[a, b], a, |backend| backend.memset(a, b, size));
+    }
+    fn set_div_policy(&self, policy: DivByZero) {
+        self.cpu.set_div_policy(policy);
+        self.gpu.set_div_policy(policy);
+    }
+    fn cumsum(&self, a: &BufferHandle, result: &BufferHandle, size: usize, reverse: bool) {
+        self.run(size, &[a], result, |backend| {
+            backend.cumsum(a, result, size, reverse)
+        });
+    }
+    fn where_mask(
+        &self,
+        cond: &BufferHandle,
+        a: &BufferHandle,
+        b: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+    ) {
+        self.run(size, &[cond, a, b], result, |backend| {
+            backend.where_mask(cond, a, b, result, size)
+        });
+    }
+    fn transpose(&self, a: &BufferHandle, result: &BufferHandle, rows: usize, cols: usize) {
+        self.run(rows * cols, &[a], result, |backend| {
+            backend.transpose(a, result, rows, cols)
+        });
+    }
+    fn triangle_mask(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        rows: usize,
+        cols: usize,
+        diagonal: i32,
+        upper: bool,
+    ) {
+        self.run(rows * cols, &[a], result, |backend| {
+            backend.triangle_mask(a, result, rows, cols, diagonal, upper)
+        });
+    }
+    fn batch_transpose(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        batch: usize,
+        rows: usize,
+        cols: usize,
+    ) {
+        self.run(batch * rows * cols, &[a], result, |backend| {
+            backend.batch_transpose(a, result, batch, rows, cols)
+        });
+    }
+    fn matmul(
+        &self,
+        a: &BufferHandle,
+        b: &BufferHandle,
+        result: &BufferHandle,
+        m: usize,
+        k: usize,
+        n: usize,
+    ) {
+        self.run(m * k * n, &[a, b], result, |backend| {
+            backend.matmul(a, b, result, m, k, n)
+        });
+    }
+    fn max_pool1d(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel: usize,
+        stride: usize,
+    ) {
+        self.run(result.size * kernel, &[a], result, |backend| {
+            backend.max_pool1d(a, result, input_size, kernel, stride)
+        });
+    }
+    fn max_pool1d_backward(
+        &self,
+        a: &BufferHandle,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel: usize,
+        stride: usize,
+    ) {
+        self.run(grad.size * kernel, &[a, grad], result, |backend| {
+            backend.max_pool1d_backward(a, grad, result, input_size, kernel, stride)
+        });
+    }
+    fn interpolate_linear(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        output_size: usize,
+    ) {
+        self.run(output_size, &[a], result, |backend| {
+            backend.interpolate_linear(a, result, input_size, output_size)
+        });
+    }
+    fn interpolate_linear_backward(
+        &self,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        output_size: usize,
+    ) {
+        self.run(output_size, &[grad], result, |backend| {
+            backend.interpolate_linear_backward(grad, result, input_size, output_size)
+        });
+    }
+    fn unary(&self, a: &BufferHandle, result: &BufferHandle, size: usize, op: UnaryOp) {
+        self.run(size, &[a], result, |backend| {
+            backend.unary(a, result, size, op)
+        });
+    }
+    fn affine(&self, a: &BufferHandle, result: &BufferHandle, size: usize, scale: f32, shift: f32) {
+        self.run(size, &[a], result, |backend| {
+            backend.affine(a, result, size, scale, shift)
+        });
+    }
+    fn clamp(&self, a: &BufferHandle, result: &BufferHandle, size: usize, min: f32, max: f32) {
+        self.run(size, &[a], result, |backend| {
+            backend.clamp(a, result, size, min, max)
+        });
+    }
+    fn sum(&self, a: &BufferHandle, result: &BufferHandle, size: usize) {
+        self.run(size, &[a], result, |backend| backend.sum(a, result, size));
+    }
+    fn expand(&self, a: &BufferHandle, result: &BufferHandle, size: usize) {
+        self.run(size, &[a], result, |backend| {
+            backend.expand(a, result, size)
+        });
+    }
+    fn repeat_interleave(&self, a: &BufferHandle, result: &BufferHandle, size: usize, n: usize) {
+        self.run(size, &[a], result, |backend| {
+            backend.repeat_interleave(a, result, size, n)
+        });
+    }
+    fn repeat_interleave_backward(
+        &self,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        n: usize,
+    ) {
+        self.run(size, &[grad], result, |backend| {
+            backend.repeat_interleave_backward(grad, result, size, n)
+        });
+    }
+    fn broadcast_to(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        n: usize,
+        inner: usize,
+    ) {
+        self.run(size, &[a], result, |backend| {
+            backend.broadcast_to(a, result, size, n, inner)
+        });
+    }
+    fn broadcast_to_backward(
+        &self,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        n: usize,
+        inner: usize,
+    ) {
+        self.run(size * n, &[grad], result, |backend| {
+            backend.broadcast_to_backward(grad, result, size, n, inner)
+        });
+    }
+    fn pad(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        left: usize,
+        right: usize,
+    ) {
+        self.run(input_size + left + right, &[a], result, |backend| {
+            backend.pad(a, result, input_size, left, right)
+        });
+    }
+    fn slice(&self, a: &BufferHandle, result: &BufferHandle, start: usize, len: usize) {
+        self.run(len, &[a], result, |backend| {
+            backend.slice(a, result, start, len)
+        });
+    }
+    fn conv1d(
+        &self,
+        input: &BufferHandle,
+        kernel: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel_size: usize,
+        stride: usize,
+    ) {
+        self.run(
+            input_size * kernel_size / stride,
+            &[input, kernel],
+            result,
+            |backend| backend.conv1d(input, kernel, result, input_size, kernel_size, stride),
+        );
+    }
+    fn conv1d_input_grad(
+        &self,
+        grad: &BufferHandle,
+        kernel: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel_size: usize,
+        stride: usize,
+    ) {
+        self.run(
+            input_size * kernel_size / stride,
+            &[grad, kernel],
+            result,
+            |backend| {
+                backend.conv1d_input_grad(grad, kernel, result, input_size, kernel_size, stride)
+            },
+        );
+    }
+    fn conv1d_kernel_grad(
+        &self,
+        input: &BufferHandle,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel_size: usize,
+        stride: usize,
+    ) {
+        self.run(
+            input_size * kernel_size / stride,
+            &[input, grad],
+            result,
+            |backend| {
+                backend.conv1d_kernel_grad(input, grad, result, input_size, kernel_size, stride)
+            },
+        );
+    }
+    fn gather(
+        &self,
+        table: &BufferHandle,
+        indices: &BufferHandle,
+        result: &BufferHandle,
+        rows: usize,
+        row_len: usize,
+        count: usize,
+    ) {
+        self.run(count * row_len, &[table, indices], result, |backend| {
+            backend.gather(table, indices, result, rows, row_len, count)
+        });
+    }
+    fn gather_backward(
+        &self,
+        grad: &BufferHandle,
+        indices: &BufferHandle,
+        result: &BufferHandle,
+        rows: usize,
+        row_len: usize,
+        count: usize,
+    ) {
+        self.run(
+            rows * row_len * count,
+            &[grad, indices],
+            result,
+            |backend| backend.gather_backward(grad, indices, result, rows, row_len, count),
+        );
+    }
+    fn scatter_add(
+        &self,
+        table: &BufferHandle,
+        indices: &BufferHandle,
+        updates: &BufferHandle,
+        result: &BufferHandle,
+        rows: usize,
+        row_len: usize,
+        count: usize,
+    ) {
+        self.run(
+            (rows + count) * row_len,
+            &[table, indices, updates],
+            result,
+            |backend| backend.scatter_add(table, indices, updates, result, rows, row_len, count),
+        );
+    }
+    fn bias_activation(
+        &self,
+        a: &BufferHandle,
+        bias: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        row_len: usize,
+        activation: Activation,
+    ) {
+        self.run(size, &[a, bias], result, |backend| {
+            backend.bias_activation(a, bias, result, size, row_len, activation)
+        });
+    }
+    fn activation_backward(
+        &self,
+        grad: &BufferHandle,
+        pre_activation: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        activation: Activation,
+    ) {
+        self.run(size, &[grad, pre_activation], result, |backend| {
+            backend.activation_backward(grad, pre_activation, result, size, activation)
+        });
+    }
+    fn log_softmax(&self, a: &BufferHandle, result: &BufferHandle, size: usize, row_len: usize) {
+        self.run(size, &[a], result, |backend| {
+            backend.log_softmax(a, result, size, row_len)
+        });
+    }
+    fn log_softmax_backward(
+        &self,
+        grad: &BufferHandle,
+        output: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        row_len: usize,
+    ) {
+        self.run(size, &[grad, output], result, |backend| {
+            backend.log_softmax_backward(grad, output, result, size, row_len)
+        });
+    }
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn instance_id(&self) -> usize {
+        self.instance_id
+    }
+    // chunked work tiles a single device's memory, the tiles would end up split over both
+    fn memory_budget(&self) -> Option<usize> {
+        None
+    }
+}
@@ -1,48 +1,79 @@
-use crate::lazybuffer::{Backend, BufferHandle, LAZYBUFFER_HANDLE_NULL, LazyBufferHandle};
+use crate::lazybuffer::{
+    Activation, Backend, BufferHandle, DType, DivByZero, LazyBufferHandle, UnaryOp, check_dtypes,
+    get_next_backend_instance_id, get_next_temporary_id,
+};
+use crate::random::{Distribution, sample, stream_key};
 use std::collections::HashMap;
 use std::sync::Mutex;
 
 pub struct CPUBackend {
     name: String,
+    instance_id: usize,
     buffers: Mutex<HashMap<LazyBufferHandle, Vec<f32>>>,
+    div_policy: Mutex<DivByZero>,
+    peak_buffers: Mutex<usize>,
 }
 
 impl CPUBackend {
     pub fn new() -> Self {
         CPUBackend {
             name: "CPU".to_string(),
+            instance_id: get_next_backend_instance_id(),
             buffers: Mutex::new(HashMap::new()),
+            div_policy: Mutex::new(DivByZero::default()),
+            peak_buffers: Mutex::new(0),
         }
     }
+    // the most buffers that were allocated at the same time, temporary ones included
+    pub fn peak_buffer_count(&self) -> usize {
+        *self.peak_buffers.lock().unwrap()
+    }
+    fn record_peak(&self, buffers: &HashMap<LazyBufferHandle, Vec<f32>>) {
+        let mut peak = self.peak_buffers.lock().unwrap();
+        *peak = (*peak).max(buffers.len());
+    }
 }
 
 impl Backend for CPUBackend {
     fn allocate_buffer(&self, lazy_buffer: LazyBufferHandle, size: usize) -> BufferHandle {
         if let Some(buffer) = self.buffers.lock().unwrap().get(&lazy_buffer) {
+            if buffer.len() != size {
+                panic!(
+                    "buffer {:?} is already allocated with {} elements, requested {}",
+                    lazy_buffer,
+                    buffer.len(),
+                    size
+                );
+            }
             return BufferHandle {
                 id: lazy_buffer,
+                dtype: DType::F32,
                 size: buffer.len(),
             };
         }
         let handle = BufferHandle {
             id: lazy_buffer,
             size,
+            dtype: DType::F32,
         };
 
         // Initialize with zeros
         let mut buffers = self.buffers.lock().unwrap();
         buffers.insert(handle.id, vec![0.0; size]);
+        self.record_peak(&buffers);
 
         handle
     }
     fn allocate_temporary_buffer(&self, data: &[f32], size: usize) -> BufferHandle {
         let handle = BufferHandle {
-            id: LAZYBUFFER_HANDLE_NULL,
+            id: get_next_temporary_id(),
             size,
+            dtype: DType::F32,
         };
 
         let mut buffers = self.buffers.lock().unwrap();
         buffers.insert(handle.id, data.to_vec());
+        self.record_peak(&buffers);
 
         handle
     }
@@ -54,6 +85,14 @@ impl Backend for CPUBackend {
             panic!("Buffer with ID {:?} not found", handle.id);
         }
     }
+    fn read_element(&self, handle: &BufferHandle, index: usize) -> f32 {
+        let buffers = self.buffers.lock().unwrap();
+        if let Some(buffer) = buffers.get(&handle.id) {
+            buffer[index]
+        } else {
+            panic!("Buffer with ID {:?} not found", handle.id);
+        }
+    }
     fn free_buffer(&self, handle: &BufferHandle) {
         let mut buffers = self.buffers.lock().unwrap();
         buffers.remove(&handle.id);
@@ -71,6 +110,37 @@ impl Backend for CPUBackend {
         }
     }
 
+    fn fill(&self, handle: &BufferHandle, value: f32, size: usize) {
+        let mut buffers = self.buffers.lock().unwrap();
+        buffers.insert(handle.id, vec![value; size]);
+    }
+
+    fn upload_iter(
+        &self,
+        values: &mut dyn Iterator<Item = f32>,
+        handle: &BufferHandle,
+        size: usize,
+    ) {
+        let mut buffers = self.buffers.lock().unwrap();
+        buffers.insert(handle.id, values.take(size).collect());
+    }
+
+    fn fill_random(
+        &self,
+        handle: &BufferHandle,
+        seed: u64,
+        distribution: Distribution,
+        size: usize,
+    ) -> bool {
+        let key = stream_key(seed);
+        let mut buffers = self.buffers.lock().unwrap();
+        buffers.insert(
+            handle.id,
+            (0..size).map(|i| sample(key, i, distribution)).collect(),
+        );
+        true
+    }
+
     fn to_host(&self, handle: &BufferHandle, _size: usize) -> Vec<f32> {
         let buffers = self.buffers.lock().unwrap();
         if let Some(buffer) = buffers.get(&handle.id) {
@@ -80,71 +150,760 @@ impl Backend for CPUBackend {
         }
     }
 
+    // the elementwise ops slice their operands to size once up front and zip over them, so
+    // there's no bounds check per element in the loop
     fn add(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize) {
+        check_dtypes("add", &[a, b, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let a_data = &buffers.get(&a.id).expect("Buffer A not found")[..size];
+        let b_data = &buffers.get(&b.id).expect("Buffer B not found")[..size];
+
+        let result_data = a_data.iter().zip(b_data).map(|(a, b)| a + b).collect();
+
+        buffers.insert(result.id, result_data);
+    }
+
+    fn subtract(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize) {
+        check_dtypes("subtract", &[a, b, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let a_data = &buffers.get(&a.id).expect("Buffer A not found")[..size];
+        let b_data = &buffers.get(&b.id).expect("Buffer B not found")[..size];
+
+        let result_data = a_data.iter().zip(b_data).map(|(a, b)| a - b).collect();
+        buffers.insert(result.id, result_data);
+    }
+
+    fn multiply(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize) {
+        check_dtypes("multiply", &[a, b, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let a_data = &buffers.get(&a.id).expect("Buffer A not found")[..size];
+        let b_data = &buffers.get(&b.id).expect("Buffer B not found")[..size];
+
+        let result_data = a_data.iter().zip(b_data).map(|(a, b)| a * b).collect();
+
+        buffers.insert(result.id, result_data);
+    }
+
+    fn divide(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize) {
+        check_dtypes("divide", &[a, b, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let a_data = &buffers.get(&a.id).expect("Buffer A not found")[..size];
+        let b_data = &buffers.get(&b.id).expect("Buffer B not found")[..size];
+
+        let policy = *self.div_policy.lock().unwrap();
+        let result_data = a_data
+            .iter()
+            .zip(b_data)
+            .map(|(&a, &b)| policy.divide(a, b))
+            .collect();
+        buffers.insert(result.id, result_data);
+    }
+    fn set_div_policy(&self, policy: DivByZero) {
+        *self.div_policy.lock().unwrap() = policy;
+    }
+    fn memset(&self, a: &BufferHandle, b: &BufferHandle, _: usize) {
+        check_dtypes("memset", &[a, b]);
+        let mut buffers = self.buffers.lock().unwrap();
+        let b_data = buffers.get(&b.id).expect("Buffer B not found").clone();
+        let a_data = buffers.get_mut(&a.id).expect("Buffer A not found");
+        a_data.clone_from_slice(&b_data);
+    }
+    fn cumsum(&self, a: &BufferHandle, result: &BufferHandle, size: usize, reverse: bool) {
+        check_dtypes("cumsum", &[a, result]);
         let mut buffers = self.buffers.lock().unwrap();
 
         let a_data = buffers.get(&a.id).expect("Buffer A not found");
-        let b_data = buffers.get(&b.id).expect("Buffer B not found");
 
-        let mut result_data = Vec::with_capacity(size);
+        let mut result_data = vec![0.0; size];
+        let mut total = 0.0;
         for i in 0..size {
-            result_data.push(a_data[i] + b_data[i]);
+            let i = if reverse { size - 1 - i } else { i };
+            total += a_data[i];
+            result_data[i] = total;
         }
+        buffers.insert(result.id, result_data);
+    }
+    fn where_mask(
+        &self,
+        cond: &BufferHandle,
+        a: &BufferHandle,
+        b: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+    ) {
+        check_dtypes("where", &[cond, a, b, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let cond_data = &buffers.get(&cond.id).expect("Buffer Cond not found")[..size];
+        let a_data = &buffers.get(&a.id).expect("Buffer A not found")[..size];
+        let b_data = &buffers.get(&b.id).expect("Buffer B not found")[..size];
 
+        let result_data = cond_data
+            .iter()
+            .zip(a_data.iter().zip(b_data))
+            .map(|(&cond, (&a, &b))| if cond != 0.0 { a } else { b })
+            .collect();
         buffers.insert(result.id, result_data);
     }
+    fn transpose(&self, a: &BufferHandle, result: &BufferHandle, rows: usize, cols: usize) {
+        check_dtypes("transpose", &[a, result]);
+        let mut buffers = self.buffers.lock().unwrap();
 
-    fn subtract(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize) {
+        let a_data = buffers.get(&a.id).expect("Buffer A not found");
+
+        let mut result_data = Vec::with_capacity(rows * cols);
+        for col in 0..cols {
+            for row in 0..rows {
+                result_data.push(a_data[row * cols + col]);
+            }
+        }
+        buffers.insert(result.id, result_data);
+    }
+    fn triangle_mask(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        rows: usize,
+        cols: usize,
+        diagonal: i32,
+        upper: bool,
+    ) {
+        check_dtypes("triangle_mask", &[a, result]);
         let mut buffers = self.buffers.lock().unwrap();
 
         let a_data = buffers.get(&a.id).expect("Buffer A not found");
-        let b_data = buffers.get(&b.id).expect("Buffer B not found");
 
-        let mut result_data = Vec::with_capacity(size);
-        for i in 0..size {
-            result_data.push(a_data[i] - b_data[i]);
+        let mut result_data = Vec::with_capacity(rows * cols);
+        for row in 0..rows {
+            for col in 0..cols {
+                let offset = col as i64 - row as i64;
+                let keep = if upper {
+                    offset >= diagonal as i64
+                } else {
+                    offset <= diagonal as i64
+                };
+                result_data.push(if keep { a_data[row * cols + col] } else { 0.0 });
+            }
         }
         buffers.insert(result.id, result_data);
     }
+    fn batch_transpose(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        batch: usize,
+        rows: usize,
+        cols: usize,
+    ) {
+        check_dtypes("batch_transpose", &[a, result]);
+        let mut buffers = self.buffers.lock().unwrap();
 
-    fn multiply(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize) {
+        let a_data = buffers.get(&a.id).expect("Buffer A not found");
+
+        let mut result_data = Vec::with_capacity(batch * rows * cols);
+        for matrix in a_data[..batch * rows * cols].chunks(rows * cols) {
+            for col in 0..cols {
+                for row in 0..rows {
+                    result_data.push(matrix[row * cols + col]);
+                }
+            }
+        }
+        buffers.insert(result.id, result_data);
+    }
+    fn matmul(
+        &self,
+        a: &BufferHandle,
+        b: &BufferHandle,
+        result: &BufferHandle,
+        m: usize,
+        k: usize,
+        n: usize,
+    ) {
+        check_dtypes("matmul", &[a, b, result]);
         let mut buffers = self.buffers.lock().unwrap();
 
         let a_data = buffers.get(&a.id).expect("Buffer A not found");
         let b_data = buffers.get(&b.id).expect("Buffer B not found");
 
-        let mut result_data = Vec::with_capacity(size);
-        for i in 0..size {
-            result_data.push(a_data[i] * b_data[i]);
+        let mut result_data = vec![0.0; m * n];
+        for row in 0..m {
+            for i in 0..k {
+                let a_value = a_data[row * k + i];
+                for col in 0..n {
+                    result_data[row * n + col] += a_value * b_data[i * n + col];
+                }
+            }
         }
+        buffers.insert(result.id, result_data);
+    }
+    fn max_pool1d(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel: usize,
+        stride: usize,
+    ) {
+        check_dtypes("max_pool1d", &[a, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let a_data = buffers.get(&a.id).expect("Buffer A not found");
 
+        let output_size = (input_size - kernel) / stride + 1;
+        let mut result_data = Vec::with_capacity(output_size);
+        for window in 0..output_size {
+            let start = window * stride;
+            result_data.push(a_data[start + argmax(&a_data[start..start + kernel])]);
+        }
         buffers.insert(result.id, result_data);
     }
+    fn max_pool1d_backward(
+        &self,
+        a: &BufferHandle,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel: usize,
+        stride: usize,
+    ) {
+        check_dtypes("max_pool1d_backward", &[a, grad, result]);
+        let mut buffers = self.buffers.lock().unwrap();
 
-    fn divide(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize) {
+        let a_data = buffers.get(&a.id).expect("Buffer A not found");
+        let grad_data = buffers.get(&grad.id).expect("Buffer Grad not found");
+
+        let output_size = (input_size - kernel) / stride + 1;
+        let mut result_data = vec![0.0; input_size];
+        for (window, &window_grad) in grad_data[..output_size].iter().enumerate() {
+            let start = window * stride;
+            result_data[start + argmax(&a_data[start..start + kernel])] += window_grad;
+        }
+        buffers.insert(result.id, result_data);
+    }
+    fn interpolate_linear(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        output_size: usize,
+    ) {
+        check_dtypes("interpolate_linear", &[a, result]);
         let mut buffers = self.buffers.lock().unwrap();
 
         let a_data = buffers.get(&a.id).expect("Buffer A not found");
-        let b_data = buffers.get(&b.id).expect("Buffer B not found");
 
-        let mut result_data = Vec::with_capacity(size);
-        for i in 0..size {
-            result_data.push(a_data[i] / b_data[i]);
+        let mut result_data = Vec::with_capacity(output_size);
+        for i in 0..output_size {
+            let (lo, hi, t) = interpolation_weights(i, input_size, output_size);
+            result_data.push(a_data[lo] * (1.0 - t) + a_data[hi] * t);
         }
         buffers.insert(result.id, result_data);
     }
-    fn memset(&self, a: &BufferHandle, b: &BufferHandle, _: usize) {
+    fn interpolate_linear_backward(
+        &self,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        output_size: usize,
+    ) {
+        check_dtypes("interpolate_linear_backward", &[grad, result]);
         let mut buffers = self.buffers.lock().unwrap();
-        let b_data = buffers.get(&b.id).expect("Buffer B not found").clone();
-        let a_data = buffers.get_mut(&a.id).expect("Buffer A not found");
-        a_data.clone_from_slice(&b_data);
+
+        let grad_data = buffers.get(&grad.id).expect("Buffer Grad not found");
+
+        let mut result_data = vec![0.0; input_size];
+        for (i, &g) in grad_data[..output_size].iter().enumerate() {
+            let (lo, hi, t) = interpolation_weights(i, input_size, output_size);
+            result_data[lo] += g * (1.0 - t);
+            result_data[hi] += g * t;
+        }
+        buffers.insert(result.id, result_data);
+    }
+    fn unary(&self, a: &BufferHandle, result: &BufferHandle, size: usize, op: UnaryOp) {
+        check_dtypes("unary", &[a, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let a_data = &buffers.get(&a.id).expect("Buffer A not found")[..size];
+
+        let result_data = a_data
+            .iter()
+            .map(|&a| match op {
+                UnaryOp::Abs => a.abs(),
+                UnaryOp::Sqrt => a.sqrt(),
+                // f32::signum is 1 for +0.0
+                UnaryOp::Sign => {
+                    if a == 0.0 {
+                        0.0
+                    } else {
+                        a.signum()
+                    }
+                }
+                UnaryOp::Neg => -a,
+                UnaryOp::Copy => a,
+                UnaryOp::Sin => a.sin(),
+                UnaryOp::Cos => a.cos(),
+                // f32::round rounds halfway cases away from zero, GLSL roundEven doesn't
+                UnaryOp::Round => a.round_ties_even(),
+                UnaryOp::Floor => a.floor(),
+                UnaryOp::Ceil => a.ceil(),
+            })
+            .collect();
+        buffers.insert(result.id, result_data);
+    }
+    fn sum(&self, a: &BufferHandle, result: &BufferHandle, size: usize) {
+        check_dtypes("sum", &[a, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let a_data = buffers.get(&a.id).expect("Buffer A not found");
+
+        let total = a_data[..size].iter().sum();
+        buffers.insert(result.id, vec![total]);
+    }
+    fn expand(&self, a: &BufferHandle, result: &BufferHandle, size: usize) {
+        check_dtypes("expand", &[a, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let a_data = buffers.get(&a.id).expect("Buffer A not found");
+
+        let result_data = vec![a_data[0]; size];
+        buffers.insert(result.id, result_data);
+    }
+    fn affine(&self, a: &BufferHandle, result: &BufferHandle, size: usize, scale: f32, shift: f32) {
+        check_dtypes("affine", &[a, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let a_data = &buffers.get(&a.id).expect("Buffer A not found")[..size];
+
+        let result_data = a_data.iter().map(|x| x.mul_add(scale, shift)).collect();
+        buffers.insert(result.id, result_data);
+    }
+    fn clamp(&self, a: &BufferHandle, result: &BufferHandle, size: usize, min: f32, max: f32) {
+        check_dtypes("clamp", &[a, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let a_data = &buffers.get(&a.id).expect("Buffer A not found")[..size];
+
+        let result_data = a_data.iter().map(|x| x.clamp(min, max)).collect();
+        buffers.insert(result.id, result_data);
+    }
+    fn repeat_interleave(&self, a: &BufferHandle, result: &BufferHandle, size: usize, n: usize) {
+        check_dtypes("repeat_interleave", &[a, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let a_data = &buffers.get(&a.id).expect("Buffer A not found")[..size / n];
+
+        let result_data = a_data
+            .iter()
+            .flat_map(|&a| std::iter::repeat_n(a, n))
+            .collect();
+        buffers.insert(result.id, result_data);
+    }
+    fn repeat_interleave_backward(
+        &self,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        n: usize,
+    ) {
+        check_dtypes("repeat_interleave_backward", &[grad, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let grad_data = &buffers.get(&grad.id).expect("Buffer Grad not found")[..size * n];
+
+        let result_data = grad_data
+            .chunks(n)
+            .map(|group| group.iter().sum())
+            .collect();
+        buffers.insert(result.id, result_data);
+    }
+    fn broadcast_to(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        n: usize,
+        inner: usize,
+    ) {
+        check_dtypes("broadcast_to", &[a, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let a_data = &buffers.get(&a.id).expect("Buffer A not found")[..size / n];
+
+        let result_data = a_data
+            .chunks(inner)
+            .flat_map(|block| std::iter::repeat_n(block, n).flatten().copied())
+            .collect();
+        buffers.insert(result.id, result_data);
+    }
+    fn broadcast_to_backward(
+        &self,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        n: usize,
+        inner: usize,
+    ) {
+        check_dtypes("broadcast_to_backward", &[grad, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let grad_data = &buffers.get(&grad.id).expect("Buffer Grad not found")[..size * n];
+
+        let result_data = grad_data
+            .chunks(n * inner)
+            .flat_map(|copies| {
+                (0..inner).map(move |i| (0..n).map(|copy| copies[copy * inner + i]).sum::<f32>())
+            })
+            .collect();
+        buffers.insert(result.id, result_data);
+    }
+    fn pad(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        left: usize,
+        right: usize,
+    ) {
+        check_dtypes("pad", &[a, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let a_data = &buffers.get(&a.id).expect("Buffer A not found")[..input_size];
+
+        let mut result_data = vec![0.0; left + input_size + right];
+        result_data[left..left + input_size].copy_from_slice(a_data);
+        buffers.insert(result.id, result_data);
+    }
+    fn slice(&self, a: &BufferHandle, result: &BufferHandle, start: usize, len: usize) {
+        check_dtypes("slice", &[a, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let a_data = buffers.get(&a.id).expect("Buffer A not found");
+
+        let result_data = a_data[start..start + len].to_vec();
+        buffers.insert(result.id, result_data);
+    }
+    fn conv1d(
+        &self,
+        input: &BufferHandle,
+        kernel: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel_size: usize,
+        stride: usize,
+    ) {
+        check_dtypes("conv1d", &[input, kernel, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let input_data = buffers.get(&input.id).expect("Buffer Input not found");
+        let kernel_data = buffers.get(&kernel.id).expect("Buffer Kernel not found");
+
+        let output_size = (input_size - kernel_size) / stride + 1;
+        let result_data = (0..output_size)
+            .map(|o| {
+                let window = &input_data[o * stride..o * stride + kernel_size];
+                window
+                    .iter()
+                    .zip(&kernel_data[..kernel_size])
+                    .map(|(x, k)| x * k)
+                    .sum()
+            })
+            .collect();
+        buffers.insert(result.id, result_data);
+    }
+    fn conv1d_input_grad(
+        &self,
+        grad: &BufferHandle,
+        kernel: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel_size: usize,
+        stride: usize,
+    ) {
+        check_dtypes("conv1d_input_grad", &[grad, kernel, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let grad_data = buffers.get(&grad.id).expect("Buffer Grad not found");
+        let kernel_data = buffers.get(&kernel.id).expect("Buffer Kernel not found");
+
+        let output_size = (input_size - kernel_size) / stride + 1;
+        let mut result_data = vec![0.0; input_size];
+        for o in 0..output_size {
+            let window = &mut result_data[o * stride..o * stride + kernel_size];
+            for (r, k) in window.iter_mut().zip(&kernel_data[..kernel_size]) {
+                *r += grad_data[o] * k;
+            }
+        }
+        buffers.insert(result.id, result_data);
+    }
+    fn conv1d_kernel_grad(
+        &self,
+        input: &BufferHandle,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel_size: usize,
+        stride: usize,
+    ) {
+        check_dtypes("conv1d_kernel_grad", &[input, grad, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let input_data = buffers.get(&input.id).expect("Buffer Input not found");
+        let grad_data = buffers.get(&grad.id).expect("Buffer Grad not found");
+
+        let output_size = (input_size - kernel_size) / stride + 1;
+        let result_data = (0..kernel_size)
+            .map(|j| {
+                (0..output_size)
+                    .map(|o| input_data[o * stride + j] * grad_data[o])
+                    .sum()
+            })
+            .collect();
+        buffers.insert(result.id, result_data);
+    }
+    fn gather(
+        &self,
+        table: &BufferHandle,
+        indices: &BufferHandle,
+        result: &BufferHandle,
+        rows: usize,
+        row_len: usize,
+        count: usize,
+    ) {
+        check_dtypes("gather", &[table, indices, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let table_data = buffers.get(&table.id).expect("Buffer Table not found");
+        let indices_data = buffers.get(&indices.id).expect("Buffer Indices not found");
+
+        let mut result_data = Vec::with_capacity(count * row_len);
+        for &index in &indices_data[..count] {
+            let row = row_index(index, rows);
+            result_data.extend_from_slice(&table_data[row * row_len..(row + 1) * row_len]);
+        }
+        buffers.insert(result.id, result_data);
+    }
+    fn gather_backward(
+        &self,
+        grad: &BufferHandle,
+        indices: &BufferHandle,
+        result: &BufferHandle,
+        rows: usize,
+        row_len: usize,
+        count: usize,
+    ) {
+        check_dtypes("gather_backward", &[grad, indices, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let grad_data = buffers.get(&grad.id).expect("Buffer Grad not found");
+        let indices_data = buffers.get(&indices.id).expect("Buffer Indices not found");
+
+        let mut result_data = vec![0.0; rows * row_len];
+        for (i, &index) in indices_data[..count].iter().enumerate() {
+            let row = row_index(index, rows);
+            for c in 0..row_len {
+                result_data[row * row_len + c] += grad_data[i * row_len + c];
+            }
+        }
+        buffers.insert(result.id, result_data);
+    }
+    fn scatter_add(
+        &self,
+        table: &BufferHandle,
+        indices: &BufferHandle,
+        updates: &BufferHandle,
+        result: &BufferHandle,
+        rows: usize,
+        row_len: usize,
+        count: usize,
+    ) {
+        check_dtypes("scatter_add", &[table, indices, updates, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let table_data = buffers.get(&table.id).expect("Buffer Table not found");
+        let indices_data = buffers.get(&indices.id).expect("Buffer Indices not found");
+        let updates_data = buffers.get(&updates.id).expect("Buffer Updates not found");
+
+        let mut result_data = table_data[..rows * row_len].to_vec();
+        for (i, &index) in indices_data[..count].iter().enumerate() {
+            let row = row_index(index, rows);
+            for c in 0..row_len {
+                result_data[row * row_len + c] += updates_data[i * row_len + c];
+            }
+        }
+        buffers.insert(result.id, result_data);
+    }
+    fn bias_activation(
+        &self,
+        a: &BufferHandle,
+        bias: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        row_len: usize,
+        activation: Activation,
+    ) {
+        check_dtypes("bias_activation", &[a, bias, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let a_data = &buffers.get(&a.id).expect("Buffer A not found")[..size];
+        let bias_data = &buffers.get(&bias.id).expect("Buffer Bias not found")[..row_len];
+
+        let result_data = a_data
+            .iter()
+            .enumerate()
+            .map(|(i, &a)| activate(a + bias_data[i % row_len], activation))
+            .collect();
+        buffers.insert(result.id, result_data);
+    }
+    fn activation_backward(
+        &self,
+        grad: &BufferHandle,
+        pre_activation: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        activation: Activation,
+    ) {
+        check_dtypes("activation_backward", &[grad, pre_activation, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let grad_data = &buffers.get(&grad.id).expect("Buffer Grad not found")[..size];
+        let pre_data = &buffers
+            .get(&pre_activation.id)
+            .expect("Buffer Pre-activation not found")[..size];
+
+        let result_data = grad_data
+            .iter()
+            .zip(pre_data)
+            .map(|(&grad, &x)| grad * activation_derivative(x, activation))
+            .collect();
+        buffers.insert(result.id, result_data);
+    }
+    fn log_softmax(&self, a: &BufferHandle, result: &BufferHandle, size: usize, row_len: usize) {
+        check_dtypes("log_softmax", &[a, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let a_data = &buffers.get(&a.id).expect("Buffer A not found")[..size];
+
+        let result_data = a_data
+            .chunks(row_len)
+            .flat_map(|row| {
+                let max = row.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+                let log_total = row.iter().map(|&x| (x - max).exp()).sum::<f32>().ln();
+                row.iter().map(move |&x| x - max - log_total)
+            })
+            .collect();
+        buffers.insert(result.id, result_data);
+    }
+    fn log_softmax_backward(
+        &self,
+        grad: &BufferHandle,
+        output: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        row_len: usize,
+    ) {
+        check_dtypes("log_softmax_backward", &[grad, output, result]);
+        let mut buffers = self.buffers.lock().unwrap();
+
+        let grad_data = &buffers.get(&grad.id).expect("Buffer Grad not found")[..size];
+        let output_data = &buffers.get(&output.id).expect("Buffer Output not found")[..size];
+
+        let result_data = grad_data
+            .chunks(row_len)
+            .zip(output_data.chunks(row_len))
+            .flat_map(|(grad_row, output_row)| {
+                let total: f32 = grad_row.iter().sum();
+                grad_row
+                    .iter()
+                    .zip(output_row)
+                    .map(move |(&g, &o)| g - o.exp() * total)
+            })
+            .collect();
+        buffers.insert(result.id, result_data);
     }
     fn name(&self) -> &str {
         &self.name
     }
+    fn instance_id(&self) -> usize {
+        self.instance_id
+    }
+    fn memory_budget(&self) -> Option<usize> {
+        None
+    }
 
     fn drop(&self) {
         let mut buffers = self.buffers.lock().unwrap();
         buffers.clear();
     }
 }
+
+// index of the first maximum, the Vulkan shaders break ties the same way
+fn argmax(window: &[f32]) -> usize {
+    let mut best = 0;
+    for (i, value) in window.iter().enumerate() {
+        if *value > window[best] {
+            best = i;
+        }
+    }
+    best
+}
+
+// the two source samples output i falls between and how far it is towards the second one
+fn interpolation_weights(i: usize, input_size: usize, output_size: usize) -> (usize, usize, f32) {
+    let position = if output_size > 1 {
+        i as f32 * (input_size - 1) as f32 / (output_size - 1) as f32
+    } else {
+        0.0
+    };
+    let lo = (position as usize).min(input_size - 1);
+    let hi = (lo + 1).min(input_size - 1);
+    (lo, hi, position - lo as f32)
+}
+
+// sqrt(2 / pi), the scale inside the tanh of the GELU approximation
+const GELU_SCALE: f32 = 0.797_884_6;
+const GELU_CUBIC: f32 = 0.044_715;
+
+fn activate(x: f32, activation: Activation) -> f32 {
+    match activation {
+        Activation::Relu => x.max(0.0),
+        Activation::Sigmoid => 1.0 / (1.0 + (-x).exp()),
+        Activation::Tanh => x.tanh(),
+        Activation::Gelu => 0.5 * x * (1.0 + (GELU_SCALE * (x + GELU_CUBIC * x * x * x)).tanh()),
+        Activation::None => x,
+    }
+}
+// derivative at the pre-activation value x
+fn activation_derivative(x: f32, activation: Activation) -> f32 {
+    match activation {
+        Activation::Relu => {
+            if x > 0.0 {
+                1.0
+            } else {
+                0.0
+            }
+        }
+        Activation::Sigmoid => {
+            let s = activate(x, Activation::Sigmoid);
+            s * (1.0 - s)
+        }
+        Activation::Tanh => 1.0 - x.tanh() * x.tanh(),
+        Activation::Gelu => {
+            let t = (GELU_SCALE * (x + GELU_CUBIC * x * x * x)).tanh();
+            0.5 * (1.0 + t)
+                + 0.5 * x * (1.0 - t * t) * GELU_SCALE * (1.0 + 3.0 * GELU_CUBIC * x * x)
+        }
+        Activation::None => 1.0,
+    }
+}
+
+// the table row a gather index stored as a float stands for
+fn row_index(index: f32, rows: usize) -> usize {
+    if index < 0.0 || index.fract() != 0.0 || index as usize >= rows {
+        panic!("gather index {} out of range for {} rows", index, rows);
+    }
+    index as usize
+}
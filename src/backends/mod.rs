@@ -1,5 +1,11 @@
+pub mod auto_backend;
 pub mod cpu_backend;
+#[cfg(feature = "test-utils")]
+pub mod tracking_backend;
 pub mod vulkan_backend;
 
+pub use auto_backend::AutoBackend;
 pub use cpu_backend::CPUBackend;
-pub use vulkan_backend::VulkanBackend;
\ No newline at end of file
+#[cfg(feature = "test-utils")]
+pub use tracking_backend::TrackingBackend;
+pub use vulkan_backend::VulkanBackend;
@@ -0,0 +1,440 @@
+use crate::lazybuffer::{Activation, Backend, BufferHandle, DivByZero, LazyBufferHandle, UnaryOp};
+use crate::random::Distribution;
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+// wraps another backend and keeps track of the device buffers allocated through it, so a test
+// can check that an op or feature frees what it allocates. a new op's test looks like
+//
+//     let backend = TrackingBackend::new(CPUBackend::new());
+//     let before = backend.snapshot();
+//     let mut out = x.new_op(..);
+//     out.realize(&backend);
+//     ... read out, free what the feature is supposed to free ...
+//     backend.assert_no_leaks_since(&before, &[out.buffer]);
+//
+// only buffers that go through the Backend trait are seen, staging buffers and the like that
+// a backend keeps internally are not
+pub struct TrackingBackend {
+    inner: Box<dyn Backend>,
+    live: Mutex<HashSet<LazyBufferHandle>>,
+    allocations: Mutex<usize>,
+    frees: Mutex<usize>,
+}
+
+impl TrackingBackend {
+    pub fn new(inner: impl Backend + 'static) -> Self {
+        TrackingBackend {
+            inner: Box::new(inner),
+            live: Mutex::new(HashSet::new()),
+            allocations: Mutex::new(0),
+            frees: Mutex::new(0),
+        }
+    }
+
+    // buffers allocated and not freed yet, temporary ones included
+    pub fn live_buffers(&self) -> usize {
+        self.live.lock().unwrap().len()
+    }
+    // allocate_buffer calls that created a buffer, plus allocate_temporary_buffer calls
+    pub fn allocations(&self) -> usize {
+        *self.allocations.lock().unwrap()
+    }
+    // free_buffer calls on a live buffer
+    pub fn frees(&self) -> usize {
+        *self.frees.lock().unwrap()
+    }
+
+    pub fn snapshot(&self) -> HashSet<LazyBufferHandle> {
+        self.live.lock().unwrap().clone()
+    }
+    // buffers alive now that weren't in the snapshot, in allocation order
+    pub fn leaked_since(&self, snapshot: &HashSet<LazyBufferHandle>) -> Vec<LazyBufferHandle> {
+        let mut leaked: Vec<LazyBufferHandle> = self
+            .live
+            .lock()
+            .unwrap()
+            .difference(snapshot)
+            .cloned()
+            .collect();
+        leaked.sort_by_key(|handle| handle.0);
+        leaked
+    }
+    // panics if anything besides the storage of the expected buffers, e.g. the realized outputs
+    // the test still reads, was allocated since the snapshot and is still alive. with reuse an
+    // output can hold storage that was allocated for another node, that counts as its own
+    pub fn assert_no_leaks_since(
+        &self,
+        snapshot: &HashSet<LazyBufferHandle>,
+        expected: &[LazyBufferHandle],
+    ) {
+        let held: HashSet<LazyBufferHandle> = expected
+            .iter()
+            .map(|handle| {
+                handle
+                    .get_device_handle()
+                    .map_or(*handle, |device| device.id)
+            })
+            .collect();
+        let leaked: Vec<LazyBufferHandle> = self
+            .leaked_since(snapshot)
+            .into_iter()
+            .filter(|handle| !held.contains(handle))
+            .collect();
+        if !leaked.is_empty() {
+            panic!(
+                "{} buffers allocated since the snapshot were never freed: {:?}",
+                leaked.len(),
+                leaked
+            );
+        }
+    }
+}
+
+impl Backend for TrackingBackend {
+    fn allocate_buffer(&self, lazy_buffer: LazyBufferHandle, size: usize) -> BufferHandle {
+        let handle = self.inner.allocate_buffer(lazy_buffer, size);
+        // an existing buffer is handed out again for a lazy buffer that already has one
+        if self.live.lock().unwrap().insert(handle.id) {
+            *self.allocations.lock().unwrap() += 1;
+        }
+        handle
+    }
+    fn allocate_temporary_buffer(&self, data: &[f32], size: usize) -> BufferHandle {
+        let handle = self.inner.allocate_temporary_buffer(data, size);
+        self.live.lock().unwrap().insert(handle.id);
+        *self.allocations.lock().unwrap() += 1;
+        handle
+    }
+    fn free_buffer(&self, handle: &BufferHandle) {
+        if self.live.lock().unwrap().remove(&handle.id) {
+            *self.frees.lock().unwrap() += 1;
+        }
+        self.inner.free_buffer(handle)
+    }
+    fn drop(&self) {
+        self.live.lock().unwrap().clear();
+        Backend::drop(self.inner.as_ref())
+    }
+    fn read_buffer(&self, handle: &BufferHandle) -> Vec<f32> {
+        self.inner.read_buffer(handle)
+    }
+    fn read_element(&self, handle: &BufferHandle, index: usize) -> f32 {
+        self.inner.read_element(handle, index)
+    }
+    fn to_device(&self, data: &[f32], handle: &BufferHandle) {
+        self.inner.to_device(data, handle)
+    }
+    fn to_host(&self, handle: &BufferHandle, size: usize) -> Vec<f32> {
+        self.inner.to_host(handle, size)
+    }
+    fn fill(&self, handle: &BufferHandle, value: f32, size: usize) {
+        self.inner.fill(handle, value, size)
+    }
+    fn upload_iter(
+        &self,
+        values: &mut dyn Iterator<Item = f32>,
+        handle: &BufferHandle,
+        size: usize,
+    ) {
+        self.inner.upload_iter(values, handle, size)
+    }
+    fn fill_random(
+        &self,
+        handle: &BufferHandle,
+        seed: u64,
+        distribution: Distribution,
+        size: usize,
+    ) -> bool {
+        self.inner.fill_random(handle, seed, distribution, size)
+    }
+    fn add(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize) {
+        self.inner.add(a, b, result, size)
+    }
+    fn subtract(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize) {
+        self.inner.subtract(a, b, result, size)
+    }
+    fn multiply(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize) {
+        self.inner.multiply(a, b, result, size)
+    }
+    fn divide(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize) {
+        self.inner.divide(a, b, result, size)
+    }
+    fn memset(&self, a: &BufferHandle, b: &BufferHandle, size: usize) {
+        self.inner.memset(a, b, size)
+    }
+    fn set_div_policy(&self, policy: DivByZero) {
+        self.inner.set_div_policy(policy)
+    }
+    fn cumsum(&self, a: &BufferHandle, result: &BufferHandle, size: usize, reverse: bool) {
+        self.inner.cumsum(a, result, size, reverse)
+    }
+    fn where_mask(
+        &self,
+        cond: &BufferHandle,
+        a: &BufferHandle,
+        b: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+    ) {
+        self.inner.where_mask(cond, a, b, result, size)
+    }
+    fn transpose(&self, a: &BufferHandle, result: &BufferHandle, rows: usize, cols: usize) {
+        self.inner.transpose(a, result, rows, cols)
+    }
+    fn triangle_mask(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        rows: usize,
+        cols: usize,
+        diagonal: i32,
+        upper: bool,
+    ) {
+        self.inner
+            .triangle_mask(a, result, rows, cols, diagonal, upper)
+    }
+    fn batch_transpose(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        batch: usize,
+        rows: usize,
+        cols: usize,
+    ) {
+        self.inner.batch_transpose(a, result, batch, rows, cols)
+    }
+    fn matmul(
+        &self,
+        a: &BufferHandle,
+        b: &BufferHandle,
+        result: &BufferHandle,
+        m: usize,
+        k: usize,
+        n: usize,
+    ) {
+        self.inner.matmul(a, b, result, m, k, n)
+    }
+    fn max_pool1d(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel: usize,
+        stride: usize,
+    ) {
+        self.inner.max_pool1d(a, result, input_size, kernel, stride)
+    }
+    fn max_pool1d_backward(
+        &self,
+        a: &BufferHandle,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel: usize,
+        stride: usize,
+    ) {
+        self.inner
+            .max_pool1d_backward(a, grad, result, input_size, kernel, stride)
+    }
+    fn interpolate_linear(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        output_size: usize,
+    ) {
+        self.inner
+            .interpolate_linear(a, result, input_size, output_size)
+    }
+    fn interpolate_linear_backward(
+        &self,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        output_size: usize,
+    ) {
+        self.inner
+            .interpolate_linear_backward(grad, result, input_size, output_size)
+    }
+    fn unary(&self, a: &BufferHandle, result: &BufferHandle, size: usize, op: UnaryOp) {
+        self.inner.unary(a, result, size, op)
+    }
+    fn affine(&self, a: &BufferHandle, result: &BufferHandle, size: usize, scale: f32, shift: f32) {
+        self.inner.affine(a, result, size, scale, shift)
+    }
+    fn clamp(&self, a: &BufferHandle, result: &BufferHandle, size: usize, min: f32, max: f32) {
+        self.inner.clamp(a, result, size, min, max)
+    }
+    fn sum(&self, a: &BufferHandle, result: &BufferHandle, size: usize) {
+        self.inner.sum(a, result, size)
+    }
+    fn expand(&self, a: &BufferHandle, result: &BufferHandle, size: usize) {
+        self.inner.expand(a, result, size)
+    }
+    fn repeat_interleave(&self, a: &BufferHandle, result: &BufferHandle, size: usize, n: usize) {
+        self.inner.repeat_interleave(a, result, size, n)
+    }
+    fn repeat_interleave_backward(
+        &self,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        n: usize,
+    ) {
+        self.inner.repeat_interleave_backward(grad, result, size, n)
+    }
+    fn broadcast_to(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        n: usize,
+        inner: usize,
+    ) {
+        self.inner.broadcast_to(a, result, size, n, inner)
+    }
+    fn broadcast_to_backward(
+        &self,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        n: usize,
+        inner: usize,
+    ) {
+        self.inner
+            .broadcast_to_backward(grad, result, size, n, inner)
+    }
+    fn pad(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        left: usize,
+        right: usize,
+    ) {
+        self.inner.pad(a, result, input_size, left, right)
+    }
+    fn slice(&self, a: &BufferHandle, result: &BufferHandle, start: usize, len: usize) {
+        self.inner.slice(a, result, start, len)
+    }
+    fn conv1d(
+        &self,
+        input: &BufferHandle,
+        kernel: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel_size: usize,
+        stride: usize,
+    ) {
+        self.inner
+            .conv1d(input, kernel, result, input_size, kernel_size, stride)
+    }
+    fn conv1d_input_grad(
+        &self,
+        grad: &BufferHandle,
+        kernel: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel_size: usize,
+        stride: usize,
+    ) {
+        self.inner
+            .conv1d_input_grad(grad, kernel, result, input_size, kernel_size, stride)
+    }
+    fn conv1d_kernel_grad(
+        &self,
+        input: &BufferHandle,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel_size: usize,
+        stride: usize,
+    ) {
+        self.inner
+            .conv1d_kernel_grad(input, grad, result, input_size, kernel_size, stride)
+    }
+    fn gather(
+        &self,
+        table: &BufferHandle,
+        indices: &BufferHandle,
+        result: &BufferHandle,
+        rows: usize,
+        row_len: usize,
+        count: usize,
+    ) {
+        self.inner
+            .gather(table, indices, result, rows, row_len, count)
+    }
+    fn gather_backward(
+        &self,
+        grad: &BufferHandle,
+        indices: &BufferHandle,
+        result: &BufferHandle,
+        rows: usize,
+        row_len: usize,
+        count: usize,
+    ) {
+        self.inner
+            .gather_backward(grad, indices, result, rows, row_len, count)
+    }
+    fn scatter_add(
+        &self,
+        table: &BufferHandle,
+        indices: &BufferHandle,
+        updates: &BufferHandle,
+        result: &BufferHandle,
+        rows: usize,
+        row_len: usize,
+        count: usize,
+    ) {
+        self.inner
+            .scatter_add(table, indices, updates, result, rows, row_len, count)
+    }
+    fn bias_activation(
+        &self,
+        a: &BufferHandle,
+        bias: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        row_len: usize,
+        activation: Activation,
+    ) {
+        self.inner
+            .bias_activation(a, bias, result, size, row_len, activation)
+    }
+    fn activation_backward(
+        &self,
+        grad: &BufferHandle,
+        pre_activation: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        activation: Activation,
+    ) {
+        self.inner
+            .activation_backward(grad, pre_activation, result, size, activation)
+    }
+    fn log_softmax(&self, a: &BufferHandle, result: &BufferHandle, size: usize, row_len: usize) {
+        self.inner.log_softmax(a, result, size, row_len)
+    }
+    fn log_softmax_backward(
+        &self,
+        grad: &BufferHandle,
+        output: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        row_len: usize,
+    ) {
+        self.inner
+            .log_softmax_backward(grad, output, result, size, row_len)
+    }
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+    fn instance_id(&self) -> usize {
+        self.inner.instance_id()
+    }
+    fn memory_budget(&self) -> Option<usize> {
+        self.inner.memory_budget()
+    }
+}
@@ -1,42 +1,177 @@
 use ash::vk;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::mem::size_of;
 use std::sync::Mutex;
+use std::time::Duration;
 
-use crate::lazybuffer::{Backend, BufferHandle, LAZYBUFFER_HANDLE_NULL, LazyBufferHandle};
-use crate::vulkan::{Buffer, VulkanBackend as VulkanCore};
+use crate::backends::CPUBackend;
+use crate::error::FlameError;
+use crate::lazybuffer::{
+    Activation, Backend, BufferHandle, DType, DivByZero, LazyBufferHandle, UnaryOp, check_dtypes,
+    get_next_backend_instance_id, get_next_temporary_id,
+};
+use crate::random::{Distribution, stream_key};
+use crate::vulkan::{Buffer, DeviceInfo, MemoryPreference, VulkanBackend as VulkanCore};
+
+// op_type values understood by the shared elementwise shader
+const OP_ADD: u32 = 0;
+const OP_SUBTRACT: u32 = 1;
+const OP_MULTIPLY: u32 = 2;
+const OP_DIVIDE: u32 = 3;
+
+// op_type values understood by the unary shader
+const OP_ABS: u32 = 0;
+const OP_SQRT: u32 = 1;
+const OP_SIGN: u32 = 2;
+const OP_NEG: u32 = 3;
+const OP_COPY: u32 = 4;
+const OP_SIN: u32 = 5;
+const OP_COS: u32 = 6;
+const OP_ROUND: u32 = 7;
+const OP_FLOOR: u32 = 8;
+const OP_CEIL: u32 = 9;
+
+// activation values understood by the bias_activation and activation_backward shaders
+fn activation_type(activation: Activation) -> u32 {
+    match activation {
+        Activation::Relu => 0,
+        Activation::Sigmoid => 1,
+        Activation::Tanh => 2,
+        Activation::Gelu => 3,
+        Activation::None => 4,
+    }
+}
+
+// rows of A / columns of B walked per pass of the tiled matmul, independent of the tile shape
+const MATMUL_TILE_K: u32 = 16;
 
 pub struct VulkanBackend {
     name: String,
+    instance_id: usize,
     vulkan: std::rc::Rc<VulkanCore>,
     buffers: Mutex<HashMap<LazyBufferHandle, Buffer>>,
     operation_type: Mutex<HashMap<vk::Pipeline, String>>,
     pipelines: Mutex<HashMap<String, vk::Pipeline>>,
+    memory_budget: Option<usize>,
+    // workgroup shape of the matmul shader, rows x cols of the result per workgroup
+    matmul_tile: Mutex<(u32, u32)>,
+    // runs the ops in unported, see allow_cpu_fallback
+    cpu_fallback: Option<CPUBackend>,
+    unported: HashSet<String>,
+    fallback_warned: Mutex<HashSet<String>>,
+    div_policy: Mutex<DivByZero>,
 }
 
 impl VulkanBackend {
     pub fn new(app_name: &str) -> Self {
-        let vulkan = std::rc::Rc::new(VulkanCore::new(app_name));
+        Self::with_memory_preference(app_name, MemoryPreference::DeviceLocal)
+    }
+
+    pub fn with_memory_preference(app_name: &str, memory_preference: MemoryPreference) -> Self {
+        let vulkan = std::rc::Rc::new(VulkanCore::with_memory_preference(
+            app_name,
+            memory_preference,
+        ));
         let op_type = HashMap::new();
         let pipelines = HashMap::new();
         VulkanBackend {
             name: "Vulkan".to_string(),
+            instance_id: get_next_backend_instance_id(),
             vulkan,
             buffers: Mutex::new(HashMap::new()),
             operation_type: Mutex::new(op_type),
             pipelines: Mutex::new(pipelines),
+            memory_budget: None,
+            matmul_tile: Mutex::new((16, 16)),
+            cpu_fallback: None,
+            unported: HashSet::new(),
+            fallback_warned: Mutex::new(HashSet::new()),
+            div_policy: Mutex::new(DivByZero::default()),
+        }
+    }
+
+    // caps how much device memory Tensor::chunk_realize uses per tile
+    pub fn with_memory_budget(mut self, bytes: usize) -> Self {
+        self.memory_budget = Some(bytes);
+        self
+    }
+
+    // during development: ops that aren't ported yet download their operands, run on the CPU
+    // backend and upload the result instead of panicking. the first fallback of each op prints a
+    // warning to stderr
+    pub fn allow_cpu_fallback(mut self, allow: bool) -> Self {
+        self.cpu_fallback = if allow {
+            let cpu = CPUBackend::new();
+            cpu.set_div_policy(*self.div_policy.lock().unwrap());
+            Some(cpu)
+        } else {
+            None
+        };
+        self
+    }
+
+    // ops whose Vulkan path is missing or broken, by the name passed to fallback_to_cpu
+    // ("elementwise", "matmul", "fill", ...). they panic unless allow_cpu_fallback is set
+    pub fn with_unported_ops(mut self, operations: &[&str]) -> Self {
+        self.unported
+            .extend(operations.iter().map(|operation| operation.to_string()));
+        self
+    }
+
+    // workgroup width of the flat shaders, execute_compute_with_pipeline divides by the same
+    // value. has to be a power of two for the sum reduction, 256 by default
+    pub fn with_local_size(self, local_size: u32) -> Self {
+        if !local_size.is_power_of_two()
+            || local_size > self.vulkan.max_compute_work_group_size[0]
+            || local_size > self.vulkan.max_compute_work_group_invocations
+        {
+            panic!(
+                "local size {} has to be a power of two within the device's workgroup limits",
+                local_size
+            );
         }
+        if local_size != self.local_size() {
+            // pipelines compiled for the old size would keep running with it while dispatch
+            // already divides by the new one, the tiled matmul shaders don't use it
+            let mut pipelines = self.pipelines.lock().unwrap();
+            let mut op_types = self.operation_type.lock().unwrap();
+            pipelines.retain(|operation, pipeline| {
+                if Self::shader_source(operation).is_none() {
+                    return true;
+                }
+                op_types.remove(pipeline);
+                unsafe { self.vulkan.device.destroy_pipeline(*pipeline, None) };
+                false
+            });
+        }
+        self.vulkan.local_size.set(local_size);
+        self
+    }
+    pub fn local_size(&self) -> u32 {
+        self.vulkan.local_size.get()
     }
 
     pub fn compile_shader_for_operation(&self, operation: &str) {
+        let shader_src = Self::shader_source(operation)
+            .unwrap_or_else(|| panic!("Unknown operation: {}", operation));
+        let shader_src = shader_src.replacen(
+            "#version 450",
+            &format!("#version 450\n#define LOCAL_SIZE {}", self.local_size()),
+            1,
+        );
+        self.insert_pipeline(operation, &shader_src);
+    }
+    fn shader_source(operation: &str) -> Option<&'static str> {
         let shader_src = match operation {
-            "add" => {
+            "elementwise" => {
                 r#"
                 #version 450
-                layout(local_size_x = 256) in;
+                layout(local_size_x = LOCAL_SIZE) in;
                 
                 layout(push_constant) uniform PushConstants {
                     uint size;
+                    uint op_type;
+                    uint div_policy; // what x / 0 gives: 0 nan, 1 inf, 2 zero
                 } push_constants;
                 
                 layout(set = 0, binding = 0) buffer TensorA {
@@ -54,413 +189,2633 @@ impl VulkanBackend {
                 void main() {
                     uint idx = gl_GlobalInvocationID.x;
                     if (idx < push_constants.size) {
-                        tensorResult.data[idx] = tensorA.data[idx] + tensorB.data[idx];
+                        float a = tensorA.data[idx];
+                        float b = tensorB.data[idx];
+                        switch (push_constants.op_type) {
+                            case 0: tensorResult.data[idx] = a + b; break;
+                            case 1: tensorResult.data[idx] = a - b; break;
+                            case 2: tensorResult.data[idx] = a * b; break;
+                            case 3:
+                                if (b == 0.0 && push_constants.div_policy == 0) {
+                                    tensorResult.data[idx] = uintBitsToFloat(0x7fc00000u);
+                                } else if (b == 0.0 && push_constants.div_policy == 2) {
+                                    tensorResult.data[idx] = 0.0;
+                                } else {
+                                    tensorResult.data[idx] = a / b;
+                                }
+                                break;
+                        }
                     }
                 }
             "#
             }
-            "subtract" => {
+            "cumsum" => {
                 r#"
                 #version 450
-                layout(local_size_x = 256) in;
-                
+                layout(local_size_x = LOCAL_SIZE) in;
+
                 layout(push_constant) uniform PushConstants {
                     uint size;
+                    uint offset;
+                    uint reverse;
                 } push_constants;
-                
+
                 layout(set = 0, binding = 0) buffer TensorA {
                     float data[];
                 } tensorA;
-                
+
                 layout(set = 0, binding = 1) buffer TensorB {
                     float data[];
                 } tensorB;
-                
+
                 layout(set = 0, binding = 2) buffer TensorResult {
                     float data[];
                 } tensorResult;
-                
+
+                // one Hillis-Steele step: every element adds the one `offset` positions behind it
                 void main() {
                     uint idx = gl_GlobalInvocationID.x;
                     if (idx < push_constants.size) {
-                        tensorResult.data[idx] = tensorA.data[idx] - tensorB.data[idx];
+                        float value = tensorA.data[idx];
+                        if (push_constants.reverse == 0) {
+                            if (idx >= push_constants.offset) {
+                                value += tensorA.data[idx - push_constants.offset];
+                            }
+                        } else if (idx + push_constants.offset < push_constants.size) {
+                            value += tensorA.data[idx + push_constants.offset];
+                        }
+                        tensorResult.data[idx] = value;
                     }
                 }
             "#
             }
-            "multiply" => {
+            "where" => {
                 r#"
                 #version 450
-                layout(local_size_x = 256) in;
-                
+                layout(local_size_x = LOCAL_SIZE) in;
+
                 layout(push_constant) uniform PushConstants {
                     uint size;
                 } push_constants;
-                
+
+                layout(set = 0, binding = 0) buffer TensorCond {
+                    float data[];
+                } tensorCond;
+
+                layout(set = 0, binding = 1) buffer TensorA {
+                    float data[];
+                } tensorA;
+
+                // already holds the else branch
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
+
+                void main() {
+                    uint idx = gl_GlobalInvocationID.x;
+                    if (idx < push_constants.size && tensorCond.data[idx] != 0.0) {
+                        tensorResult.data[idx] = tensorA.data[idx];
+                    }
+                }
+            "#
+            }
+            "transpose" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
+
+                layout(push_constant) uniform PushConstants {
+                    uint size;
+                    uint rows;
+                    uint cols;
+                } push_constants;
+
                 layout(set = 0, binding = 0) buffer TensorA {
                     float data[];
                 } tensorA;
-                
+
                 layout(set = 0, binding = 1) buffer TensorB {
                     float data[];
                 } tensorB;
-                
+
                 layout(set = 0, binding = 2) buffer TensorResult {
                     float data[];
                 } tensorResult;
-                
+
+                // result is cols x rows, one invocation per result element
                 void main() {
                     uint idx = gl_GlobalInvocationID.x;
                     if (idx < push_constants.size) {
-                        tensorResult.data[idx] = tensorA.data[idx] * tensorB.data[idx];
+                        uint row = idx / push_constants.rows;
+                        uint col = idx % push_constants.rows;
+                        tensorResult.data[idx] = tensorA.data[col * push_constants.cols + row];
                     }
                 }
             "#
             }
-            "divide" => {
+            "triangle_mask" => {
                 r#"
                 #version 450
-                layout(local_size_x = 256) in;
-                
+                layout(local_size_x = LOCAL_SIZE) in;
+
                 layout(push_constant) uniform PushConstants {
                     uint size;
+                    uint cols;
+                    int diagonal;
+                    uint upper;
                 } push_constants;
-                
+
                 layout(set = 0, binding = 0) buffer TensorA {
                     float data[];
                 } tensorA;
-                
+
                 layout(set = 0, binding = 1) buffer TensorB {
                     float data[];
                 } tensorB;
-                
+
                 layout(set = 0, binding = 2) buffer TensorResult {
                     float data[];
                 } tensorResult;
-                
+
+                // one invocation per element, kept or zeroed by its distance from the diagonal
                 void main() {
                     uint idx = gl_GlobalInvocationID.x;
                     if (idx < push_constants.size) {
-                        tensorResult.data[idx] = tensorA.data[idx] / tensorB.data[idx];
+                        int offset = int(idx % push_constants.cols) - int(idx / push_constants.cols);
+                        bool keep = push_constants.upper == 1u
+                            ? offset >= push_constants.diagonal
+                            : offset <= push_constants.diagonal;
+                        tensorResult.data[idx] = keep ? tensorA.data[idx] : 0.0;
                     }
                 }
             "#
             }
-            "memset" => {
+            "batch_transpose" => {
                 r#"
                 #version 450
-                layout(local_size_x = 256) in;
-                        
+                layout(local_size_x = LOCAL_SIZE) in;
+
                 layout(push_constant) uniform PushConstants {
                     uint size;
+                    uint rows;
+                    uint cols;
                 } push_constants;
-                        
+
                 layout(set = 0, binding = 0) buffer TensorA {
                     float data[];
                 } tensorA;
-                        
+
                 layout(set = 0, binding = 1) buffer TensorB {
                     float data[];
                 } tensorB;
-                        
+
                 layout(set = 0, binding = 2) buffer TensorResult {
                     float data[];
                 } tensorResult;
-                        
+
+                // result is batch x cols x rows, one invocation per result element
                 void main() {
                     uint idx = gl_GlobalInvocationID.x;
                     if (idx < push_constants.size) {
-                        tensorA.data[idx] = tensorB.data[idx];
+                        uint matrix = push_constants.rows * push_constants.cols;
+                        uint offset = (idx / matrix) * matrix;
+                        uint row = (idx % matrix) / push_constants.rows;
+                        uint col = (idx % matrix) % push_constants.rows;
+                        tensorResult.data[idx] =
+                            tensorA.data[offset + col * push_constants.cols + row];
                     }
                 }
-                "#
+            "#
             }
-            _ => panic!("Unknown operation: {}", operation),
-        };
-        let pipeline = self.vulkan.create_pipeline_for_shader(shader_src);
-        let mut pipelines = self.pipelines.lock().unwrap();
-        let mut op_types = self.operation_type.lock().unwrap();
-        pipelines.insert(operation.to_string(), pipeline);
-        op_types.insert(pipeline, operation.to_string());
-    }
-}
-
-impl Backend for VulkanBackend {
-    fn allocate_buffer(&self, lazy_buffer: LazyBufferHandle, size: usize) -> BufferHandle {
-        if let Some(buffer) = self.buffers.lock().unwrap().get(&lazy_buffer) {
-            return BufferHandle {
-                id: lazy_buffer,
-                size: buffer.size as usize,
-            };
-        }
-        println!("Allocating buffer with ID {:?}", lazy_buffer);
-        let buffer_size = (size * size_of::<f32>()) as u64;
-        let buffer = self.vulkan.create_gpu_buffer(buffer_size);
-
-        let handle = BufferHandle {
-            id: lazy_buffer,
-            size,
-        };
+            "max_pool1d" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
 
-        self.buffers.lock().unwrap().insert(handle.id, buffer);
-        handle
-    }
-    fn allocate_temporary_buffer(&self, data: &[f32], size: usize) -> BufferHandle {
-        let buffer_size = (size * size_of::<f32>()) as u64;
-        let buffer = self.vulkan.create_gpu_buffer(buffer_size);
-        let handle = BufferHandle {
-            id: LAZYBUFFER_HANDLE_NULL,
-            size,
-        };
-        self.buffers.lock().unwrap().insert(handle.id, buffer);
-        self.to_device(data, &handle);
-        handle
-    }
-    fn read_buffer(&self, handle: &BufferHandle) -> Vec<f32> {
-        let buffers = self.buffers.lock().unwrap();
-        if let Some(buffer) = buffers.get(&handle.id) {
-            let buffer_size = (handle.size * size_of::<f32>()) as u64;
-            let staging_buffer = self.vulkan.create_staging_buffer(buffer_size);
+                layout(push_constant) uniform PushConstants {
+                    uint size;
+                    uint kernel;
+                    uint stride;
+                } push_constants;
 
-            let fence = self
-                .vulkan
-                .copy_buffer(buffer, &staging_buffer, buffer_size);
-            self.vulkan.wait_for_fence(fence);
+                layout(set = 0, binding = 0) buffer TensorA {
+                    float data[];
+                } tensorA;
 
-            let result = self.vulkan.read_buffer::<f32>(&staging_buffer, handle.size);
+                layout(set = 0, binding = 1) buffer TensorB {
+                    float data[];
+                } tensorB;
 
-            unsafe {
-                self.vulkan
-                    .device
-                    .destroy_buffer(staging_buffer.buffer, None);
-                self.vulkan.device.free_memory(staging_buffer.memory, None);
-            }
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
 
-            result
-        } else {
-            panic!("Buffer with ID {:?} not found", handle.id);
-        }
-    }
-    fn free_buffer(&self, handle: &BufferHandle) {
-        let mut buffers = self.buffers.lock().unwrap();
-        if let Some(buffer) = buffers.remove(&handle.id) {
-            unsafe {
-                self.vulkan.device.destroy_buffer(buffer.buffer, None);
-                self.vulkan.device.free_memory(buffer.memory, None);
+                // one invocation per window
+                void main() {
+                    uint idx = gl_GlobalInvocationID.x;
+                    if (idx < push_constants.size) {
+                        uint start = idx * push_constants.stride;
+                        float best = tensorA.data[start];
+                        for (uint i = 1; i < push_constants.kernel; i++) {
+                            best = max(best, tensorA.data[start + i]);
+                        }
+                        tensorResult.data[idx] = best;
+                    }
+                }
+            "#
             }
-        }
-    }
+            "max_pool1d_backward" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
 
-    fn to_device(&self, data: &[f32], handle: &BufferHandle) {
-        let buffers = self.buffers.lock().unwrap();
-        if let Some(buffer) = buffers.get(&handle.id) {
-            let staging_buffer = self
-                .vulkan
-                .create_staging_buffer((data.len() * size_of::<f32>()) as u64);
-            self.vulkan.upload_to_buffer(data, &staging_buffer);
-            let fence = self.vulkan.copy_buffer(
-                &staging_buffer,
-                buffer,
-                (data.len() * size_of::<f32>()) as u64,
-            );
-            self.vulkan.wait_for_fence(fence);
-            unsafe {
-                self.vulkan
-                    .device
-                    .destroy_buffer(staging_buffer.buffer, None);
-                self.vulkan.device.free_memory(staging_buffer.memory, None);
-            }
-        }
-    }
+                layout(push_constant) uniform PushConstants {
+                    uint size;
+                    uint kernel;
+                    uint stride;
+                    uint output_size;
+                } push_constants;
 
-    fn to_host(&self, handle: &BufferHandle, size: usize) -> Vec<f32> {
-        let buffers = self.buffers.lock().unwrap();
-        if let Some(buffer) = buffers.get(&handle.id) {
-            let buffer_size = (size * size_of::<f32>()) as u64;
-            let staging_buffer = self.vulkan.create_staging_buffer(buffer_size);
+                layout(set = 0, binding = 0) buffer TensorA {
+                    float data[];
+                } tensorA;
 
-            let fence = self
-                .vulkan
-                .copy_buffer(buffer, &staging_buffer, buffer_size);
-            self.vulkan.wait_for_fence(fence);
+                layout(set = 0, binding = 1) buffer TensorGrad {
+                    float data[];
+                } tensorGrad;
 
-            let result = self.vulkan.read_buffer::<f32>(&staging_buffer, size);
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
 
-            unsafe {
-                self.vulkan
-                    .device
-                    .destroy_buffer(staging_buffer.buffer, None);
-                self.vulkan.device.free_memory(staging_buffer.memory, None);
+                // one invocation per input element, gathering from every window that covers it
+                // and picked it as its first maximum, so no two invocations write the same slot
+                void main() {
+                    uint idx = gl_GlobalInvocationID.x;
+                    if (idx < push_constants.size) {
+                        uint kernel = push_constants.kernel;
+                        uint stride = push_constants.stride;
+                        uint first = idx + 1 > kernel ? (idx + 1 - kernel + stride - 1) / stride : 0;
+                        uint last = min(idx / stride, push_constants.output_size - 1);
+                        float sum = 0.0;
+                        for (uint window = first; window <= last; window++) {
+                            uint start = window * stride;
+                            uint best = start;
+                            for (uint i = start + 1; i < start + kernel; i++) {
+                                if (tensorA.data[i] > tensorA.data[best]) {
+                                    best = i;
+                                }
+                            }
+                            if (best == idx) {
+                                sum += tensorGrad.data[window];
+                            }
+                        }
+                        tensorResult.data[idx] = sum;
+                    }
+                }
+            "#
             }
+            "conv1d" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
 
-            result
-        } else {
-            panic!("Buffer with ID {:?} not found", handle.id);
-        }
-    }
+                layout(push_constant) uniform PushConstants {
+                    uint size;
+                    uint input_size;
+                    uint kernel_size;
+                    uint stride;
+                } push_constants;
 
-    fn add(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize) {
-        let buffers = self.buffers.lock().unwrap();
-        if let (Some(buffer_a), Some(buffer_b), Some(result_buffer)) = (
-            buffers.get(&a.id),
-            buffers.get(&b.id),
-            buffers.get(&result.id),
-        ) {
-            {
-                let pipelines = self.pipelines.lock().unwrap();
-                if !pipelines.contains_key("add") {
-                    drop(pipelines);
-                    self.compile_shader_for_operation("add");
+                layout(set = 0, binding = 0) buffer TensorA {
+                    float data[];
+                } tensorA;
+
+                layout(set = 0, binding = 1) buffer TensorB {
+                    float data[];
+                } tensorB;
+
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
+
+                // one invocation per output element, a dot product of its window with the kernel
+                void main() {
+                    uint idx = gl_GlobalInvocationID.x;
+                    if (idx < push_constants.size) {
+                        uint start = idx * push_constants.stride;
+                        float sum = 0.0;
+                        for (uint j = 0; j < push_constants.kernel_size; j++) {
+                            sum += tensorA.data[start + j] * tensorB.data[j];
+                        }
+                        tensorResult.data[idx] = sum;
+                    }
                 }
+            "#
             }
+            "conv1d_input_grad" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
 
-            let pipeline = {
-                let pipelines = self.pipelines.lock().unwrap();
-                *pipelines.get("add").unwrap()
+                layout(push_constant) uniform PushConstants {
+                    uint size;
+                    uint kernel_size;
+                    uint stride;
+                    uint output_size;
+                } push_constants;
+
+                layout(set = 0, binding = 0) buffer TensorA {
+                    float data[];
+                } tensorA;
+
+                layout(set = 0, binding = 1) buffer TensorB {
+                    float data[];
+                } tensorB;
+
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
+
+                // one invocation per input element, gathering from every window that covers it
+                void main() {
+                    uint idx = gl_GlobalInvocationID.x;
+                    if (idx < push_constants.size) {
+                        uint kernel_size = push_constants.kernel_size;
+                        uint stride = push_constants.stride;
+                        uint first = idx + 1 > kernel_size ? (idx + 1 - kernel_size + stride - 1) / stride : 0;
+                        uint last = min(idx / stride, push_constants.output_size - 1);
+                        float sum = 0.0;
+                        for (uint window = first; window <= last; window++) {
+                            sum += tensorA.data[window] * tensorB.data[idx - window * stride];
+                        }
+                        tensorResult.data[idx] = sum;
+                    }
+                }
+            "#
+            }
+            "conv1d_kernel_grad" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
+
+                layout(push_constant) uniform PushConstants {
+                    uint size;
+                    uint stride;
+                    uint output_size;
+                    uint unused;
+                } push_constants;
+
+                layout(set = 0, binding = 0) buffer TensorA {
+                    float data[];
+                } tensorA;
+
+                layout(set = 0, binding = 1) buffer TensorB {
+                    float data[];
+                } tensorB;
+
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
+
+                // one invocation per kernel element, summing over all windows
+                void main() {
+                    uint idx = gl_GlobalInvocationID.x;
+                    if (idx < push_constants.size) {
+                        float sum = 0.0;
+                        for (uint window = 0; window < push_constants.output_size; window++) {
+                            sum += tensorA.data[window * push_constants.stride + idx] * tensorB.data[window];
+                        }
+                        tensorResult.data[idx] = sum;
+                    }
+                }
+            "#
+            }
+            "gather" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
+
+                layout(push_constant) uniform PushConstants {
+                    uint size;
+                    uint rows;
+                    uint row_len;
+                } push_constants;
+
+                layout(set = 0, binding = 0) buffer TensorA {
+                    float data[];
+                } tensorA;
+
+                layout(set = 0, binding = 1) buffer TensorB {
+                    float data[];
+                } tensorB;
+
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
+
+                // one invocation per output element, A is the table and B holds the indices
+                void main() {
+                    uint idx = gl_GlobalInvocationID.x;
+                    if (idx < push_constants.size) {
+                        float index = tensorB.data[idx / push_constants.row_len];
+                        uint col = idx % push_constants.row_len;
+                        if (index >= 0.0 && index < float(push_constants.rows)) {
+                            uint row = uint(index);
+                            tensorResult.data[idx] =
+                                tensorA.data[row * push_constants.row_len + col];
+                        } else {
+                            tensorResult.data[idx] = 0.0;
+                        }
+                    }
+                }
+            "#
+            }
+            "gather_backward" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
+
+                layout(push_constant) uniform PushConstants {
+                    uint size;
+                    uint row_len;
+                    uint count;
+                } push_constants;
+
+                layout(set = 0, binding = 0) buffer TensorA {
+                    float data[];
+                } tensorA;
+
+                layout(set = 0, binding = 1) buffer TensorB {
+                    float data[];
+                } tensorB;
+
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
+
+                // one invocation per table element, summing the gradient rows of every index
+                // that picked its row, so repeated indices need no atomics
+                void main() {
+                    uint idx = gl_GlobalInvocationID.x;
+                    if (idx < push_constants.size) {
+                        float row = float(idx / push_constants.row_len);
+                        uint col = idx % push_constants.row_len;
+                        float sum = 0.0;
+                        for (uint i = 0; i < push_constants.count; i++) {
+                            if (tensorB.data[i] == row) {
+                                sum += tensorA.data[i * push_constants.row_len + col];
+                            }
+                        }
+                        tensorResult.data[idx] = sum;
+                    }
+                }
+            "#
+            }
+            "scatter_add" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
+
+                layout(push_constant) uniform PushConstants {
+                    uint size;
+                    uint rows;
+                    uint row_len;
+                } push_constants;
+
+                layout(set = 0, binding = 0) buffer TensorA {
+                    float data[];
+                } tensorA;
+
+                layout(set = 0, binding = 1) buffer TensorB {
+                    float data[];
+                } tensorB;
+
+                // already holds the table, viewed as bits for the atomics
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    uint data[];
+                } tensorResult;
+
+                // one invocation per update element, A holds the updates and B the indices.
+                // invocations for repeated indices write the same element, so the add is an
+                // atomic compare-and-swap loop. float atomicAdd needs an extension and a device
+                // feature that not every GPU has
+                void main() {
+                    uint idx = gl_GlobalInvocationID.x;
+                    if (idx < push_constants.size) {
+                        float index = tensorB.data[idx / push_constants.row_len];
+                        if (index >= 0.0 && index < float(push_constants.rows)) {
+                            uint target = uint(index) * push_constants.row_len
+                                + idx % push_constants.row_len;
+                            float value = tensorA.data[idx];
+                            uint expected = tensorResult.data[target];
+                            while (true) {
+                                uint desired = floatBitsToUint(uintBitsToFloat(expected) + value);
+                                uint actual =
+                                    atomicCompSwap(tensorResult.data[target], expected, desired);
+                                if (actual == expected) {
+                                    break;
+                                }
+                                expected = actual;
+                            }
+                        }
+                    }
+                }
+            "#
+            }
+            "bias_activation" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
+
+                layout(push_constant) uniform PushConstants {
+                    uint size;
+                    uint row_len;
+                    uint activation;
+                } push_constants;
+
+                layout(set = 0, binding = 0) buffer TensorA {
+                    float data[];
+                } tensorA;
+
+                layout(set = 0, binding = 1) buffer TensorB {
+                    float data[];
+                } tensorB;
+
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
+
+                // one invocation per element, A is the input and B the bias row. the
+                // activations match the CPU backend, gelu is the tanh approximation
+                void main() {
+                    uint idx = gl_GlobalInvocationID.x;
+                    if (idx < push_constants.size) {
+                        float x = tensorA.data[idx] + tensorB.data[idx % push_constants.row_len];
+                        switch (push_constants.activation) {
+                            case 0: x = max(x, 0.0); break;
+                            case 1: x = 1.0 / (1.0 + exp(-x)); break;
+                            case 2: x = tanh(x); break;
+                            case 3:
+                                x = 0.5 * x * (1.0 + tanh(0.7978846 * (x + 0.044715 * x * x * x)));
+                                break;
+                        }
+                        tensorResult.data[idx] = x;
+                    }
+                }
+            "#
+            }
+            "activation_backward" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
+
+                layout(push_constant) uniform PushConstants {
+                    uint size;
+                    uint activation;
+                } push_constants;
+
+                layout(set = 0, binding = 0) buffer TensorA {
+                    float data[];
+                } tensorA;
+
+                layout(set = 0, binding = 1) buffer TensorB {
+                    float data[];
+                } tensorB;
+
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
+
+                // one invocation per element, A is the output gradient and B the values before
+                // the activation
+                void main() {
+                    uint idx = gl_GlobalInvocationID.x;
+                    if (idx < push_constants.size) {
+                        float x = tensorB.data[idx];
+                        float derivative = 1.0;
+                        switch (push_constants.activation) {
+                            case 0: derivative = x > 0.0 ? 1.0 : 0.0; break;
+                            case 1: {
+                                float s = 1.0 / (1.0 + exp(-x));
+                                derivative = s * (1.0 - s);
+                                break;
+                            }
+                            case 2: derivative = 1.0 - tanh(x) * tanh(x); break;
+                            case 3: {
+                                float t = tanh(0.7978846 * (x + 0.044715 * x * x * x));
+                                derivative = 0.5 * (1.0 + t)
+                                    + 0.5 * x * (1.0 - t * t) * 0.7978846
+                                        * (1.0 + 3.0 * 0.044715 * x * x);
+                                break;
+                            }
+                        }
+                        tensorResult.data[idx] = tensorA.data[idx] * derivative;
+                    }
+                }
+            "#
+            }
+            "fill_random" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
+
+                layout(push_constant) uniform PushConstants {
+                    uint size;
+                    uint key;
+                    uint uniform_only;
+                } push_constants;
+
+                layout(set = 0, binding = 0) buffer TensorA {
+                    float data[];
+                } tensorA;
+
+                layout(set = 0, binding = 1) buffer TensorB {
+                    float data[];
+                } tensorB;
+
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
+
+                // same hash and counters as random::sample
+                uint pcg_hash(uint value) {
+                    uint state = value * 747796405u + 2891336453u;
+                    uint word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+                    return (word >> 22u) ^ word;
+                }
+
+                float counter_uniform(uint counter) {
+                    return float(pcg_hash(pcg_hash(counter) ^ push_constants.key) >> 8u)
+                        / 16777216.0;
+                }
+
+                void main() {
+                    uint idx = gl_GlobalInvocationID.x;
+                    if (idx < push_constants.size) {
+                        if (push_constants.uniform_only == 1u) {
+                            tensorResult.data[idx] = counter_uniform(idx);
+                        } else {
+                            float u1 = 1.0 - counter_uniform(idx * 2u);
+                            float u2 = counter_uniform(idx * 2u + 1u);
+                            tensorResult.data[idx] =
+                                sqrt(-2.0 * log(u1)) * cos(6.28318530718 * u2);
+                        }
+                    }
+                }
+            "#
+            }
+            "log_softmax" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
+
+                layout(push_constant) uniform PushConstants {
+                    uint rows;
+                    uint row_len;
+                } push_constants;
+
+                layout(set = 0, binding = 0) buffer TensorA {
+                    float data[];
+                } tensorA;
+
+                layout(set = 0, binding = 1) buffer TensorB {
+                    float data[];
+                } tensorB;
+
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
+
+                // one invocation per row, exp only ever sees values <= 0 after the max shift
+                void main() {
+                    uint row = gl_GlobalInvocationID.x;
+                    if (row < push_constants.rows) {
+                        uint start = row * push_constants.row_len;
+                        uint end = start + push_constants.row_len;
+                        float row_max = tensorA.data[start];
+                        for (uint i = start + 1; i < end; i++) {
+                            row_max = max(row_max, tensorA.data[i]);
+                        }
+                        float total = 0.0;
+                        for (uint i = start; i < end; i++) {
+                            total += exp(tensorA.data[i] - row_max);
+                        }
+                        float log_total = log(total);
+                        for (uint i = start; i < end; i++) {
+                            tensorResult.data[i] = tensorA.data[i] - row_max - log_total;
+                        }
+                    }
+                }
+            "#
+            }
+            "log_softmax_backward" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
+
+                layout(push_constant) uniform PushConstants {
+                    uint rows;
+                    uint row_len;
+                } push_constants;
+
+                layout(set = 0, binding = 0) buffer TensorGrad {
+                    float data[];
+                } tensorGrad;
+
+                layout(set = 0, binding = 1) buffer TensorOutput {
+                    float data[];
+                } tensorOutput;
+
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
+
+                // one invocation per row: grad - softmax * sum(grad), softmax being exp(output)
+                void main() {
+                    uint row = gl_GlobalInvocationID.x;
+                    if (row < push_constants.rows) {
+                        uint start = row * push_constants.row_len;
+                        uint end = start + push_constants.row_len;
+                        float total = 0.0;
+                        for (uint i = start; i < end; i++) {
+                            total += tensorGrad.data[i];
+                        }
+                        for (uint i = start; i < end; i++) {
+                            tensorResult.data[i] =
+                                tensorGrad.data[i] - exp(tensorOutput.data[i]) * total;
+                        }
+                    }
+                }
+            "#
+            }
+            "interpolate_linear" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
+
+                layout(push_constant) uniform PushConstants {
+                    uint size;
+                    uint input_size;
+                } push_constants;
+
+                layout(set = 0, binding = 0) buffer TensorA {
+                    float data[];
+                } tensorA;
+
+                layout(set = 0, binding = 1) buffer TensorB {
+                    float data[];
+                } tensorB;
+
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
+
+                void main() {
+                    uint idx = gl_GlobalInvocationID.x;
+                    if (idx < push_constants.size) {
+                        uint n = push_constants.input_size;
+                        uint m = push_constants.size;
+                        float position = m > 1 ? float(idx) * float(n - 1) / float(m - 1) : 0.0;
+                        uint lo = min(uint(position), n - 1);
+                        uint hi = min(lo + 1, n - 1);
+                        float t = position - float(lo);
+                        tensorResult.data[idx] = tensorA.data[lo] * (1.0 - t) + tensorA.data[hi] * t;
+                    }
+                }
+            "#
+            }
+            "interpolate_linear_backward" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
+
+                layout(push_constant) uniform PushConstants {
+                    uint size;
+                    uint output_size;
+                } push_constants;
+
+                layout(set = 0, binding = 0) buffer TensorGrad {
+                    float data[];
+                } tensorGrad;
+
+                layout(set = 0, binding = 1) buffer TensorB {
+                    float data[];
+                } tensorB;
+
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
+
+                // one invocation per input sample, gathering from the outputs around it. the
+                // forward mapping is recomputed exactly so every output is counted once
+                void main() {
+                    uint idx = gl_GlobalInvocationID.x;
+                    if (idx < push_constants.size) {
+                        uint n = push_constants.size;
+                        uint m = push_constants.output_size;
+                        uint first = 0;
+                        uint last = m - 1;
+                        if (n > 1 && m > 1) {
+                            float scale = float(m - 1) / float(n - 1);
+                            first = uint(max(floor(float(idx) * scale - scale) - 1.0, 0.0));
+                            last = min(uint(ceil(float(idx) * scale + scale)) + 1, m - 1);
+                        }
+                        float sum = 0.0;
+                        for (uint i = first; i <= last; i++) {
+                            float position = m > 1 ? float(i) * float(n - 1) / float(m - 1) : 0.0;
+                            uint lo = min(uint(position), n - 1);
+                            uint hi = min(lo + 1, n - 1);
+                            float t = position - float(lo);
+                            if (lo == idx) {
+                                sum += tensorGrad.data[i] * (1.0 - t);
+                            }
+                            if (hi == idx) {
+                                sum += tensorGrad.data[i] * t;
+                            }
+                        }
+                        tensorResult.data[idx] = sum;
+                    }
+                }
+            "#
+            }
+            "unary" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
+
+                layout(push_constant) uniform PushConstants {
+                    uint size;
+                    uint op_type;
+                } push_constants;
+
+                layout(set = 0, binding = 0) buffer TensorA {
+                    float data[];
+                } tensorA;
+
+                layout(set = 0, binding = 1) buffer TensorB {
+                    float data[];
+                } tensorB;
+
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
+
+                void main() {
+                    uint idx = gl_GlobalInvocationID.x;
+                    if (idx < push_constants.size) {
+                        float a = tensorA.data[idx];
+                        switch (push_constants.op_type) {
+                            case 0: tensorResult.data[idx] = abs(a); break;
+                            case 1: tensorResult.data[idx] = sqrt(a); break;
+                            case 2: tensorResult.data[idx] = sign(a); break;
+                            case 3: tensorResult.data[idx] = -a; break;
+                            case 4: tensorResult.data[idx] = a; break;
+                            case 5: tensorResult.data[idx] = sin(a); break;
+                            case 6: tensorResult.data[idx] = cos(a); break;
+                            // plain round() may go either way at .5, the CPU rounds to even
+                            case 7: tensorResult.data[idx] = roundEven(a); break;
+                            case 8: tensorResult.data[idx] = floor(a); break;
+                            case 9: tensorResult.data[idx] = ceil(a); break;
+                        }
+                    }
+                }
+            "#
+            }
+            "sum" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
+
+                layout(push_constant) uniform PushConstants {
+                    uint size;
+                } push_constants;
+
+                layout(set = 0, binding = 0) buffer TensorA {
+                    float data[];
+                } tensorA;
+
+                layout(set = 0, binding = 1) buffer TensorB {
+                    float data[];
+                } tensorB;
+
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
+
+                shared float partial[LOCAL_SIZE];
+
+                // dispatched as a single workgroup: every invocation sums a strided slice, then
+                // the partial sums are folded pairwise in shared memory
+                void main() {
+                    uint idx = gl_LocalInvocationID.x;
+                    float sum = 0.0;
+                    for (uint i = idx; i < push_constants.size; i += LOCAL_SIZE) {
+                        sum += tensorA.data[i];
+                    }
+                    partial[idx] = sum;
+                    barrier();
+                    for (uint offset = LOCAL_SIZE / 2; offset > 0; offset /= 2) {
+                        if (idx < offset) {
+                            partial[idx] += partial[idx + offset];
+                        }
+                        barrier();
+                    }
+                    if (idx == 0) {
+                        tensorResult.data[0] = partial[0];
+                    }
+                }
+            "#
+            }
+            "expand" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
+
+                layout(push_constant) uniform PushConstants {
+                    uint size;
+                } push_constants;
+
+                layout(set = 0, binding = 0) buffer TensorA {
+                    float data[];
+                } tensorA;
+
+                layout(set = 0, binding = 1) buffer TensorB {
+                    float data[];
+                } tensorB;
+
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
+
+                void main() {
+                    uint idx = gl_GlobalInvocationID.x;
+                    if (idx < push_constants.size) {
+                        tensorResult.data[idx] = tensorA.data[0];
+                    }
+                }
+            "#
+            }
+            "affine" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
+
+                layout(push_constant) uniform PushConstants {
+                    uint size;
+                    uint scale_bits;
+                    uint shift_bits;
+                } push_constants;
+
+                layout(set = 0, binding = 0) buffer TensorA {
+                    float data[];
+                } tensorA;
+
+                layout(set = 0, binding = 1) buffer TensorB {
+                    float data[];
+                } tensorB;
+
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
+
+                void main() {
+                    uint idx = gl_GlobalInvocationID.x;
+                    if (idx < push_constants.size) {
+                        tensorResult.data[idx] = fma(
+                            tensorA.data[idx],
+                            uintBitsToFloat(push_constants.scale_bits),
+                            uintBitsToFloat(push_constants.shift_bits)
+                        );
+                    }
+                }
+            "#
+            }
+            "clamp" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
+
+                layout(push_constant) uniform PushConstants {
+                    uint size;
+                    uint min_bits;
+                    uint max_bits;
+                } push_constants;
+
+                layout(set = 0, binding = 0) buffer TensorA {
+                    float data[];
+                } tensorA;
+
+                layout(set = 0, binding = 1) buffer TensorB {
+                    float data[];
+                } tensorB;
+
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
+
+                void main() {
+                    uint idx = gl_GlobalInvocationID.x;
+                    if (idx < push_constants.size) {
+                        tensorResult.data[idx] = clamp(
+                            tensorA.data[idx],
+                            uintBitsToFloat(push_constants.min_bits),
+                            uintBitsToFloat(push_constants.max_bits)
+                        );
+                    }
+                }
+            "#
+            }
+            "repeat_interleave" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
+
+                layout(push_constant) uniform PushConstants {
+                    uint size;
+                    uint repeats;
+                } push_constants;
+
+                layout(set = 0, binding = 0) buffer TensorA {
+                    float data[];
+                } tensorA;
+
+                layout(set = 0, binding = 1) buffer TensorB {
+                    float data[];
+                } tensorB;
+
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
+
+                void main() {
+                    uint idx = gl_GlobalInvocationID.x;
+                    if (idx < push_constants.size) {
+                        tensorResult.data[idx] = tensorA.data[idx / push_constants.repeats];
+                    }
+                }
+            "#
+            }
+            "repeat_interleave_backward" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
+
+                layout(push_constant) uniform PushConstants {
+                    uint size;
+                    uint repeats;
+                } push_constants;
+
+                layout(set = 0, binding = 0) buffer TensorGrad {
+                    float data[];
+                } tensorGrad;
+
+                layout(set = 0, binding = 1) buffer TensorB {
+                    float data[];
+                } tensorB;
+
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
+
+                // one invocation per input element, summing the gradients of its copies
+                void main() {
+                    uint idx = gl_GlobalInvocationID.x;
+                    if (idx < push_constants.size) {
+                        float sum = 0.0;
+                        for (uint i = 0; i < push_constants.repeats; i++) {
+                            sum += tensorGrad.data[idx * push_constants.repeats + i];
+                        }
+                        tensorResult.data[idx] = sum;
+                    }
+                }
+            "#
+            }
+            "broadcast_to" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
+
+                layout(push_constant) uniform PushConstants {
+                    uint size;
+                    uint repeats;
+                    uint inner;
+                } push_constants;
+
+                layout(set = 0, binding = 0) buffer TensorA {
+                    float data[];
+                } tensorA;
+
+                layout(set = 0, binding = 1) buffer TensorB {
+                    float data[];
+                } tensorB;
+
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
+
+                void main() {
+                    uint idx = gl_GlobalInvocationID.x;
+                    if (idx < push_constants.size) {
+                        uint inner = push_constants.inner;
+                        uint block = idx / (push_constants.repeats * inner);
+                        tensorResult.data[idx] = tensorA.data[block * inner + idx % inner];
+                    }
+                }
+            "#
+            }
+            "broadcast_to_backward" => {
+                r#"
+                #version 450
+                layout(local_size_x = LOCAL_SIZE) in;
+
+                layout(push_constant) uniform PushConstants {
+                    uint size;
+                    uint repeats;
+                    uint inner;
+                } push_constants;
+
+                layout(set = 0, binding = 0) buffer TensorGrad {
+                    float data[];
+                } tensorGrad;
+
+                layout(set = 0, binding = 1) buffer TensorB {
+                    float data[];
+                } tensorB;
+
+                layout(set = 0, binding = 2) buffer TensorResult {
+                    float data[];
+                } tensorResult;
+
+                // one invocation per input element, summing the gradients of its copies
+                void main() {
+                    uint idx = gl_GlobalInvocationID.x;
+                    if (idx < push_constants.size) {
+                        uint inner = push_constants.inner;
+                        uint first = (idx / inner) * push_constants.repeats * inner + idx % inner;
+                        float sum = 0.0;
+                        for (uint i = 0; i < push_constants.repeats; i++) {
+                            sum += tensorGrad.data[first + i * inner];
+                        }
+                        tensorResult.data[idx] = sum;
+                    }
+                }
+            "#
+            }
+            _ => return None,
+        };
+        Some(shader_src)
+    }
+    fn insert_pipeline(&self, operation: &str, shader_src: &str) {
+        let pipeline = self.vulkan.create_pipeline_for_shader(shader_src);
+        let mut pipelines = self.pipelines.lock().unwrap();
+        let mut op_types = self.operation_type.lock().unwrap();
+        pipelines.insert(operation.to_string(), pipeline);
+        op_types.insert(pipeline, operation.to_string());
+    }
+
+    // GPU-side duration of the most recent kernel, None if the queue can't record timestamps
+    pub fn last_kernel_time(&self) -> Option<Duration> {
+        self.vulkan
+            .last_dispatch_nanos()
+            .map(|nanos| Duration::from_nanos(nanos as u64))
+    }
+
+    // name, type and compute limits of the device this backend runs on
+    pub fn device_info(&self) -> DeviceInfo {
+        self.vulkan.device_info.clone()
+    }
+    // how many device allocations this backend has made so far, staging buffers included
+    pub fn allocation_count(&self) -> usize {
+        self.vulkan.allocation_count.get()
+    }
+    // command buffers allocated so far, they are reused so this stays at the number of ops that
+    // were in flight at once
+    pub fn command_buffer_count(&self) -> usize {
+        self.vulkan.command_buffer_count.get()
+    }
+    // picks the workgroup shape matmul runs with, the best one depends on the GPU so this is
+    // meant to be tuned with `--bench-matmul`. fails if the device can't run a workgroup that big
+    // or the tiles don't fit in shared memory
+    pub fn set_matmul_tile(&self, rows: u32, cols: u32) -> Result<(), FlameError> {
+        let shared_memory = (rows + cols) * MATMUL_TILE_K * size_of::<f32>() as u32;
+        let [max_x, max_y, _] = self.vulkan.max_compute_work_group_size;
+        if rows == 0
+            || cols == 0
+            || cols > max_x
+            || rows > max_y
+            || rows * cols > self.vulkan.max_compute_work_group_invocations
+            || shared_memory > self.vulkan.max_compute_shared_memory_size
+        {
+            return Err(FlameError::InvalidTileSize(rows, cols));
+        }
+        *self.matmul_tile.lock().unwrap() = (rows, cols);
+        Ok(())
+    }
+    // one shader per tile shape, generated on first use
+    fn matmul_pipeline(&self, rows: u32, cols: u32) -> vk::Pipeline {
+        let operation = format!("matmul_{}x{}", rows, cols);
+        if let Some(pipeline) = self.pipelines.lock().unwrap().get(&operation) {
+            return *pipeline;
+        }
+        let shader_src = format!(
+            r#"
+            #version 450
+            layout(local_size_x = {cols}, local_size_y = {rows}) in;
+
+            layout(push_constant) uniform PushConstants {{
+                uint size;
+                uint m;
+                uint k;
+                uint n;
+            }} push_constants;
+
+            layout(set = 0, binding = 0) buffer TensorA {{
+                float data[];
+            }} tensorA;
+
+            layout(set = 0, binding = 1) buffer TensorB {{
+                float data[];
+            }} tensorB;
+
+            layout(set = 0, binding = 2) buffer TensorResult {{
+                float data[];
+            }} tensorResult;
+
+            shared float tileA[{rows}][{tile_k}];
+            shared float tileB[{tile_k}][{cols}];
+
+            // every workgroup computes a {rows}x{cols} block of the result, staging {tile_k} wide
+            // strips of A and B in shared memory; out of range elements are loaded as zero
+            void main() {{
+                uint m = push_constants.m;
+                uint k = push_constants.k;
+                uint n = push_constants.n;
+                uint first_row = gl_WorkGroupID.y * {rows};
+                uint first_col = gl_WorkGroupID.x * {cols};
+                uint local_row = gl_LocalInvocationID.y;
+                uint local_col = gl_LocalInvocationID.x;
+                float sum = 0.0;
+                for (uint start = 0; start < k; start += {tile_k}) {{
+                    for (uint i = gl_LocalInvocationIndex; i < {rows} * {tile_k}; i += {rows} * {cols}) {{
+                        uint row = first_row + i / {tile_k};
+                        uint col = start + i % {tile_k};
+                        tileA[i / {tile_k}][i % {tile_k}] = (row < m && col < k) ? tensorA.data[row * k + col] : 0.0;
+                    }}
+                    for (uint i = gl_LocalInvocationIndex; i < {tile_k} * {cols}; i += {rows} * {cols}) {{
+                        uint row = start + i / {cols};
+                        uint col = first_col + i % {cols};
+                        tileB[i / {cols}][i % {cols}] = (row < k && col < n) ? tensorB.data[row * n + col] : 0.0;
+                    }}
+                    barrier();
+                    for (uint i = 0; i < {tile_k}; i++) {{
+                        sum += tileA[local_row][i] * tileB[i][local_col];
+                    }}
+                    barrier();
+                }}
+                uint row = first_row + local_row;
+                uint col = first_col + local_col;
+                if (row < m && col < n) {{
+                    tensorResult.data[row * n + col] = sum;
+                }}
+            }}
+        "#,
+            rows = rows,
+            cols = cols,
+            tile_k = MATMUL_TILE_K
+        );
+        self.insert_pipeline(&operation, &shader_src);
+        *self.pipelines.lock().unwrap().get(&operation).unwrap()
+    }
+    fn pipeline_for(&self, operation: &str) -> vk::Pipeline {
+        {
+            let pipelines = self.pipelines.lock().unwrap();
+            if let Some(pipeline) = pipelines.get(operation) {
+                return *pipeline;
+            }
+        }
+        self.compile_shader_for_operation(operation);
+        *self.pipelines.lock().unwrap().get(operation).unwrap()
+    }
+
+    // true if the operation is unported and was run by cpu_op on the fallback backend instead
+    fn fallback_to_cpu(
+        &self,
+        operation: &str,
+        inputs: &[&BufferHandle],
+        result: &BufferHandle,
+        cpu_op: impl FnOnce(&CPUBackend),
+    ) -> bool {
+        if !self.unported.contains(operation) {
+            return false;
+        }
+        let Some(cpu) = &self.cpu_fallback else {
+            panic!(
+                "{} isn't ported to Vulkan (allow_cpu_fallback runs it on the CPU)",
+                operation
+            );
+        };
+        if self
+            .fallback_warned
+            .lock()
+            .unwrap()
+            .insert(operation.to_string())
+        {
+            eprintln!(
+                "Warning: {} isn't ported to Vulkan, running it on the CPU",
+                operation
+            );
+        }
+        for input in inputs {
+            cpu.to_device(&self.to_host(input, input.size), input);
+        }
+        cpu_op(cpu);
+        self.to_device(&cpu.read_buffer(result), result);
+        for input in inputs {
+            cpu.free_buffer(input);
+        }
+        cpu.free_buffer(result);
+        true
+    }
+    // dispatches a shader with one input, which is bound to the unused binding 1 as well
+    fn execute_single_input(
+        &self,
+        operation: &str,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        params: [u32; 3],
+    ) {
+        let buffers = self.buffers.lock().unwrap();
+        if let (Some(buffer_a), Some(result_buffer)) = (buffers.get(&a.id), buffers.get(&result.id))
+        {
+            let pipeline = self.pipeline_for(operation);
+            let fence = self.vulkan.execute_compute_with_pipeline(
+                buffer_a,
+                buffer_a,
+                result_buffer,
+                size as u32,
+                params,
+                pipeline,
+            );
+            self.vulkan.wait_for_fence(fence);
+        } else {
+            panic!("Buffer not found for {}", operation);
+        }
+    }
+    // add/subtract/multiply/divide all run through the same pipeline, the op is picked by the
+    // op_type push constant so consecutive elementwise ops never switch pipelines
+    fn execute_elementwise(
+        &self,
+        a: &BufferHandle,
+        b: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        op_type: u32,
+        op_name: &str,
+    ) {
+        let div_policy = match *self.div_policy.lock().unwrap() {
+            DivByZero::Nan => 0,
+            DivByZero::Inf => 1,
+            DivByZero::Zero => 2,
+        };
+        let buffers = self.buffers.lock().unwrap();
+        if let (Some(buffer_a), Some(buffer_b), Some(result_buffer)) = (
+            buffers.get(&a.id),
+            buffers.get(&b.id),
+            buffers.get(&result.id),
+        ) {
+            let pipeline = self.pipeline_for("elementwise");
+
+            let fence = self.vulkan.execute_compute_with_pipeline(
+                buffer_a,
+                buffer_b,
+                result_buffer,
+                size as u32,
+                [op_type, div_policy, 0],
+                pipeline,
+            );
+            self.vulkan.wait_for_fence(fence);
+        } else {
+            panic!("Buffer not found for {}", op_name);
+        }
+    }
+}
+
+impl Backend for VulkanBackend {
+    fn allocate_buffer(&self, lazy_buffer: LazyBufferHandle, size: usize) -> BufferHandle {
+        if let Some(buffer) = self.buffers.lock().unwrap().get(&lazy_buffer) {
+            if buffer.len::<f32>() != size {
+                panic!(
+                    "buffer {:?} is already allocated with {} elements, requested {}",
+                    lazy_buffer,
+                    buffer.len::<f32>(),
+                    size
+                );
+            }
+            return BufferHandle {
+                id: lazy_buffer,
+                dtype: DType::F32,
+                size: buffer.len::<f32>(),
             };
+        }
+        println!("Allocating buffer with ID {:?}", lazy_buffer);
+        let buffer_size = (size * size_of::<f32>()) as u64;
+        let buffer = self.vulkan.create_gpu_buffer(buffer_size);
+
+        let handle = BufferHandle {
+            id: lazy_buffer,
+            size,
+            dtype: DType::F32,
+        };
+
+        self.buffers.lock().unwrap().insert(handle.id, buffer);
+        handle
+    }
+    fn allocate_temporary_buffer(&self, data: &[f32], size: usize) -> BufferHandle {
+        let buffer_size = (size * size_of::<f32>()) as u64;
+        let buffer = self.vulkan.create_gpu_buffer(buffer_size);
+        let handle = BufferHandle {
+            id: get_next_temporary_id(),
+            size,
+            dtype: DType::F32,
+        };
+        self.buffers.lock().unwrap().insert(handle.id, buffer);
+        self.to_device(data, &handle);
+        handle
+    }
+    fn read_buffer(&self, handle: &BufferHandle) -> Vec<f32> {
+        let buffers = self.buffers.lock().unwrap();
+        if let Some(buffer) = buffers.get(&handle.id) {
+            let count = handle.size.min(buffer.len::<f32>());
+            if self.vulkan.is_host_visible() {
+                return self.vulkan.read_buffer::<f32>(buffer, count);
+            }
+            let buffer_size = (count * size_of::<f32>()) as u64;
+            let staging_buffer = self.vulkan.create_staging_buffer(buffer_size);
+
+            let fence = self
+                .vulkan
+                .copy_buffer(buffer, &staging_buffer, buffer_size);
+            self.vulkan.wait_for_fence(fence);
+
+            let result = self.vulkan.read_buffer::<f32>(&staging_buffer, count);
+
+            unsafe {
+                self.vulkan
+                    .device
+                    .destroy_buffer(staging_buffer.buffer, None);
+                self.vulkan.device.free_memory(staging_buffer.memory, None);
+            }
+
+            result
+        } else {
+            panic!("Buffer with ID {:?} not found", handle.id);
+        }
+    }
+    fn read_element(&self, handle: &BufferHandle, index: usize) -> f32 {
+        let buffers = self.buffers.lock().unwrap();
+        if let Some(buffer) = buffers.get(&handle.id) {
+            assert!(
+                index < buffer.len::<f32>(),
+                "Index {} out of bounds for buffer of length {}",
+                index,
+                buffer.len::<f32>()
+            );
+            if self.vulkan.is_host_visible() {
+                return self.vulkan.read_buffer::<f32>(buffer, index + 1)[index];
+            }
+            // only the requested element crosses the bus
+            let element_size = size_of::<f32>() as u64;
+            let staging_buffer = self.vulkan.create_staging_buffer(element_size);
+            let fence = self.vulkan.copy_buffer_region(
+                buffer,
+                &staging_buffer,
+                index as u64 * element_size,
+                0,
+                element_size,
+            );
+            self.vulkan.wait_for_fence(fence);
+
+            let result = self.vulkan.read_buffer::<f32>(&staging_buffer, 1)[0];
+
+            unsafe {
+                self.vulkan
+                    .device
+                    .destroy_buffer(staging_buffer.buffer, None);
+                self.vulkan.device.free_memory(staging_buffer.memory, None);
+            }
+
+            result
+        } else {
+            panic!("Buffer with ID {:?} not found", handle.id);
+        }
+    }
+    fn free_buffer(&self, handle: &BufferHandle) {
+        let mut buffers = self.buffers.lock().unwrap();
+        if let Some(buffer) = buffers.remove(&handle.id) {
+            unsafe {
+                self.vulkan.device.destroy_buffer(buffer.buffer, None);
+                self.vulkan.device.free_memory(buffer.memory, None);
+            }
+        }
+    }
+
+    fn to_device(&self, data: &[f32], handle: &BufferHandle) {
+        let buffers = self.buffers.lock().unwrap();
+        if let Some(buffer) = buffers.get(&handle.id) {
+            if self.vulkan.is_host_visible() {
+                self.vulkan.upload_to_buffer(data, buffer);
+                return;
+            }
+            let staging_buffer = self
+                .vulkan
+                .create_staging_buffer((data.len() * size_of::<f32>()) as u64);
+            self.vulkan.upload_to_buffer(data, &staging_buffer);
+            let fence = self.vulkan.copy_buffer(
+                &staging_buffer,
+                buffer,
+                (data.len() * size_of::<f32>()) as u64,
+            );
+            self.vulkan.wait_for_fence(fence);
+            unsafe {
+                self.vulkan
+                    .device
+                    .destroy_buffer(staging_buffer.buffer, None);
+                self.vulkan.device.free_memory(staging_buffer.memory, None);
+            }
+        }
+    }
+
+    fn fill(&self, handle: &BufferHandle, value: f32, size: usize) {
+        if self.fallback_to_cpu("fill", &[], handle, |cpu| cpu.fill(handle, value, size)) {
+            return;
+        }
+        let buffers = self.buffers.lock().unwrap();
+        if let Some(buffer) = buffers.get(&handle.id) {
+            let fence =
+                self.vulkan
+                    .fill_buffer(buffer, value.to_bits(), (size * size_of::<f32>()) as u64);
+            self.vulkan.wait_for_fence(fence);
+        } else {
+            panic!("Buffer not found for fill");
+        }
+    }
+
+    fn upload_iter(
+        &self,
+        values: &mut dyn Iterator<Item = f32>,
+        handle: &BufferHandle,
+        size: usize,
+    ) {
+        let buffers = self.buffers.lock().unwrap();
+        if let Some(buffer) = buffers.get(&handle.id) {
+            if self.vulkan.is_host_visible() {
+                self.vulkan.upload_iter_to_buffer(values, size, buffer);
+                return;
+            }
+            let staging_buffer = self
+                .vulkan
+                .create_staging_buffer((size * size_of::<f32>()) as u64);
+            self.vulkan
+                .upload_iter_to_buffer(values, size, &staging_buffer);
+            let fence =
+                self.vulkan
+                    .copy_buffer(&staging_buffer, buffer, (size * size_of::<f32>()) as u64);
+            self.vulkan.wait_for_fence(fence);
+            unsafe {
+                self.vulkan
+                    .device
+                    .destroy_buffer(staging_buffer.buffer, None);
+                self.vulkan.device.free_memory(staging_buffer.memory, None);
+            }
+        }
+    }
+
+    // one invocation per element, the values never pass through host memory
+    fn fill_random(
+        &self,
+        handle: &BufferHandle,
+        seed: u64,
+        distribution: Distribution,
+        size: usize,
+    ) -> bool {
+        let key = stream_key(seed);
+        match distribution {
+            Distribution::Normal => {
+                self.execute_single_input("fill_random", handle, handle, size, [key, 0, 0]);
+            }
+            // drawn in [0, 1) and moved into place
+            Distribution::Uniform(low, high) => {
+                self.execute_single_input("fill_random", handle, handle, size, [key, 1, 0]);
+                self.affine(handle, handle, size, high - low, low);
+            }
+        }
+        true
+    }
+
+    fn to_host(&self, handle: &BufferHandle, size: usize) -> Vec<f32> {
+        let buffers = self.buffers.lock().unwrap();
+        if let Some(buffer) = buffers.get(&handle.id) {
+            let size = size.min(buffer.len::<f32>());
+            if self.vulkan.is_host_visible() {
+                return self.vulkan.read_buffer::<f32>(buffer, size);
+            }
+            let buffer_size = (size * size_of::<f32>()) as u64;
+            let staging_buffer = self.vulkan.create_staging_buffer(buffer_size);
+
+            let fence = self
+                .vulkan
+                .copy_buffer(buffer, &staging_buffer, buffer_size);
+            self.vulkan.wait_for_fence(fence);
+
+            let result = self.vulkan.read_buffer::<f32>(&staging_buffer, size);
+
+            unsafe {
+                self.vulkan
+                    .device
+                    .destroy_buffer(staging_buffer.buffer, None);
+                self.vulkan.device.free_memory(staging_buffer.memory, None);
+            }
+
+            result
+        } else {
+            panic!("Buffer with ID {:?} not found", handle.id);
+        }
+    }
+
+    fn add(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize) {
+        check_dtypes("add", &[a, b, result]);
+        if self.fallback_to_cpu("elementwise", &[a, b], result, |cpu| {
+            cpu.add(a, b, result, size)
+        }) {
+            return;
+        }
+        self.execute_elementwise(a, b, result, size, OP_ADD, "addition");
+    }
+
+    fn subtract(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize) {
+        check_dtypes("subtract", &[a, b, result]);
+        if self.fallback_to_cpu("elementwise", &[a, b], result, |cpu| {
+            cpu.subtract(a, b, result, size)
+        }) {
+            return;
+        }
+        self.execute_elementwise(a, b, result, size, OP_SUBTRACT, "subtraction");
+    }
+
+    fn multiply(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize) {
+        check_dtypes("multiply", &[a, b, result]);
+        if self.fallback_to_cpu("elementwise", &[a, b], result, |cpu| {
+            cpu.multiply(a, b, result, size)
+        }) {
+            return;
+        }
+        self.execute_elementwise(a, b, result, size, OP_MULTIPLY, "multiplication");
+    }
+
+    fn divide(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize) {
+        check_dtypes("divide", &[a, b, result]);
+        if self.fallback_to_cpu("elementwise", &[a, b], result, |cpu| {
+            cpu.divide(a, b, result, size)
+        }) {
+            return;
+        }
+        self.execute_elementwise(a, b, result, size, OP_DIVIDE, "division");
+    }
+    fn set_div_policy(&self, policy: DivByZero) {
+        *self.div_policy.lock().unwrap() = policy;
+        if let Some(cpu) = &self.cpu_fallback {
+            cpu.set_div_policy(policy);
+        }
+    }
+    // a device to device copy of B into A, no shader and no host round trip. every backward
+    // writes its gradients through this
+    fn memset(&self, a: &BufferHandle, b: &BufferHandle, size: usize) {
+        check_dtypes("memset", &[a, b]);
+        if self.fallback_to_cpu("memset", &[a, b], a, |cpu| cpu.memset(a, b, size)) {
+            return;
+        }
+        let buffers = self.buffers.lock().unwrap();
+        if let (Some(buffer_a), Some(buffer_b)) = (buffers.get(&a.id), buffers.get(&b.id)) {
+            let fence =
+                self.vulkan
+                    .copy_buffer(buffer_b, buffer_a, (size * size_of::<f32>()) as u64);
+            self.vulkan.wait_for_fence(fence);
+        } else {
+            panic!("Buffer not found for memset");
+        }
+    }
+    fn cumsum(&self, a: &BufferHandle, result: &BufferHandle, size: usize, reverse: bool) {
+        check_dtypes("cumsum", &[a, result]);
+        if self.fallback_to_cpu("cumsum", &[a], result, |cpu| {
+            cpu.cumsum(a, result, size, reverse)
+        }) {
+            return;
+        }
+        let buffers = self.buffers.lock().unwrap();
+        if let (Some(buffer_a), Some(result_buffer)) = (buffers.get(&a.id), buffers.get(&result.id))
+        {
+            let pipeline = self.pipeline_for("cumsum");
+            // Hillis-Steele scan: log2(size) passes that ping-pong between the result and a
+            // scratch buffer, the first pass reads the input so it is never written to
+            let scratch = self
+                .vulkan
+                .create_gpu_buffer((size * size_of::<f32>()) as u64);
+            let targets = [result_buffer, &scratch];
+            let mut passes = 0;
+            let mut offset = 1;
+            while offset < size {
+                let src = if passes == 0 {
+                    buffer_a
+                } else {
+                    targets[(passes - 1) % 2]
+                };
+                let fence = self.vulkan.execute_compute_with_pipeline(
+                    src,
+                    src,
+                    targets[passes % 2],
+                    size as u32,
+                    [offset as u32, reverse as u32, 0],
+                    pipeline,
+                );
+                self.vulkan.wait_for_fence(fence);
+                passes += 1;
+                offset *= 2;
+            }
+            let buffer_size = (size * size_of::<f32>()) as u64;
+            if passes == 0 {
+                let fence = self
+                    .vulkan
+                    .copy_buffer(buffer_a, result_buffer, buffer_size);
+                self.vulkan.wait_for_fence(fence);
+            } else if passes % 2 == 0 {
+                // the last pass wrote into the scratch buffer
+                let fence = self
+                    .vulkan
+                    .copy_buffer(&scratch, result_buffer, buffer_size);
+                self.vulkan.wait_for_fence(fence);
+            }
+            unsafe {
+                self.vulkan.device.destroy_buffer(scratch.buffer, None);
+                self.vulkan.device.free_memory(scratch.memory, None);
+            }
+        } else {
+            panic!("Buffer not found for cumsum");
+        }
+    }
+    fn where_mask(
+        &self,
+        cond: &BufferHandle,
+        a: &BufferHandle,
+        b: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+    ) {
+        check_dtypes("where", &[cond, a, b, result]);
+        if self.fallback_to_cpu("where", &[cond, a, b], result, |cpu| {
+            cpu.where_mask(cond, a, b, result, size)
+        }) {
+            return;
+        }
+        let buffers = self.buffers.lock().unwrap();
+        if let (Some(buffer_cond), Some(buffer_a), Some(buffer_b), Some(result_buffer)) = (
+            buffers.get(&cond.id),
+            buffers.get(&a.id),
+            buffers.get(&b.id),
+            buffers.get(&result.id),
+        ) {
+            // only three bindings per dispatch, so the else branch is copied into the result
+            // first and the shader overwrites the elements where the condition holds
+            let fence =
+                self.vulkan
+                    .copy_buffer(buffer_b, result_buffer, (size * size_of::<f32>()) as u64);
+            self.vulkan.wait_for_fence(fence);
 
+            let pipeline = self.pipeline_for("where");
             let fence = self.vulkan.execute_compute_with_pipeline(
+                buffer_cond,
                 buffer_a,
-                buffer_b,
                 result_buffer,
                 size as u32,
+                [0, 0, 0],
                 pipeline,
             );
             self.vulkan.wait_for_fence(fence);
         } else {
-            panic!("Buffer not found for addition");
+            panic!("Buffer not found for where");
         }
     }
-
-    fn subtract(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize) {
+    fn transpose(&self, a: &BufferHandle, result: &BufferHandle, rows: usize, cols: usize) {
+        check_dtypes("transpose", &[a, result]);
+        if self.fallback_to_cpu("transpose", &[a], result, |cpu| {
+            cpu.transpose(a, result, rows, cols)
+        }) {
+            return;
+        }
+        let buffers = self.buffers.lock().unwrap();
+        if let (Some(buffer_a), Some(result_buffer)) = (buffers.get(&a.id), buffers.get(&result.id))
+        {
+            let pipeline = self.pipeline_for("transpose");
+            // binding 1 is unused, A is bound there as well
+            let fence = self.vulkan.execute_compute_with_pipeline(
+                buffer_a,
+                buffer_a,
+                result_buffer,
+                (rows * cols) as u32,
+                [rows as u32, cols as u32, 0],
+                pipeline,
+            );
+            self.vulkan.wait_for_fence(fence);
+        } else {
+            panic!("Buffer not found for transpose");
+        }
+    }
+    fn triangle_mask(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        rows: usize,
+        cols: usize,
+        diagonal: i32,
+        upper: bool,
+    ) {
+        check_dtypes("triangle_mask", &[a, result]);
+        if self.fallback_to_cpu("triangle_mask", &[a], result, |cpu| {
+            cpu.triangle_mask(a, result, rows, cols, diagonal, upper)
+        }) {
+            return;
+        }
+        // the shader reads the diagonal back as an int
+        self.execute_single_input(
+            "triangle_mask",
+            a,
+            result,
+            rows * cols,
+            [cols as u32, diagonal as u32, upper as u32],
+        );
+    }
+    fn batch_transpose(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        batch: usize,
+        rows: usize,
+        cols: usize,
+    ) {
+        check_dtypes("batch_transpose", &[a, result]);
+        if self.fallback_to_cpu("batch_transpose", &[a], result, |cpu| {
+            cpu.batch_transpose(a, result, batch, rows, cols)
+        }) {
+            return;
+        }
+        let buffers = self.buffers.lock().unwrap();
+        if let (Some(buffer_a), Some(result_buffer)) = (buffers.get(&a.id), buffers.get(&result.id))
+        {
+            let pipeline = self.pipeline_for("batch_transpose");
+            // binding 1 is unused, A is bound there as well
+            let fence = self.vulkan.execute_compute_with_pipeline(
+                buffer_a,
+                buffer_a,
+                result_buffer,
+                (batch * rows * cols) as u32,
+                [rows as u32, cols as u32, 0],
+                pipeline,
+            );
+            self.vulkan.wait_for_fence(fence);
+        } else {
+            panic!("Buffer not found for batch_transpose");
+        }
+    }
+    fn matmul(
+        &self,
+        a: &BufferHandle,
+        b: &BufferHandle,
+        result: &BufferHandle,
+        m: usize,
+        k: usize,
+        n: usize,
+    ) {
+        check_dtypes("matmul", &[a, b, result]);
+        if self.fallback_to_cpu("matmul", &[a, b], result, |cpu| {
+            cpu.matmul(a, b, result, m, k, n)
+        }) {
+            return;
+        }
         let buffers = self.buffers.lock().unwrap();
         if let (Some(buffer_a), Some(buffer_b), Some(result_buffer)) = (
             buffers.get(&a.id),
             buffers.get(&b.id),
             buffers.get(&result.id),
         ) {
-            {
-                let pipelines = self.pipelines.lock().unwrap();
-                if !pipelines.contains_key("subtract") {
-                    drop(pipelines);
-                    self.compile_shader_for_operation("subtract");
-                }
-            }
-
-            let pipeline = {
-                let pipelines = self.pipelines.lock().unwrap();
-                *pipelines.get("subtract").unwrap()
-            };
-
+            let (rows, cols) = *self.matmul_tile.lock().unwrap();
+            let pipeline = self.matmul_pipeline(rows, cols);
+            let fence = self.vulkan.execute_compute_with_groups(
+                [buffer_a, buffer_b, result_buffer],
+                [(m * n) as u32, m as u32, k as u32, n as u32],
+                pipeline,
+                [(n as u32).div_ceil(cols), (m as u32).div_ceil(rows), 1],
+            );
+            self.vulkan.wait_for_fence(fence);
+        } else {
+            panic!("Buffer not found for matmul");
+        }
+    }
+    fn max_pool1d(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel: usize,
+        stride: usize,
+    ) {
+        check_dtypes("max_pool1d", &[a, result]);
+        if self.fallback_to_cpu("max_pool1d", &[a], result, |cpu| {
+            cpu.max_pool1d(a, result, input_size, kernel, stride)
+        }) {
+            return;
+        }
+        let buffers = self.buffers.lock().unwrap();
+        if let (Some(buffer_a), Some(result_buffer)) = (buffers.get(&a.id), buffers.get(&result.id))
+        {
+            let pipeline = self.pipeline_for("max_pool1d");
+            // binding 1 is unused, A is bound there as well
+            let fence = self.vulkan.execute_compute_with_pipeline(
+                buffer_a,
+                buffer_a,
+                result_buffer,
+                ((input_size - kernel) / stride + 1) as u32,
+                [kernel as u32, stride as u32, 0],
+                pipeline,
+            );
+            self.vulkan.wait_for_fence(fence);
+        } else {
+            panic!("Buffer not found for max_pool1d");
+        }
+    }
+    fn max_pool1d_backward(
+        &self,
+        a: &BufferHandle,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel: usize,
+        stride: usize,
+    ) {
+        check_dtypes("max_pool1d_backward", &[a, grad, result]);
+        if self.fallback_to_cpu("max_pool1d_backward", &[a, grad], result, |cpu| {
+            cpu.max_pool1d_backward(a, grad, result, input_size, kernel, stride)
+        }) {
+            return;
+        }
+        let buffers = self.buffers.lock().unwrap();
+        if let (Some(buffer_a), Some(buffer_grad), Some(result_buffer)) = (
+            buffers.get(&a.id),
+            buffers.get(&grad.id),
+            buffers.get(&result.id),
+        ) {
+            let pipeline = self.pipeline_for("max_pool1d_backward");
+            let fence = self.vulkan.execute_compute_with_pipeline(
+                buffer_a,
+                buffer_grad,
+                result_buffer,
+                input_size as u32,
+                [
+                    kernel as u32,
+                    stride as u32,
+                    ((input_size - kernel) / stride + 1) as u32,
+                ],
+                pipeline,
+            );
+            self.vulkan.wait_for_fence(fence);
+        } else {
+            panic!("Buffer not found for max_pool1d backward");
+        }
+    }
+    fn conv1d(
+        &self,
+        input: &BufferHandle,
+        kernel: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel_size: usize,
+        stride: usize,
+    ) {
+        check_dtypes("conv1d", &[input, kernel, result]);
+        if self.fallback_to_cpu("conv1d", &[input, kernel], result, |cpu| {
+            cpu.conv1d(input, kernel, result, input_size, kernel_size, stride)
+        }) {
+            return;
+        }
+        let output_size = (input_size - kernel_size) / stride + 1;
+        let buffers = self.buffers.lock().unwrap();
+        if let (Some(buffer_a), Some(buffer_b), Some(result_buffer)) = (
+            buffers.get(&input.id),
+            buffers.get(&kernel.id),
+            buffers.get(&result.id),
+        ) {
+            let pipeline = self.pipeline_for("conv1d");
             let fence = self.vulkan.execute_compute_with_pipeline(
                 buffer_a,
                 buffer_b,
                 result_buffer,
-                size as u32,
+                output_size as u32,
+                [input_size as u32, kernel_size as u32, stride as u32],
                 pipeline,
             );
             self.vulkan.wait_for_fence(fence);
         } else {
-            panic!("Buffer not found for subtraction");
+            panic!("Buffer not found for conv1d");
         }
     }
-
-    fn multiply(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize) {
+    fn conv1d_input_grad(
+        &self,
+        grad: &BufferHandle,
+        kernel: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel_size: usize,
+        stride: usize,
+    ) {
+        check_dtypes("conv1d_input_grad", &[grad, kernel, result]);
+        if self.fallback_to_cpu("conv1d_input_grad", &[grad, kernel], result, |cpu| {
+            cpu.conv1d_input_grad(grad, kernel, result, input_size, kernel_size, stride)
+        }) {
+            return;
+        }
+        let output_size = (input_size - kernel_size) / stride + 1;
         let buffers = self.buffers.lock().unwrap();
         if let (Some(buffer_a), Some(buffer_b), Some(result_buffer)) = (
-            buffers.get(&a.id),
-            buffers.get(&b.id),
+            buffers.get(&grad.id),
+            buffers.get(&kernel.id),
             buffers.get(&result.id),
         ) {
-            {
-                let pipelines = self.pipelines.lock().unwrap();
-                if !pipelines.contains_key("multiply") {
-                    drop(pipelines);
-                    self.compile_shader_for_operation("multiply");
-                }
-            }
-
-            let pipeline = {
-                let pipelines = self.pipelines.lock().unwrap();
-                *pipelines.get("multiply").unwrap()
-            };
-
+            let pipeline = self.pipeline_for("conv1d_input_grad");
             let fence = self.vulkan.execute_compute_with_pipeline(
                 buffer_a,
                 buffer_b,
                 result_buffer,
-                size as u32,
+                input_size as u32,
+                [kernel_size as u32, stride as u32, output_size as u32],
                 pipeline,
             );
             self.vulkan.wait_for_fence(fence);
         } else {
-            panic!("Buffer not found for multiplication");
+            panic!("Buffer not found for conv1d input gradient");
         }
     }
-
-    fn divide(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize) {
+    fn conv1d_kernel_grad(
+        &self,
+        input: &BufferHandle,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel_size: usize,
+        stride: usize,
+    ) {
+        check_dtypes("conv1d_kernel_grad", &[input, grad, result]);
+        if self.fallback_to_cpu("conv1d_kernel_grad", &[input, grad], result, |cpu| {
+            cpu.conv1d_kernel_grad(input, grad, result, input_size, kernel_size, stride)
+        }) {
+            return;
+        }
+        let output_size = (input_size - kernel_size) / stride + 1;
         let buffers = self.buffers.lock().unwrap();
         if let (Some(buffer_a), Some(buffer_b), Some(result_buffer)) = (
-            buffers.get(&a.id),
-            buffers.get(&b.id),
+            buffers.get(&input.id),
+            buffers.get(&grad.id),
             buffers.get(&result.id),
         ) {
-            {
-                let pipelines = self.pipelines.lock().unwrap();
-                if !pipelines.contains_key("divide") {
-                    drop(pipelines);
-                    self.compile_shader_for_operation("divide");
-                }
-            }
-
-            let pipeline = {
-                let pipelines = self.pipelines.lock().unwrap();
-                *pipelines.get("divide").unwrap()
-            };
-
+            let pipeline = self.pipeline_for("conv1d_kernel_grad");
             let fence = self.vulkan.execute_compute_with_pipeline(
                 buffer_a,
                 buffer_b,
                 result_buffer,
-                size as u32,
+                kernel_size as u32,
+                [stride as u32, output_size as u32, 0],
                 pipeline,
             );
             self.vulkan.wait_for_fence(fence);
         } else {
-            panic!("Buffer not found for division");
+            panic!("Buffer not found for conv1d kernel gradient");
         }
     }
-    fn memset(&self, a: &BufferHandle, b: &BufferHandle, size: usize) {
+    fn gather(
+        &self,
+        table: &BufferHandle,
+        indices: &BufferHandle,
+        result: &BufferHandle,
+        rows: usize,
+        row_len: usize,
+        count: usize,
+    ) {
+        check_dtypes("gather", &[table, indices, result]);
+        if self.fallback_to_cpu("gather", &[table, indices], result, |cpu| {
+            cpu.gather(table, indices, result, rows, row_len, count)
+        }) {
+            return;
+        }
         let buffers = self.buffers.lock().unwrap();
-        if let (Some(buffer_a), Some(buffer_b)) = (buffers.get(&a.id), buffers.get(&b.id)) {
-            {
-                let pipelines = self.pipelines.lock().unwrap();
-                if !pipelines.contains_key("memset") {
-                    drop(pipelines);
-                    self.compile_shader_for_operation("memset");
-                }
-            }
-
-            let pipeline = {
-                let pipelines = self.pipelines.lock().unwrap();
-                *pipelines.get("memset").unwrap()
-            };
-
+        if let (Some(buffer_a), Some(buffer_b), Some(result_buffer)) = (
+            buffers.get(&table.id),
+            buffers.get(&indices.id),
+            buffers.get(&result.id),
+        ) {
+            let pipeline = self.pipeline_for("gather");
+            let fence = self.vulkan.execute_compute_with_pipeline(
+                buffer_a,
+                buffer_b,
+                result_buffer,
+                (count * row_len) as u32,
+                [rows as u32, row_len as u32, 0],
+                pipeline,
+            );
+            self.vulkan.wait_for_fence(fence);
+        } else {
+            panic!("Buffer not found for gather");
+        }
+    }
+    fn gather_backward(
+        &self,
+        grad: &BufferHandle,
+        indices: &BufferHandle,
+        result: &BufferHandle,
+        rows: usize,
+        row_len: usize,
+        count: usize,
+    ) {
+        check_dtypes("gather_backward", &[grad, indices, result]);
+        if self.fallback_to_cpu("gather_backward", &[grad, indices], result, |cpu| {
+            cpu.gather_backward(grad, indices, result, rows, row_len, count)
+        }) {
+            return;
+        }
+        let buffers = self.buffers.lock().unwrap();
+        if let (Some(buffer_a), Some(buffer_b), Some(result_buffer)) = (
+            buffers.get(&grad.id),
+            buffers.get(&indices.id),
+            buffers.get(&result.id),
+        ) {
+            let pipeline = self.pipeline_for("gather_backward");
             let fence = self.vulkan.execute_compute_with_pipeline(
                 buffer_a,
                 buffer_b,
+                result_buffer,
+                (rows * row_len) as u32,
+                [row_len as u32, count as u32, 0],
+                pipeline,
+            );
+            self.vulkan.wait_for_fence(fence);
+        } else {
+            panic!("Buffer not found for gather gradient");
+        }
+    }
+    fn scatter_add(
+        &self,
+        table: &BufferHandle,
+        indices: &BufferHandle,
+        updates: &BufferHandle,
+        result: &BufferHandle,
+        rows: usize,
+        row_len: usize,
+        count: usize,
+    ) {
+        check_dtypes("scatter_add", &[table, indices, updates, result]);
+        if self.fallback_to_cpu("scatter_add", &[table, indices, updates], result, |cpu| {
+            cpu.scatter_add(table, indices, updates, result, rows, row_len, count)
+        }) {
+            return;
+        }
+        let buffers = self.buffers.lock().unwrap();
+        if let (
+            Some(buffer_table),
+            Some(buffer_indices),
+            Some(buffer_updates),
+            Some(result_buffer),
+        ) = (
+            buffers.get(&table.id),
+            buffers.get(&indices.id),
+            buffers.get(&updates.id),
+            buffers.get(&result.id),
+        ) {
+            // like where, the table is copied into the result first and the shader adds the
+            // updates on top of it
+            let fence = self.vulkan.copy_buffer(
+                buffer_table,
+                result_buffer,
+                (rows * row_len * size_of::<f32>()) as u64,
+            );
+            self.vulkan.wait_for_fence(fence);
+
+            let pipeline = self.pipeline_for("scatter_add");
+            let fence = self.vulkan.execute_compute_with_pipeline(
+                buffer_updates,
+                buffer_indices,
+                result_buffer,
+                (count * row_len) as u32,
+                [rows as u32, row_len as u32, 0],
+                pipeline,
+            );
+            self.vulkan.wait_for_fence(fence);
+        } else {
+            panic!("Buffer not found for scatter add");
+        }
+    }
+    // the bias add and the activation in one kernel, the sum never goes back to memory
+    fn bias_activation(
+        &self,
+        a: &BufferHandle,
+        bias: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        row_len: usize,
+        activation: Activation,
+    ) {
+        check_dtypes("bias_activation", &[a, bias, result]);
+        if self.fallback_to_cpu("bias_activation", &[a, bias], result, |cpu| {
+            cpu.bias_activation(a, bias, result, size, row_len, activation)
+        }) {
+            return;
+        }
+        let buffers = self.buffers.lock().unwrap();
+        if let (Some(buffer_a), Some(buffer_bias), Some(result_buffer)) = (
+            buffers.get(&a.id),
+            buffers.get(&bias.id),
+            buffers.get(&result.id),
+        ) {
+            let pipeline = self.pipeline_for("bias_activation");
+            let fence = self.vulkan.execute_compute_with_pipeline(
                 buffer_a,
+                buffer_bias,
+                result_buffer,
                 size as u32,
+                [row_len as u32, activation_type(activation), 0],
                 pipeline,
             );
             self.vulkan.wait_for_fence(fence);
         } else {
-            panic!("Buffer not found for memset");
+            panic!("Buffer not found for bias activation");
+        }
+    }
+    fn activation_backward(
+        &self,
+        grad: &BufferHandle,
+        pre_activation: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        activation: Activation,
+    ) {
+        check_dtypes("activation_backward", &[grad, pre_activation, result]);
+        if self.fallback_to_cpu(
+            "activation_backward",
+            &[grad, pre_activation],
+            result,
+            |cpu| cpu.activation_backward(grad, pre_activation, result, size, activation),
+        ) {
+            return;
+        }
+        let buffers = self.buffers.lock().unwrap();
+        if let (Some(buffer_grad), Some(buffer_pre), Some(result_buffer)) = (
+            buffers.get(&grad.id),
+            buffers.get(&pre_activation.id),
+            buffers.get(&result.id),
+        ) {
+            let pipeline = self.pipeline_for("activation_backward");
+            let fence = self.vulkan.execute_compute_with_pipeline(
+                buffer_grad,
+                buffer_pre,
+                result_buffer,
+                size as u32,
+                [activation_type(activation), 0, 0],
+                pipeline,
+            );
+            self.vulkan.wait_for_fence(fence);
+        } else {
+            panic!("Buffer not found for activation backward");
+        }
+    }
+    fn log_softmax(&self, a: &BufferHandle, result: &BufferHandle, size: usize, row_len: usize) {
+        check_dtypes("log_softmax", &[a, result]);
+        if self.fallback_to_cpu("log_softmax", &[a], result, |cpu| {
+            cpu.log_softmax(a, result, size, row_len)
+        }) {
+            return;
+        }
+        // one invocation per row
+        self.execute_single_input(
+            "log_softmax",
+            a,
+            result,
+            size / row_len,
+            [row_len as u32, 0, 0],
+        );
+    }
+    fn log_softmax_backward(
+        &self,
+        grad: &BufferHandle,
+        output: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        row_len: usize,
+    ) {
+        check_dtypes("log_softmax_backward", &[grad, output, result]);
+        if self.fallback_to_cpu("log_softmax_backward", &[grad, output], result, |cpu| {
+            cpu.log_softmax_backward(grad, output, result, size, row_len)
+        }) {
+            return;
+        }
+        let buffers = self.buffers.lock().unwrap();
+        if let (Some(buffer_grad), Some(buffer_output), Some(result_buffer)) = (
+            buffers.get(&grad.id),
+            buffers.get(&output.id),
+            buffers.get(&result.id),
+        ) {
+            let pipeline = self.pipeline_for("log_softmax_backward");
+            let fence = self.vulkan.execute_compute_with_pipeline(
+                buffer_grad,
+                buffer_output,
+                result_buffer,
+                (size / row_len) as u32,
+                [row_len as u32, 0, 0],
+                pipeline,
+            );
+            self.vulkan.wait_for_fence(fence);
+        } else {
+            panic!("Buffer not found for log_softmax backward");
+        }
+    }
+    fn interpolate_linear(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        output_size: usize,
+    ) {
+        check_dtypes("interpolate_linear", &[a, result]);
+        if self.fallback_to_cpu("interpolate_linear", &[a], result, |cpu| {
+            cpu.interpolate_linear(a, result, input_size, output_size)
+        }) {
+            return;
+        }
+        let buffers = self.buffers.lock().unwrap();
+        if let (Some(buffer_a), Some(result_buffer)) = (buffers.get(&a.id), buffers.get(&result.id))
+        {
+            let pipeline = self.pipeline_for("interpolate_linear");
+            // binding 1 is unused, A is bound there as well
+            let fence = self.vulkan.execute_compute_with_pipeline(
+                buffer_a,
+                buffer_a,
+                result_buffer,
+                output_size as u32,
+                [input_size as u32, 0, 0],
+                pipeline,
+            );
+            self.vulkan.wait_for_fence(fence);
+        } else {
+            panic!("Buffer not found for interpolate_linear");
+        }
+    }
+    fn interpolate_linear_backward(
+        &self,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        output_size: usize,
+    ) {
+        check_dtypes("interpolate_linear_backward", &[grad, result]);
+        if self.fallback_to_cpu("interpolate_linear_backward", &[grad], result, |cpu| {
+            cpu.interpolate_linear_backward(grad, result, input_size, output_size)
+        }) {
+            return;
+        }
+        let buffers = self.buffers.lock().unwrap();
+        if let (Some(buffer_grad), Some(result_buffer)) =
+            (buffers.get(&grad.id), buffers.get(&result.id))
+        {
+            let pipeline = self.pipeline_for("interpolate_linear_backward");
+            // binding 1 is unused, the gradient is bound there as well
+            let fence = self.vulkan.execute_compute_with_pipeline(
+                buffer_grad,
+                buffer_grad,
+                result_buffer,
+                input_size as u32,
+                [output_size as u32, 0, 0],
+                pipeline,
+            );
+            self.vulkan.wait_for_fence(fence);
+        } else {
+            panic!("Buffer not found for interpolate_linear backward");
+        }
+    }
+    fn unary(&self, a: &BufferHandle, result: &BufferHandle, size: usize, op: UnaryOp) {
+        check_dtypes("unary", &[a, result]);
+        if self.fallback_to_cpu("unary", &[a], result, |cpu| cpu.unary(a, result, size, op)) {
+            return;
+        }
+        let op_type = match op {
+            UnaryOp::Abs => OP_ABS,
+            UnaryOp::Sqrt => OP_SQRT,
+            UnaryOp::Sign => OP_SIGN,
+            UnaryOp::Neg => OP_NEG,
+            UnaryOp::Copy => OP_COPY,
+            UnaryOp::Sin => OP_SIN,
+            UnaryOp::Cos => OP_COS,
+            UnaryOp::Round => OP_ROUND,
+            UnaryOp::Floor => OP_FLOOR,
+            UnaryOp::Ceil => OP_CEIL,
+        };
+        self.execute_single_input("unary", a, result, size, [op_type, 0, 0]);
+    }
+    fn affine(&self, a: &BufferHandle, result: &BufferHandle, size: usize, scale: f32, shift: f32) {
+        check_dtypes("affine", &[a, result]);
+        if self.fallback_to_cpu("affine", &[a], result, |cpu| {
+            cpu.affine(a, result, size, scale, shift)
+        }) {
+            return;
+        }
+        self.execute_single_input(
+            "affine",
+            a,
+            result,
+            size,
+            [scale.to_bits(), shift.to_bits(), 0],
+        );
+    }
+    fn clamp(&self, a: &BufferHandle, result: &BufferHandle, size: usize, min: f32, max: f32) {
+        check_dtypes("clamp", &[a, result]);
+        if self.fallback_to_cpu("clamp", &[a], result, |cpu| {
+            cpu.clamp(a, result, size, min, max)
+        }) {
+            return;
+        }
+        // the bounds go through the integer push constants bit for bit
+        self.execute_single_input("clamp", a, result, size, [min.to_bits(), max.to_bits(), 0]);
+    }
+    fn sum(&self, a: &BufferHandle, result: &BufferHandle, size: usize) {
+        check_dtypes("sum", &[a, result]);
+        if self.fallback_to_cpu("sum", &[a], result, |cpu| cpu.sum(a, result, size)) {
+            return;
+        }
+        let buffers = self.buffers.lock().unwrap();
+        if let (Some(buffer_a), Some(result_buffer)) = (buffers.get(&a.id), buffers.get(&result.id))
+        {
+            let pipeline = self.pipeline_for("sum");
+            let fence = self.vulkan.execute_compute_with_groups(
+                [buffer_a, buffer_a, result_buffer],
+                [size as u32, 0, 0, 0],
+                pipeline,
+                [1, 1, 1],
+            );
+            self.vulkan.wait_for_fence(fence);
+        } else {
+            panic!("Buffer not found for sum");
+        }
+    }
+    fn expand(&self, a: &BufferHandle, result: &BufferHandle, size: usize) {
+        check_dtypes("expand", &[a, result]);
+        if self.fallback_to_cpu("expand", &[a], result, |cpu| cpu.expand(a, result, size)) {
+            return;
+        }
+        self.execute_single_input("expand", a, result, size, [0, 0, 0]);
+    }
+    fn repeat_interleave(&self, a: &BufferHandle, result: &BufferHandle, size: usize, n: usize) {
+        check_dtypes("repeat_interleave", &[a, result]);
+        if self.fallback_to_cpu("repeat_interleave", &[a], result, |cpu| {
+            cpu.repeat_interleave(a, result, size, n)
+        }) {
+            return;
+        }
+        self.execute_single_input("repeat_interleave", a, result, size, [n as u32, 0, 0]);
+    }
+    fn repeat_interleave_backward(
+        &self,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        n: usize,
+    ) {
+        check_dtypes("repeat_interleave_backward", &[grad, result]);
+        if self.fallback_to_cpu("repeat_interleave_backward", &[grad], result, |cpu| {
+            cpu.repeat_interleave_backward(grad, result, size, n)
+        }) {
+            return;
+        }
+        self.execute_single_input(
+            "repeat_interleave_backward",
+            grad,
+            result,
+            size,
+            [n as u32, 0, 0],
+        );
+    }
+    fn broadcast_to(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        n: usize,
+        inner: usize,
+    ) {
+        check_dtypes("broadcast_to", &[a, result]);
+        if self.fallback_to_cpu("broadcast_to", &[a], result, |cpu| {
+            cpu.broadcast_to(a, result, size, n, inner)
+        }) {
+            return;
+        }
+        self.execute_single_input("broadcast_to", a, result, size, [n as u32, inner as u32, 0]);
+    }
+    fn broadcast_to_backward(
+        &self,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        n: usize,
+        inner: usize,
+    ) {
+        check_dtypes("broadcast_to_backward", &[grad, result]);
+        if self.fallback_to_cpu("broadcast_to_backward", &[grad], result, |cpu| {
+            cpu.broadcast_to_backward(grad, result, size, n, inner)
+        }) {
+            return;
+        }
+        self.execute_single_input(
+            "broadcast_to_backward",
+            grad,
+            result,
+            size,
+            [n as u32, inner as u32, 0],
+        );
+    }
+    // no shader, the result is zero filled and the input copied into the middle
+    fn pad(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        left: usize,
+        right: usize,
+    ) {
+        check_dtypes("pad", &[a, result]);
+        if self.fallback_to_cpu("pad", &[a], result, |cpu| {
+            cpu.pad(a, result, input_size, left, right)
+        }) {
+            return;
+        }
+        let element_size = size_of::<f32>() as u64;
+        let buffers = self.buffers.lock().unwrap();
+        if let (Some(buffer_a), Some(result_buffer)) = (buffers.get(&a.id), buffers.get(&result.id))
+        {
+            let result_size = (left + input_size + right) as u64 * element_size;
+            let fence = self.vulkan.fill_buffer(result_buffer, 0, result_size);
+            self.vulkan.wait_for_fence(fence);
+            if input_size > 0 {
+                let fence = self.vulkan.copy_buffer_region(
+                    buffer_a,
+                    result_buffer,
+                    0,
+                    left as u64 * element_size,
+                    input_size as u64 * element_size,
+                );
+                self.vulkan.wait_for_fence(fence);
+            }
+        } else {
+            panic!("Buffer not found for pad");
+        }
+    }
+    fn slice(&self, a: &BufferHandle, result: &BufferHandle, start: usize, len: usize) {
+        check_dtypes("slice", &[a, result]);
+        if len == 0
+            || self.fallback_to_cpu("slice", &[a], result, |cpu| {
+                cpu.slice(a, result, start, len)
+            })
+        {
+            return;
+        }
+        let element_size = size_of::<f32>() as u64;
+        let buffers = self.buffers.lock().unwrap();
+        if let (Some(buffer_a), Some(result_buffer)) = (buffers.get(&a.id), buffers.get(&result.id))
+        {
+            let fence = self.vulkan.copy_buffer_region(
+                buffer_a,
+                result_buffer,
+                start as u64 * element_size,
+                0,
+                len as u64 * element_size,
+            );
+            self.vulkan.wait_for_fence(fence);
+        } else {
+            panic!("Buffer not found for slice");
         }
     }
     fn name(&self) -> &str {
         &self.name
     }
+    fn instance_id(&self) -> usize {
+        self.instance_id
+    }
+    fn memory_budget(&self) -> Option<usize> {
+        self.memory_budget
+    }
     fn drop(&self) {
         let buffers = self.buffers.lock().unwrap();
         for buffer in buffers.values() {
@@ -0,0 +1,91 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::lazybuffer::{Backend, mark_parameters_updated};
+use crate::tensor::Tensor;
+
+// file layout: magic, parameter count, then per parameter its length followed by the values,
+// everything little endian
+const MAGIC: &[u8; 4] = b"FLMR";
+
+// writes the current values of params to path so a run can be resumed with load_checkpoint.
+// params are realized first if they haven't been yet
+pub fn save_checkpoint(
+    path: impl AsRef<Path>,
+    params: &[Tensor],
+    backend: &dyn Backend,
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&(params.len() as u64).to_le_bytes())?;
+    for param in params {
+        if param.buffer.get_device_handle().is_none()
+            || param.buffer.is_stale()
+            || param.buffer.is_reused()
+        {
+            param.buffer.realize(backend, false);
+        }
+        let values = param.buffer.get_data(backend);
+        writer.write_all(&(values.len() as u64).to_le_bytes())?;
+        for value in values {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+    }
+    writer.flush()
+}
+
+// overwrites the values of params with the ones saved by save_checkpoint, in the same order.
+// the graph built on top of params stays valid since only the device buffers are written
+pub fn load_checkpoint(
+    path: impl AsRef<Path>,
+    params: &[Tensor],
+    backend: &dyn Backend,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("not a checkpoint file".to_string()));
+    }
+    let count = read_u64(&mut reader)? as usize;
+    if count != params.len() {
+        return Err(invalid_data(format!(
+            "checkpoint has {} parameters, expected {}",
+            count,
+            params.len()
+        )));
+    }
+    for param in params {
+        let len = read_u64(&mut reader)? as usize;
+        let size = param.buffer.get_size();
+        if len != size {
+            return Err(invalid_data(format!(
+                "checkpoint parameter has {} values, tensor {:?} has {}",
+                len, param.id, size
+            )));
+        }
+        let mut bytes = vec![0; len * size_of::<f32>()];
+        reader.read_exact(&mut bytes)?;
+        let values: Vec<f32> = bytes
+            .chunks_exact(size_of::<f32>())
+            .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        if param.buffer.get_device_handle().is_none() {
+            param.buffer.realize(backend, false);
+        }
+        backend.to_device(&values, &param.buffer.get_device_handle().unwrap());
+    }
+    mark_parameters_updated();
+    Ok(())
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,60 @@
+use std::fmt;
+
+use crate::lazybuffer::LazyBufferHandle;
+use crate::tensor::TensorId;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlameError {
+    // an optimizer step would have written NaN or Inf into this tensor, nothing was updated
+    NonFiniteUpdate(TensorId),
+    // operands of an elementwise op have different sizes (lhs, rhs)
+    SizeMismatch(usize, usize),
+    // an op was built on a tensor created without any data
+    EmptyOperand(TensorId),
+    // the buffer was computed before parameters were updated and has to be realized again
+    StaleBuffer(LazyBufferHandle),
+    // the buffer's storage was handed to another node during realize, see LazyBufferHandle::retain
+    ReusedBuffer(LazyBufferHandle),
+    // a matmul workgroup of rows x cols is beyond the device's compute limits
+    InvalidTileSize(u32, u32),
+    // realize_cancellable stopped because its token was cancelled
+    Cancelled,
+}
+
+impl fmt::Display for FlameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlameError::NonFiniteUpdate(id) => {
+                write!(f, "update would make tensor {:?} non-finite", id)
+            }
+            FlameError::SizeMismatch(a, b) => {
+                write!(f, "Size mismatch in operation: {} vs {}", a, b)
+            }
+            FlameError::StaleBuffer(handle) => write!(
+                f,
+                "buffer {:?} was computed before the last parameter update, realize it again",
+                handle
+            ),
+            FlameError::ReusedBuffer(handle) => write!(
+                f,
+                "buffer {:?} was overwritten by buffer reuse, retain it to read it",
+                handle
+            ),
+            FlameError::InvalidTileSize(rows, cols) => write!(
+                f,
+                "matmul tile {}x{} exceeds the device's compute limits",
+                rows, cols
+            ),
+            FlameError::Cancelled => write!(f, "realize was cancelled"),
+            FlameError::EmptyOperand(id) => {
+                write!(
+                    f,
+                    "tensor {:?} has no data, it can't be used in an operation",
+                    id
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for FlameError {}
@@ -0,0 +1,51 @@
+use crate::lazybuffer::LazyOp;
+use crate::tensor::{Tensor, TensorId, registered_tensors};
+
+// what a debugger needs to draw one tensor of the live graph, read without downloading any
+// data. it's a snapshot: nothing in it points back into the registries, so holding on to it
+// can't change or lock anything, and it goes stale once the graph changes
+#[derive(Debug, Clone, PartialEq)]
+pub struct TensorInfo {
+    pub shape: Vec<usize>,
+    pub requires_grad: bool,
+    // the buffer holds device values that are still current, see LazyBufferHandle::is_stale
+    pub is_realized: bool,
+    // name of the op that computes the tensor, e.g. "MatMul", "Creation" for leaves
+    pub op_kind: String,
+    pub has_grad: bool,
+}
+
+// every tensor registered on this thread, in creation order. reshape views are tensors of
+// their own and are listed too
+pub fn all_tensors() -> Vec<TensorId> {
+    registered_tensors()
+        .iter()
+        .map(|tensor| tensor.id)
+        .collect()
+}
+
+pub fn tensor_info(id: TensorId) -> TensorInfo {
+    let tensors = registered_tensors();
+    let Some(tensor) = tensors.iter().find(|tensor| tensor.id == id) else {
+        panic!("tensor_info for unknown tensor {:?}", id);
+    };
+    TensorInfo {
+        shape: tensor.shape(),
+        requires_grad: tensor.requires_grad,
+        is_realized: tensor.buffer.get_device_handle().is_some() && !tensor.buffer.is_stale(),
+        op_kind: op_kind(tensor),
+        has_grad: tensor.gradient.is_some(),
+    }
+}
+
+fn op_kind(tensor: &Tensor) -> String {
+    match tensor.buffer.get_op() {
+        // the data of a leaf can be huge, it's not formatted
+        LazyOp::Creation(_) => "Creation".to_string(),
+        // every other op only holds handles and sizes, so its Debug output is short
+        op => {
+            let debug = format!("{:?}", op);
+            debug.split('(').next().unwrap_or_default().to_string()
+        }
+    }
+}
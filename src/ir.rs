@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::lazybuffer::{
+    Backend, CreationType, LazyBuffer, LazyBufferHandle, LazyOp, get_next_buffer_id, map_operands,
+};
+
+// the op graph behind a buffer as plain data, so it can be written with any serde format and
+// rebuilt in another run. leaves whose values only exist on the device or in a Tensor::from_fn
+// closure are exported as raw data: the device values are downloaded, closures are evaluated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphIR {
+    pub root: LazyBufferHandle,
+    // operands are listed before the nodes that read them
+    pub nodes: Vec<IrNode>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IrNode {
+    pub id: LazyBufferHandle,
+    pub size: usize,
+    pub op: LazyOp,
+}
+
+impl LazyBufferHandle {
+    pub fn to_ir(&self, backend: &dyn Backend) -> GraphIR {
+        let nodes = self
+            .graph_nodes()
+            .into_iter()
+            .map(|(id, size, op)| {
+                let op = match op {
+                    LazyOp::Creation(CreationType::Created) => LazyOp::Creation(
+                        CreationType::RawData(id.get_data(backend).into_boxed_slice()),
+                    ),
+                    LazyOp::Creation(CreationType::Generated(generator)) => {
+                        let data = (0..size).map(|i| (generator.0)(i)).collect();
+                        LazyOp::Creation(CreationType::RawData(data))
+                    }
+                    op => op,
+                };
+                IrNode { id, size, op }
+            })
+            .collect();
+        GraphIR { root: *self, nodes }
+    }
+}
+
+impl GraphIR {
+    // registers every node again as a new scratch buffer and returns the rebuilt root. the
+    // rebuilt graph isn't attached to any tensor, so backward doesn't see it
+    pub fn rebuild(&self) -> LazyBufferHandle {
+        let ids: HashMap<LazyBufferHandle, LazyBufferHandle> = self
+            .nodes
+            .iter()
+            .map(|node| (node.id, get_next_buffer_id()))
+            .collect();
+        for node in &self.nodes {
+            let op = map_operands(&node.op, |operand| ids[&operand]);
+            LazyBuffer::push_scratch(ids[&node.id], node.size, op);
+        }
+        ids[&self.root]
+    }
+}
@@ -1,21 +1,46 @@
 use core::panic;
 use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::hash::{DefaultHasher, Hash, Hasher};
+use std::rc::Rc;
 
-use crate::tensor::TensorId;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+use crate::error::FlameError;
+use crate::random::{Distribution, Rng, next_stream_seed, sample, stream_key};
+use crate::tensor::{Tensor, TensorId};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct LazyBufferHandle(pub usize);
 pub const LAZYBUFFER_HANDLE_NULL: LazyBufferHandle = LazyBufferHandle(usize::MAX);
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum CreationType {
-    Random,
+    Random(u64),            // standard normal, drawn from the given stream when realized
+    Uniform(u64, f32, f32), // uniform in [low, high), drawn like Random
+    RandomMask(u64, f32),   // 0 with probability p, 1 / (1 - p) otherwise
     RawData(Box<[f32]>),
+    #[serde(skip)]
+    Generated(Generator), // value of every element computed from its index while uploading
+    Filled(f32), // every element set on the device, nothing is uploaded
     Created,
 }
+#[derive(Clone)]
+pub struct Generator(pub Rc<dyn Fn(usize) -> f32>);
+impl fmt::Debug for Generator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Generator")
+    }
+}
+impl PartialEq for Generator {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LazyOp {
     Creation(CreationType),
     Clear(LazyBufferHandle),
@@ -24,6 +49,85 @@ pub enum LazyOp {
     Multiply(LazyBufferHandle, LazyBufferHandle),
     Divide(LazyBufferHandle, LazyBufferHandle),
     Memset(LazyBufferHandle, LazyBufferHandle), // set A to B
+    CumSum(LazyBufferHandle, bool),             // running total of A, back to front if set
+    Where(LazyBufferHandle, LazyBufferHandle, LazyBufferHandle), // A != 0 ? B : C
+    Transpose(LazyBufferHandle, usize, usize),  // A is rows x cols, row major
+    // A (rows of cols elements) with every element zeroed whose col - row is below the diagonal
+    // if upper is set, above it otherwise
+    TriangleMask(LazyBufferHandle, usize, i32, bool),
+    // every rows x cols matrix of A transposed, A holds batch of them back to back
+    BatchTranspose(LazyBufferHandle, usize, usize, usize),
+    MatMul(LazyBufferHandle, LazyBufferHandle, usize, usize, usize), // (m x k) @ (k x n)
+    MaxPool1d(LazyBufferHandle, usize, usize), // max over windows of A, kernel then stride
+    // gradient of MaxPool1d(A, ..) wrt A, B holds the gradient of the pooled output
+    MaxPool1dBackward(LazyBufferHandle, LazyBufferHandle, usize, usize),
+    InterpolateLinear(LazyBufferHandle, usize), // A resampled to the given length
+    // gradient of InterpolateLinear wrt its input, A is the output gradient and the length is
+    // the one of the original input
+    InterpolateLinearBackward(LazyBufferHandle, usize),
+    Unary(LazyBufferHandle, UnaryOp), // op applied to every element of A
+    Affine(LazyBufferHandle, f32, f32), // A * scale + shift in one op
+    // A unchanged, the gradient flowing back through it is multiplied by the factor
+    ScaleGrad(LazyBufferHandle, f32),
+    Sum(LazyBufferHandle),           // single element holding the sum of A
+    Expand(LazyBufferHandle, usize), // single element A repeated to the given length
+    RepeatInterleave(LazyBufferHandle, usize), // every element of A repeated n times in a row
+    // gradient of RepeatInterleave(_, n), every n consecutive elements of A summed
+    RepeatInterleaveBackward(LazyBufferHandle, usize),
+    // every block of inner consecutive elements of A repeated n times in a row, one broadcast
+    // axis of size n inserted in front of the last inner elements
+    BroadcastTo(LazyBufferHandle, usize, usize),
+    // gradient of BroadcastTo(_, n, inner), the n copies of every block of A summed
+    BroadcastToBackward(LazyBufferHandle, usize, usize),
+    Pad(LazyBufferHandle, usize, usize), // A with that many zeros before and after it
+    Slice(LazyBufferHandle, usize, usize), // the elements of A from start, of the given length
+    // A slid over by kernel B with the given stride, only where B fits entirely
+    Conv1d(LazyBufferHandle, LazyBufferHandle, usize),
+    // gradients of Conv1d(input, kernel, stride): wrt the input from the output gradient A and
+    // kernel B, and wrt the kernel from input A and output gradient B. the length is the one of
+    // the input and the kernel respectively
+    Conv1dInputGrad(LazyBufferHandle, LazyBufferHandle, usize, usize),
+    Conv1dKernelGrad(LazyBufferHandle, LazyBufferHandle, usize, usize),
+    // the rows of A (row_len elements each) picked by the indices stored as floats in B
+    Gather(LazyBufferHandle, LazyBufferHandle, usize),
+    // gradient of Gather wrt the table: the rows of A added into a table of the given rows and
+    // row_len at the indices in B, repeated indices accumulate
+    GatherBackward(LazyBufferHandle, LazyBufferHandle, usize, usize),
+    // A (row_len elements per row) with the rows of C added into it at the indices in B,
+    // repeated indices accumulate
+    ScatterAdd(LazyBufferHandle, LazyBufferHandle, LazyBufferHandle, usize),
+    // log of the softmax of every row of A (row_len elements each), shifted by the row max so
+    // large values don't overflow
+    LogSoftmax(LazyBufferHandle, usize),
+    // gradient of LogSoftmax wrt its input from the output gradient A and the output B,
+    // A - exp(B) * sum(A) per row
+    LogSoftmaxBackward(LazyBufferHandle, LazyBufferHandle, usize),
+    // every row of A (row_len elements each) plus the row B, then the activation, in one pass
+    BiasActivation(LazyBufferHandle, LazyBufferHandle, usize, Activation),
+    // gradient of the activation from the output gradient A and the pre-activation values B
+    ActivationBackward(LazyBufferHandle, LazyBufferHandle, Activation),
+}
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum UnaryOp {
+    Abs,
+    Sqrt,
+    Sign, // -1, 0 or 1
+    Neg,
+    Copy, // same values in a new buffer, see Tensor::detach
+    Sin,
+    Cos,
+    Round, // halfway cases to even on every backend
+    Floor,
+    Ceil,
+}
+// applied by BiasActivation, see Tensor::linear_activation
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Activation {
+    Relu,
+    Sigmoid,
+    Tanh,
+    Gelu, // the tanh approximation
+    None,
 }
 fn calculate_op_hash(op: &LazyOp) -> Option<usize> {
     let mut hasher = DefaultHasher::new();
@@ -65,30 +169,474 @@ fn calculate_op_hash(op: &LazyOp) -> Option<usize> {
             b.0.hash(&mut hasher);
             6_usize.hash(&mut hasher);
         }
+        LazyOp::CumSum(a, reverse) => {
+            a.0.hash(&mut hasher);
+            reverse.hash(&mut hasher);
+            7_usize.hash(&mut hasher);
+        }
+        LazyOp::Where(cond, a, b) => {
+            cond.0.hash(&mut hasher);
+            a.0.hash(&mut hasher);
+            b.0.hash(&mut hasher);
+            8_usize.hash(&mut hasher);
+        }
+        LazyOp::Transpose(a, rows, cols) => {
+            a.0.hash(&mut hasher);
+            rows.hash(&mut hasher);
+            cols.hash(&mut hasher);
+            9_usize.hash(&mut hasher);
+        }
+        LazyOp::TriangleMask(a, cols, diagonal, upper) => {
+            a.0.hash(&mut hasher);
+            (cols, diagonal, upper).hash(&mut hasher);
+            34_usize.hash(&mut hasher);
+        }
+        LazyOp::MatMul(a, b, m, k, n) => {
+            a.0.hash(&mut hasher);
+            b.0.hash(&mut hasher);
+            (m, k, n).hash(&mut hasher);
+            10_usize.hash(&mut hasher);
+        }
+        LazyOp::MaxPool1d(a, kernel, stride) => {
+            a.0.hash(&mut hasher);
+            (kernel, stride).hash(&mut hasher);
+            11_usize.hash(&mut hasher);
+        }
+        LazyOp::MaxPool1dBackward(a, b, kernel, stride) => {
+            a.0.hash(&mut hasher);
+            b.0.hash(&mut hasher);
+            (kernel, stride).hash(&mut hasher);
+            12_usize.hash(&mut hasher);
+        }
+        LazyOp::InterpolateLinear(a, len) => {
+            a.0.hash(&mut hasher);
+            len.hash(&mut hasher);
+            13_usize.hash(&mut hasher);
+        }
+        LazyOp::InterpolateLinearBackward(a, len) => {
+            a.0.hash(&mut hasher);
+            len.hash(&mut hasher);
+            14_usize.hash(&mut hasher);
+        }
+        LazyOp::Unary(a, unary) => {
+            a.0.hash(&mut hasher);
+            unary.hash(&mut hasher);
+            15_usize.hash(&mut hasher);
+        }
+        LazyOp::Affine(a, scale, shift) => {
+            a.0.hash(&mut hasher);
+            (scale.to_bits(), shift.to_bits()).hash(&mut hasher);
+            28_usize.hash(&mut hasher);
+        }
+        LazyOp::ScaleGrad(a, factor) => {
+            a.0.hash(&mut hasher);
+            factor.to_bits().hash(&mut hasher);
+            37_usize.hash(&mut hasher);
+        }
+        LazyOp::Sum(a) => {
+            a.0.hash(&mut hasher);
+            16_usize.hash(&mut hasher);
+        }
+        LazyOp::Expand(a, len) => {
+            a.0.hash(&mut hasher);
+            len.hash(&mut hasher);
+            17_usize.hash(&mut hasher);
+        }
+        LazyOp::RepeatInterleave(a, n) => {
+            a.0.hash(&mut hasher);
+            n.hash(&mut hasher);
+            18_usize.hash(&mut hasher);
+        }
+        LazyOp::RepeatInterleaveBackward(a, n) => {
+            a.0.hash(&mut hasher);
+            n.hash(&mut hasher);
+            19_usize.hash(&mut hasher);
+        }
+        LazyOp::BroadcastTo(a, n, inner) => {
+            a.0.hash(&mut hasher);
+            n.hash(&mut hasher);
+            inner.hash(&mut hasher);
+            29_usize.hash(&mut hasher);
+        }
+        LazyOp::BroadcastToBackward(a, n, inner) => {
+            a.0.hash(&mut hasher);
+            n.hash(&mut hasher);
+            inner.hash(&mut hasher);
+            30_usize.hash(&mut hasher);
+        }
+        LazyOp::Pad(a, left, right) => {
+            a.0.hash(&mut hasher);
+            (left, right).hash(&mut hasher);
+            20_usize.hash(&mut hasher);
+        }
+        LazyOp::Slice(a, start, len) => {
+            a.0.hash(&mut hasher);
+            (start, len).hash(&mut hasher);
+            21_usize.hash(&mut hasher);
+        }
+        LazyOp::Conv1d(a, b, stride) => {
+            a.0.hash(&mut hasher);
+            b.0.hash(&mut hasher);
+            stride.hash(&mut hasher);
+            22_usize.hash(&mut hasher);
+        }
+        LazyOp::Conv1dInputGrad(a, b, stride, len) => {
+            a.0.hash(&mut hasher);
+            b.0.hash(&mut hasher);
+            (stride, len).hash(&mut hasher);
+            23_usize.hash(&mut hasher);
+        }
+        LazyOp::Conv1dKernelGrad(a, b, stride, len) => {
+            a.0.hash(&mut hasher);
+            b.0.hash(&mut hasher);
+            (stride, len).hash(&mut hasher);
+            24_usize.hash(&mut hasher);
+        }
+        LazyOp::Gather(a, indices, row_len) => {
+            a.0.hash(&mut hasher);
+            indices.0.hash(&mut hasher);
+            row_len.hash(&mut hasher);
+            26_usize.hash(&mut hasher);
+        }
+        LazyOp::GatherBackward(a, indices, rows, row_len) => {
+            a.0.hash(&mut hasher);
+            indices.0.hash(&mut hasher);
+            (rows, row_len).hash(&mut hasher);
+            27_usize.hash(&mut hasher);
+        }
+        LazyOp::ScatterAdd(a, indices, updates, row_len) => {
+            a.0.hash(&mut hasher);
+            indices.0.hash(&mut hasher);
+            updates.0.hash(&mut hasher);
+            row_len.hash(&mut hasher);
+            33_usize.hash(&mut hasher);
+        }
+        LazyOp::LogSoftmax(a, row_len) => {
+            a.0.hash(&mut hasher);
+            row_len.hash(&mut hasher);
+            31_usize.hash(&mut hasher);
+        }
+        LazyOp::LogSoftmaxBackward(a, b, row_len) => {
+            a.0.hash(&mut hasher);
+            b.0.hash(&mut hasher);
+            row_len.hash(&mut hasher);
+            32_usize.hash(&mut hasher);
+        }
+        LazyOp::BiasActivation(a, b, row_len, activation) => {
+            a.0.hash(&mut hasher);
+            b.0.hash(&mut hasher);
+            (row_len, activation).hash(&mut hasher);
+            35_usize.hash(&mut hasher);
+        }
+        LazyOp::ActivationBackward(a, b, activation) => {
+            a.0.hash(&mut hasher);
+            b.0.hash(&mut hasher);
+            activation.hash(&mut hasher);
+            36_usize.hash(&mut hasher);
+        }
+        LazyOp::BatchTranspose(a, batch, rows, cols) => {
+            a.0.hash(&mut hasher);
+            (batch, rows, cols).hash(&mut hasher);
+            25_usize.hash(&mut hasher);
+        }
     }
 
     Some(hasher.finish() as usize)
 }
 pub trait Backend {
+    // returns the existing buffer if lazy_buffer already has one, that's how realized data is
+    // found again. panics if the existing buffer has a different size, the id was reused
     fn allocate_buffer(&self, lazy_buffer: LazyBufferHandle, size: usize) -> BufferHandle;
+    // a buffer holding data that belongs to no lazy buffer, under an id from
+    // get_next_temporary_id. the caller frees it once it's done with it
     fn allocate_temporary_buffer(&self, data: &[f32], size: usize) -> BufferHandle;
     fn read_buffer(&self, handle: &BufferHandle) -> Vec<f32>;
+    fn read_element(&self, handle: &BufferHandle, index: usize) -> f32;
     fn free_buffer(&self, handle: &BufferHandle);
     fn drop(&self);
     fn to_device(&self, data: &[f32], handle: &BufferHandle);
     fn to_host(&self, handle: &BufferHandle, size: usize) -> Vec<f32>;
+    // like to_device, without the values ever being collected on the host
+    fn fill(&self, handle: &BufferHandle, value: f32, size: usize);
+    fn upload_iter(
+        &self,
+        values: &mut dyn Iterator<Item = f32>,
+        handle: &BufferHandle,
+        size: usize,
+    );
+    // random values generated on the backend's side instead of uploaded, element i is
+    // random::sample(stream_key(seed), i, ..). false if the backend can't, the caller then
+    // uploads the same values generated on the host
+    fn fill_random(
+        &self,
+        handle: &BufferHandle,
+        seed: u64,
+        distribution: Distribution,
+        size: usize,
+    ) -> bool;
     fn add(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize);
     fn subtract(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize);
     fn multiply(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize);
     fn divide(&self, a: &BufferHandle, b: &BufferHandle, result: &BufferHandle, size: usize);
     fn memset(&self, a: &BufferHandle, b: &BufferHandle, size: usize);
+    // what divide writes where the divisor is zero, Inf unless changed
+    fn set_div_policy(&self, policy: DivByZero);
+    fn cumsum(&self, a: &BufferHandle, result: &BufferHandle, size: usize, reverse: bool);
+    fn where_mask(
+        &self,
+        cond: &BufferHandle,
+        a: &BufferHandle,
+        b: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+    );
+    fn transpose(&self, a: &BufferHandle, result: &BufferHandle, rows: usize, cols: usize);
+    // result[r * cols + c] = a[r * cols + c] where c - r >= diagonal (upper) or
+    // c - r <= diagonal (lower), 0 everywhere else
+    fn triangle_mask(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        rows: usize,
+        cols: usize,
+        diagonal: i32,
+        upper: bool,
+    );
+    fn batch_transpose(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        batch: usize,
+        rows: usize,
+        cols: usize,
+    );
+    fn matmul(
+        &self,
+        a: &BufferHandle,
+        b: &BufferHandle,
+        result: &BufferHandle,
+        m: usize,
+        k: usize,
+        n: usize,
+    );
+    fn max_pool1d(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel: usize,
+        stride: usize,
+    );
+    // result[i] = sum of grad over the windows whose (first) maximum is a[i]
+    fn max_pool1d_backward(
+        &self,
+        a: &BufferHandle,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel: usize,
+        stride: usize,
+    );
+    // the first and last elements line up, everything between is linearly interpolated
+    fn interpolate_linear(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        output_size: usize,
+    );
+    fn interpolate_linear_backward(
+        &self,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        output_size: usize,
+    );
+    fn unary(&self, a: &BufferHandle, result: &BufferHandle, size: usize, op: UnaryOp);
+    // result = a * scale + shift, fused
+    fn affine(&self, a: &BufferHandle, result: &BufferHandle, size: usize, scale: f32, shift: f32);
+    // result = a limited to [min, max], a and result may be the same buffer
+    fn clamp(&self, a: &BufferHandle, result: &BufferHandle, size: usize, min: f32, max: f32);
+    // result is a single element
+    fn sum(&self, a: &BufferHandle, result: &BufferHandle, size: usize);
+    // a is a single element
+    fn expand(&self, a: &BufferHandle, result: &BufferHandle, size: usize);
+    // size is the length of the result in both
+    fn repeat_interleave(&self, a: &BufferHandle, result: &BufferHandle, size: usize, n: usize);
+    fn repeat_interleave_backward(
+        &self,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        n: usize,
+    );
+    // size is the length of the result in both, a block is inner consecutive elements
+    fn broadcast_to(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        n: usize,
+        inner: usize,
+    );
+    fn broadcast_to_backward(
+        &self,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        n: usize,
+        inner: usize,
+    );
+    fn pad(
+        &self,
+        a: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        left: usize,
+        right: usize,
+    );
+    fn slice(&self, a: &BufferHandle, result: &BufferHandle, start: usize, len: usize);
+    // result[o] = sum over j of input[o * stride + j] * kernel[j]
+    fn conv1d(
+        &self,
+        input: &BufferHandle,
+        kernel: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel_size: usize,
+        stride: usize,
+    );
+    // result[i] = sum of grad[o] * kernel[j] over all o * stride + j == i
+    fn conv1d_input_grad(
+        &self,
+        grad: &BufferHandle,
+        kernel: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel_size: usize,
+        stride: usize,
+    );
+    // result[j] = sum over o of input[o * stride + j] * grad[o]
+    fn conv1d_kernel_grad(
+        &self,
+        input: &BufferHandle,
+        grad: &BufferHandle,
+        result: &BufferHandle,
+        input_size: usize,
+        kernel_size: usize,
+        stride: usize,
+    );
+    // result[i * row_len + c] = table[indices[i] * row_len + c]. an index that isn't a row of
+    // the table panics on the CPU, the Vulkan backend reads zeros for it
+    fn gather(
+        &self,
+        table: &BufferHandle,
+        indices: &BufferHandle,
+        result: &BufferHandle,
+        rows: usize,
+        row_len: usize,
+        count: usize,
+    );
+    // result[r * row_len + c] = sum of grad[i * row_len + c] over all i with indices[i] == r
+    fn gather_backward(
+        &self,
+        grad: &BufferHandle,
+        indices: &BufferHandle,
+        result: &BufferHandle,
+        rows: usize,
+        row_len: usize,
+        count: usize,
+    );
+    // result = table with updates[i * row_len + c] added to row indices[i], repeated indices
+    // accumulate. an index that isn't a row of the table panics on the CPU, the Vulkan backend
+    // skips it
+    #[allow(clippy::too_many_arguments)]
+    fn scatter_add(
+        &self,
+        table: &BufferHandle,
+        indices: &BufferHandle,
+        updates: &BufferHandle,
+        result: &BufferHandle,
+        rows: usize,
+        row_len: usize,
+        count: usize,
+    );
+    // result[i] = activation(a[i] + bias[i % row_len])
+    fn bias_activation(
+        &self,
+        a: &BufferHandle,
+        bias: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        row_len: usize,
+        activation: Activation,
+    );
+    // result[i] = grad[i] * activation'(pre_activation[i])
+    fn activation_backward(
+        &self,
+        grad: &BufferHandle,
+        pre_activation: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        activation: Activation,
+    );
+    // size is the length of the input and the result, every row_len elements are one row
+    fn log_softmax(&self, a: &BufferHandle, result: &BufferHandle, size: usize, row_len: usize);
+    // output is the log_softmax result the gradient is taken of
+    fn log_softmax_backward(
+        &self,
+        grad: &BufferHandle,
+        output: &BufferHandle,
+        result: &BufferHandle,
+        size: usize,
+        row_len: usize,
+    );
     fn name(&self) -> &str;
+    // tells apart two backends of the same type, e.g. Vulkan backends on different GPUs,
+    // anything caching device buffers across backends should key on this rather than name()
+    fn instance_id(&self) -> usize;
+    // bytes of device memory chunked work may use at once, None means no limit
+    fn memory_budget(&self) -> Option<usize>;
 }
 
 #[derive(Debug, Clone)]
 pub struct BufferHandle {
     pub id: LazyBufferHandle,
-    pub size: usize,
+    pub size: usize, // in elements of dtype
+    pub dtype: DType,
+}
+// element type of a device buffer, so a buffer can't be read as a different type by accident
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum DType {
+    F32,
+}
+// result of x / 0 in divide. Inf is plain IEEE division, so 0 / 0 is still NaN there
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum DivByZero {
+    Nan,
+    #[default]
+    Inf,
+    Zero,
+}
+impl DivByZero {
+    pub fn divide(self, a: f32, b: f32) -> f32 {
+        match self {
+            DivByZero::Nan if b == 0.0 => f32::NAN,
+            DivByZero::Zero if b == 0.0 => 0.0,
+            _ => a / b,
+        }
+    }
+}
+// every op is implemented for f32 only, backends call this on all buffers an op touches
+pub fn check_dtypes(operation: &str, handles: &[&BufferHandle]) {
+    for handle in handles {
+        if handle.dtype != DType::F32 {
+            panic!(
+                "{} expects f32 buffers, buffer {:?} holds {:?}",
+                operation, handle.id, handle.dtype
+            );
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -123,6 +671,94 @@ thread_local! {
 thread_local! {
     static TENSOR_TO_BUFFERS: RefCell<HashMap<TensorId, Vec<LazyBufferHandle>>> = RefCell::new(HashMap::new());
 }
+// bumped every time parameters are written in place (optimizer steps, checkpoint loads). a
+// computed buffer realized under an older generation holds values from before that write
+thread_local! {
+    static PARAMETER_GENERATION: RefCell<usize> = const { RefCell::new(0) };
+    static REALIZED_GENERATION: RefCell<HashMap<LazyBufferHandle, usize>> = RefCell::new(HashMap::new());
+}
+pub fn mark_parameters_updated() {
+    PARAMETER_GENERATION.with_borrow_mut(|generation| *generation += 1);
+}
+// evaluate elementwise ops on constant operands while building the graph, see fold_constants
+thread_local! {
+    static CONSTANT_FOLDING: RefCell<bool> = const { RefCell::new(true) };
+}
+pub fn set_constant_folding(enabled: bool) {
+    CONSTANT_FOLDING.with_borrow_mut(|folding| *folding = enabled);
+}
+// when enabled, realize hands the storage of an intermediate to a later node of the same size
+// once nothing reads it anymore. afterwards only the root and retained buffers can be read,
+// REUSED_BUFFERS holds the ones whose values were overwritten
+thread_local! {
+    static BUFFER_REUSE: RefCell<bool> = const { RefCell::new(false) };
+    static RETAINED_BUFFERS: RefCell<HashSet<LazyBufferHandle>> = RefCell::new(HashSet::new());
+    static REUSED_BUFFERS: RefCell<HashSet<LazyBufferHandle>> = RefCell::new(HashSet::new());
+}
+// outputs of checkpointed subgraphs. the intermediates between a checkpoint and the data (or
+// earlier checkpoints) it's computed from lose their device storage after every realize
+thread_local! {
+    static CHECKPOINTS: RefCell<HashSet<LazyBufferHandle>> = RefCell::new(HashSet::new());
+}
+// realize order of each realized root. a Memset rewrites an existing node and can change the
+// graph under any root, so every cached order remembers the graph version it was computed at and
+// is only used while that is still the current one
+thread_local! {
+    static GRAPH_VERSION: RefCell<usize> = const { RefCell::new(0) };
+    static SCHEDULE_CACHE: RefCell<HashMap<LazyBufferHandle, (usize, Vec<LazyBufferHandle>)>> =
+        RefCell::new(HashMap::new());
+}
+pub fn set_buffer_reuse(enabled: bool) {
+    BUFFER_REUSE.with_borrow_mut(|reuse| *reuse = enabled);
+}
+// stops a realize_cancellable between two ops. clones share the flag, so it can be set from
+// another thread or from inside the graph, e.g. a Tensor::from_fn closure
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+// temporary buffers count down from just below LAZYBUFFER_HANDLE_NULL, so two of them never
+// share an id and lazy buffer ids, which count up from 0, are never reached
+static NEXT_TEMPORARY_ID: AtomicUsize = AtomicUsize::new(usize::MAX - 1);
+pub fn get_next_temporary_id() -> LazyBufferHandle {
+    LazyBufferHandle(NEXT_TEMPORARY_ID.fetch_sub(1, Ordering::Relaxed))
+}
+static NEXT_BACKEND_INSTANCE_ID: AtomicUsize = AtomicUsize::new(0);
+// unique per backend object, unlike name() which is the same for every backend of a type
+pub fn get_next_backend_instance_id() -> usize {
+    NEXT_BACKEND_INSTANCE_ID.fetch_add(1, Ordering::Relaxed)
+}
+// (ids handed out, buffers in the registry), see stats::stats
+pub(crate) fn buffer_counts() -> (usize, usize) {
+    (
+        NEXT_BUFFER_ID.with_borrow(|id| *id),
+        LAZYBUFFER_REGISTRY.with_borrow(|registry| registry.len()),
+    )
+}
+// forgets every lazy buffer of this thread and starts ids from 0 again, toggles are kept
+#[cfg(feature = "test-utils")]
+pub(crate) fn reset_buffers() {
+    LAZYBUFFER_REGISTRY.with_borrow_mut(|registry| registry.clear());
+    NEXT_BUFFER_ID.with_borrow_mut(|id| *id = 0);
+    SCRATCHPAD_CACHE.with_borrow_mut(|cache| cache.clear());
+    SCRATCH_PAD_OP_CACHE.with_borrow_mut(|cache| cache.clear());
+    TENSOR_TO_BUFFERS.with_borrow_mut(|buffers| buffers.clear());
+    PARAMETER_GENERATION.with_borrow_mut(|generation| *generation = 0);
+    REALIZED_GENERATION.with_borrow_mut(|realized| realized.clear());
+    RETAINED_BUFFERS.with_borrow_mut(|retained| retained.clear());
+    REUSED_BUFFERS.with_borrow_mut(|reused| reused.clear());
+    CHECKPOINTS.with_borrow_mut(|checkpoints| checkpoints.clear());
+    SCHEDULE_CACHE.with_borrow_mut(|cache| cache.clear());
+}
 pub fn get_next_buffer_id() -> LazyBufferHandle {
     let id = NEXT_BUFFER_ID.with_borrow_mut(|id| {
         let current = *id;
@@ -131,6 +767,160 @@ pub fn get_next_buffer_id() -> LazyBufferHandle {
     });
     LazyBufferHandle(id)
 }
+// the same op reading from f(operand) instead of each operand
+pub(crate) fn map_operands(
+    op: &LazyOp,
+    f: impl Fn(LazyBufferHandle) -> LazyBufferHandle,
+) -> LazyOp {
+    match op {
+        LazyOp::Creation(creation) => LazyOp::Creation(creation.clone()),
+        LazyOp::Clear(a) => LazyOp::Clear(f(*a)),
+        LazyOp::Add(a, b) => LazyOp::Add(f(*a), f(*b)),
+        LazyOp::Subtract(a, b) => LazyOp::Subtract(f(*a), f(*b)),
+        LazyOp::Multiply(a, b) => LazyOp::Multiply(f(*a), f(*b)),
+        LazyOp::Divide(a, b) => LazyOp::Divide(f(*a), f(*b)),
+        LazyOp::Memset(a, b) => LazyOp::Memset(f(*a), f(*b)),
+        LazyOp::CumSum(a, reverse) => LazyOp::CumSum(f(*a), *reverse),
+        LazyOp::Where(cond, a, b) => LazyOp::Where(f(*cond), f(*a), f(*b)),
+        LazyOp::Transpose(a, rows, cols) => LazyOp::Transpose(f(*a), *rows, *cols),
+        LazyOp::TriangleMask(a, cols, diagonal, upper) => {
+            LazyOp::TriangleMask(f(*a), *cols, *diagonal, *upper)
+        }
+        LazyOp::BatchTranspose(a, batch, rows, cols) => {
+            LazyOp::BatchTranspose(f(*a), *batch, *rows, *cols)
+        }
+        LazyOp::MatMul(a, b, m, k, n) => LazyOp::MatMul(f(*a), f(*b), *m, *k, *n),
+        LazyOp::MaxPool1d(a, kernel, stride) => LazyOp::MaxPool1d(f(*a), *kernel, *stride),
+        LazyOp::MaxPool1dBackward(a, grad, kernel, stride) => {
+            LazyOp::MaxPool1dBackward(f(*a), f(*grad), *kernel, *stride)
+        }
+        LazyOp::InterpolateLinear(a, len) => LazyOp::InterpolateLinear(f(*a), *len),
+        LazyOp::InterpolateLinearBackward(a, len) => LazyOp::InterpolateLinearBackward(f(*a), *len),
+        LazyOp::Unary(a, unary) => LazyOp::Unary(f(*a), *unary),
+        LazyOp::Affine(a, scale, shift) => LazyOp::Affine(f(*a), *scale, *shift),
+        LazyOp::ScaleGrad(a, factor) => LazyOp::ScaleGrad(f(*a), *factor),
+        LazyOp::Sum(a) => LazyOp::Sum(f(*a)),
+        LazyOp::Expand(a, len) => LazyOp::Expand(f(*a), *len),
+        LazyOp::RepeatInterleave(a, n) => LazyOp::RepeatInterleave(f(*a), *n),
+        LazyOp::RepeatInterleaveBackward(a, n) => LazyOp::RepeatInterleaveBackward(f(*a), *n),
+        LazyOp::BroadcastTo(a, n, inner) => LazyOp::BroadcastTo(f(*a), *n, *inner),
+        LazyOp::BroadcastToBackward(a, n, inner) => LazyOp::BroadcastToBackward(f(*a), *n, *inner),
+        LazyOp::Pad(a, left, right) => LazyOp::Pad(f(*a), *left, *right),
+        LazyOp::Slice(a, start, len) => LazyOp::Slice(f(*a), *start, *len),
+        LazyOp::Conv1d(a, b, stride) => LazyOp::Conv1d(f(*a), f(*b), *stride),
+        LazyOp::Conv1dInputGrad(a, b, stride, len) => {
+            LazyOp::Conv1dInputGrad(f(*a), f(*b), *stride, *len)
+        }
+        LazyOp::Conv1dKernelGrad(a, b, stride, len) => {
+            LazyOp::Conv1dKernelGrad(f(*a), f(*b), *stride, *len)
+        }
+        LazyOp::Gather(a, indices, row_len) => LazyOp::Gather(f(*a), f(*indices), *row_len),
+        LazyOp::GatherBackward(a, indices, rows, row_len) => {
+            LazyOp::GatherBackward(f(*a), f(*indices), *rows, *row_len)
+        }
+        LazyOp::ScatterAdd(a, indices, updates, row_len) => {
+            LazyOp::ScatterAdd(f(*a), f(*indices), f(*updates), *row_len)
+        }
+        LazyOp::LogSoftmax(a, row_len) => LazyOp::LogSoftmax(f(*a), *row_len),
+        LazyOp::LogSoftmaxBackward(a, b, row_len) => {
+            LazyOp::LogSoftmaxBackward(f(*a), f(*b), *row_len)
+        }
+        LazyOp::BiasActivation(a, b, row_len, activation) => {
+            LazyOp::BiasActivation(f(*a), f(*b), *row_len, *activation)
+        }
+        LazyOp::ActivationBackward(a, b, activation) => {
+            LazyOp::ActivationBackward(f(*a), f(*b), *activation)
+        }
+    }
+}
+// random values for a Random or Uniform creation, on the backend's side if it can
+fn fill_random(
+    backend: &dyn Backend,
+    handle: &BufferHandle,
+    seed: u64,
+    distribution: Distribution,
+    size: usize,
+) {
+    if backend.fill_random(handle, seed, distribution, size) {
+        return;
+    }
+    let key = stream_key(seed);
+    let mut values = (0..size).map(|i| sample(key, i, distribution));
+    backend.upload_iter(&mut values, handle, size);
+}
+// the computed nodes between the checkpoints in deps and the data or earlier checkpoints they are
+// computed from. the root and retained buffers are left out, their values are still wanted
+fn checkpointed_intermediates(
+    root: LazyBufferHandle,
+    deps: &HashMap<LazyBufferHandle, LazyBuffer>,
+) -> HashSet<LazyBufferHandle> {
+    let checkpoints = CHECKPOINTS.with_borrow(|checkpoints| checkpoints.clone());
+    let mut stack: Vec<LazyBufferHandle> = deps
+        .iter()
+        .filter(|(handle, _)| checkpoints.contains(handle))
+        .flat_map(|(_, node)| operands(&node.operation))
+        .collect();
+    let mut inside = HashSet::new();
+    while let Some(handle) = stack.pop() {
+        let node = deps.get(&handle).unwrap();
+        let kept = handle == root
+            || checkpoints.contains(&handle)
+            || RETAINED_BUFFERS.with_borrow(|retained| retained.contains(&handle));
+        let data = matches!(
+            node.operation,
+            LazyOp::Creation(_) | LazyOp::Clear(_) | LazyOp::Memset(_, _)
+        );
+        if kept || data || !inside.insert(handle) {
+            continue;
+        }
+        stack.extend(operands(&node.operation));
+    }
+    inside
+}
+// buffers the op reads from, every traversal of the graph goes through this. Clear and
+// Memset rewrite their node a in place, so a is the node itself and not an operand: Clear
+// reads nothing and Memset only reads b
+fn operands(op: &LazyOp) -> Vec<LazyBufferHandle> {
+    match op {
+        LazyOp::Creation(_) | LazyOp::Clear(_) => vec![],
+        LazyOp::Memset(_, b) => vec![*b],
+        LazyOp::CumSum(a, _)
+        | LazyOp::Transpose(a, _, _)
+        | LazyOp::TriangleMask(a, _, _, _)
+        | LazyOp::BatchTranspose(a, _, _, _)
+        | LazyOp::MaxPool1d(a, _, _)
+        | LazyOp::InterpolateLinear(a, _)
+        | LazyOp::InterpolateLinearBackward(a, _)
+        | LazyOp::Unary(a, _)
+        | LazyOp::Affine(a, _, _)
+        | LazyOp::ScaleGrad(a, _)
+        | LazyOp::Sum(a)
+        | LazyOp::Expand(a, _)
+        | LazyOp::RepeatInterleave(a, _)
+        | LazyOp::RepeatInterleaveBackward(a, _)
+        | LazyOp::BroadcastTo(a, _, _)
+        | LazyOp::BroadcastToBackward(a, _, _)
+        | LazyOp::LogSoftmax(a, _)
+        | LazyOp::Pad(a, _, _)
+        | LazyOp::Slice(a, _, _) => vec![*a],
+        LazyOp::Add(a, b)
+        | LazyOp::Subtract(a, b)
+        | LazyOp::Multiply(a, b)
+        | LazyOp::Divide(a, b)
+        | LazyOp::MatMul(a, b, _, _, _)
+        | LazyOp::MaxPool1dBackward(a, b, _, _)
+        | LazyOp::Conv1d(a, b, _)
+        | LazyOp::Conv1dInputGrad(a, b, _, _)
+        | LazyOp::Conv1dKernelGrad(a, b, _, _)
+        | LazyOp::Gather(a, b, _)
+        | LazyOp::GatherBackward(a, b, _, _)
+        | LazyOp::LogSoftmaxBackward(a, b, _)
+        | LazyOp::BiasActivation(a, b, _, _)
+        | LazyOp::ActivationBackward(a, b, _) => vec![*a, *b],
+        LazyOp::Where(cond, a, b) => vec![*cond, *a, *b],
+        LazyOp::ScatterAdd(a, indices, updates, _) => vec![*a, *indices, *updates],
+    }
+}
 fn calculate_data_hash(data: &[f32]) -> usize {
     let mut hasher = DefaultHasher::new();
     for value in data {
@@ -154,6 +944,76 @@ impl LazyBuffer {
         });
         id
     }
+    // data computed from the element index when realized, no host copy is made
+    pub fn from_fn(
+        tensor_id: TensorId,
+        size: usize,
+        f: impl Fn(usize) -> f32 + 'static,
+    ) -> LazyBufferHandle {
+        let id = get_next_buffer_id();
+        let buffer = LazyBuffer {
+            size,
+            operation: LazyOp::Creation(CreationType::Generated(Generator(Rc::new(f)))),
+            device_buffer: None,
+            id,
+            kind: LazybufferType::TensorData(tensor_id),
+        };
+        LAZYBUFFER_REGISTRY.with_borrow_mut(|registry| {
+            registry.push(buffer);
+        });
+        id
+    }
+    // every element set to value on the device when realized, e.g. zeroed gradients
+    pub fn filled(tensor_id: TensorId, size: usize, value: f32) -> LazyBufferHandle {
+        let id = get_next_buffer_id();
+        let buffer = LazyBuffer {
+            size,
+            operation: LazyOp::Creation(CreationType::Filled(value)),
+            device_buffer: None,
+            id,
+            kind: LazybufferType::TensorData(tensor_id),
+        };
+        LAZYBUFFER_REGISTRY.with_borrow_mut(|registry| {
+            registry.push(buffer);
+        });
+        id
+    }
+    // data drawn when realized. tensor_id None makes it a scratch buffer, the stream comes from
+    // random::seed
+    pub fn random(
+        tensor_id: Option<TensorId>,
+        size: usize,
+        creation: impl FnOnce(u64) -> CreationType,
+    ) -> LazyBufferHandle {
+        let id = get_next_buffer_id();
+        let buffer = LazyBuffer {
+            size,
+            operation: LazyOp::Creation(creation(next_stream_seed())),
+            device_buffer: None,
+            id,
+            kind: match tensor_id {
+                Some(tensor_id) => LazybufferType::TensorData(tensor_id),
+                None => LazybufferType::Scratch,
+            },
+        };
+        LAZYBUFFER_REGISTRY.with_borrow_mut(|registry| {
+            registry.push(buffer);
+        });
+        id
+    }
+    // registers a scratch node under an id taken from get_next_buffer_id, without going through
+    // the caches. ids have to be pushed in the order they were taken
+    pub(crate) fn push_scratch(id: LazyBufferHandle, size: usize, operation: LazyOp) {
+        LAZYBUFFER_REGISTRY.with_borrow_mut(|registry| {
+            registry.push(LazyBuffer {
+                size,
+                operation,
+                device_buffer: None,
+                id,
+                kind: LazybufferType::Scratch,
+            });
+        });
+    }
     // should be exclusively used for temporary buffers that are not directly linked to any tensor
     pub fn scratch(data: Vec<f32>) -> LazyBufferHandle {
         let size = data.len();
@@ -206,25 +1066,10 @@ impl LazyBuffer {
                 }
             }
         }
-        let size = match &op {
-            LazyOp::Add(a, b)
-            | LazyOp::Subtract(a, b)
-            | LazyOp::Multiply(a, b)
-            | LazyOp::Divide(a, b)
-            | LazyOp::Memset(a, b) => {
-                let a_size =
-                    LAZYBUFFER_REGISTRY.with_borrow(|registry| registry.get(a.0).unwrap().size);
-                let b_size =
-                    LAZYBUFFER_REGISTRY.with_borrow(|registry| registry.get(b.0).unwrap().size);
-                if a_size != b_size {
-                    panic!("Size mismatch in operation: {} vs {}", a_size, b_size);
-                }
-                a_size
-            }
-            _ => {
-                panic!("Unsupported operation for size calculation: {:?}", op);
-            }
-        };
+        if let Some(data) = Self::fold_constants(&op) {
+            return LazyBuffer::new(tensor_id, data);
+        }
+        let size = Self::op_size(&op);
         match &op {
             LazyOp::Memset(a, b) => {
                 let buffer = LazyBuffer {
@@ -237,6 +1082,7 @@ impl LazyBuffer {
                 LAZYBUFFER_REGISTRY.with_borrow_mut(|registry| {
                     registry[a.0] = buffer;
                 });
+                GRAPH_VERSION.with_borrow_mut(|version| *version += 1);
                 return *a;
             }
             _ => {
@@ -270,30 +1116,10 @@ impl LazyBuffer {
                 return cached_handle;
             }
         }
-        let size = match &op {
-            LazyOp::Creation(CreationType::RawData(data)) => data.len(),
-            LazyOp::Clear(a) => {
-                let a_size =
-                    LAZYBUFFER_REGISTRY.with_borrow(|registry| registry.get(a.0).unwrap().size);
-                a_size
-            }
-            LazyOp::Add(a, b)
-            | LazyOp::Subtract(a, b)
-            | LazyOp::Multiply(a, b)
-            | LazyOp::Divide(a, b) => {
-                let a_size =
-                    LAZYBUFFER_REGISTRY.with_borrow(|registry| registry.get(a.0).unwrap().size);
-                let b_size =
-                    LAZYBUFFER_REGISTRY.with_borrow(|registry| registry.get(b.0).unwrap().size);
-                if a_size != b_size {
-                    panic!("Size mismatch in operation: {} vs {}", a_size, b_size);
-                }
-                a_size
-            }
-            _ => {
-                panic!("Unsupported operation for size calculation: {:?}", op);
-            }
-        };
+        if let Some(data) = Self::fold_constants(&op) {
+            return LazyBuffer::scratch(data);
+        }
+        let size = Self::op_size(&op);
         let id = get_next_buffer_id();
 
         let buffer = LazyBuffer {
@@ -312,6 +1138,363 @@ impl LazyBuffer {
         }
         id
     }
+    // the result of an elementwise op whose operands are both unrealized data that can never
+    // change (scratch buffers or tensors without grad), computed right away on the host
+    fn fold_constants(op: &LazyOp) -> Option<Vec<f32>> {
+        let (a, b) = match op {
+            LazyOp::Add(a, b)
+            | LazyOp::Subtract(a, b)
+            | LazyOp::Multiply(a, b)
+            | LazyOp::Divide(a, b) => (*a, *b),
+            _ => return None,
+        };
+        if !CONSTANT_FOLDING.with_borrow(|folding| *folding) {
+            return None;
+        }
+        let constant_data = |handle: LazyBufferHandle| {
+            let buffer = LAZYBUFFER_REGISTRY.with_borrow(|registry| registry[handle.0].clone());
+            let constant = match buffer.kind {
+                LazybufferType::Scratch => true,
+                LazybufferType::TensorData(id) => !Tensor::requires_grad_for(id),
+            };
+            match buffer.operation {
+                LazyOp::Creation(CreationType::RawData(data)) if constant => Some(data),
+                _ => None,
+            }
+        };
+        let (a_data, b_data) = (constant_data(a)?, constant_data(b)?);
+        // the result of a division by zero depends on the backend's DivByZero policy
+        if matches!(op, LazyOp::Divide(_, _)) && b_data.contains(&0.0) {
+            return None;
+        }
+        if a_data.len() != b_data.len() {
+            panic!(
+                "Size mismatch in operation: {} vs {}",
+                a_data.len(),
+                b_data.len()
+            );
+        }
+        let apply: fn(f32, f32) -> f32 = match op {
+            LazyOp::Add(_, _) => |x, y| x + y,
+            LazyOp::Subtract(_, _) => |x, y| x - y,
+            LazyOp::Multiply(_, _) => |x, y| x * y,
+            _ => |x, y| x / y,
+        };
+        Some(
+            a_data
+                .iter()
+                .zip(b_data.iter())
+                .map(|(x, y)| apply(*x, *y))
+                .collect(),
+        )
+    }
+    // the number of elements op produces, panics if its operands don't fit together
+    fn op_size(op: &LazyOp) -> usize {
+        match op {
+            LazyOp::Creation(CreationType::RawData(data)) => data.len(),
+            LazyOp::Add(a, b)
+            | LazyOp::Subtract(a, b)
+            | LazyOp::Multiply(a, b)
+            | LazyOp::Divide(a, b)
+            | LazyOp::Memset(a, b) => {
+                let a_size =
+                    LAZYBUFFER_REGISTRY.with_borrow(|registry| registry.get(a.0).unwrap().size);
+                let b_size =
+                    LAZYBUFFER_REGISTRY.with_borrow(|registry| registry.get(b.0).unwrap().size);
+                if a_size != b_size {
+                    panic!("Size mismatch in operation: {} vs {}", a_size, b_size);
+                }
+                a_size
+            }
+            LazyOp::Clear(a) | LazyOp::CumSum(a, _) => {
+                LAZYBUFFER_REGISTRY.with_borrow(|registry| registry.get(a.0).unwrap().size)
+            }
+            LazyOp::Where(cond, a, b) => Self::where_size(*cond, *a, *b),
+            LazyOp::Transpose(a, rows, cols) => Self::transpose_size(*a, *rows, *cols),
+            LazyOp::TriangleMask(a, cols, _, _) => {
+                if *cols == 0 || a.get_size() % cols != 0 {
+                    panic!(
+                        "Buffer of size {} doesn't split into rows of {}",
+                        a.get_size(),
+                        cols
+                    );
+                }
+                a.get_size()
+            }
+            LazyOp::BatchTranspose(a, batch, rows, cols) => {
+                Self::transpose_size(*a, batch * rows, *cols)
+            }
+            LazyOp::MatMul(a, b, m, k, n) => Self::matmul_size(*a, *b, *m, *k, *n),
+            LazyOp::MaxPool1d(a, kernel, stride) => Self::max_pool1d_size(*a, *kernel, *stride),
+            LazyOp::Conv1d(a, b, stride) => Self::conv1d_size(a.get_size(), b.get_size(), *stride),
+            LazyOp::Gather(a, indices, row_len) => {
+                Self::gather_size(a.get_size(), indices.get_size(), *row_len)
+            }
+            LazyOp::GatherBackward(a, indices, rows, row_len) => {
+                let output_size = Self::gather_size(rows * row_len, indices.get_size(), *row_len);
+                if a.get_size() != output_size {
+                    panic!(
+                        "Size mismatch in gather backward: {} vs {}",
+                        a.get_size(),
+                        output_size
+                    );
+                }
+                rows * row_len
+            }
+            LazyOp::ScatterAdd(a, indices, updates, row_len) => Self::scatter_add_size(
+                a.get_size(),
+                indices.get_size(),
+                updates.get_size(),
+                *row_len,
+            ),
+            LazyOp::LogSoftmax(a, row_len) => {
+                if *row_len == 0 || a.get_size() % row_len != 0 {
+                    panic!(
+                        "Buffer of size {} doesn't split into rows of {}",
+                        a.get_size(),
+                        row_len
+                    );
+                }
+                a.get_size()
+            }
+            LazyOp::LogSoftmaxBackward(a, b, _) => {
+                if a.get_size() != b.get_size() {
+                    panic!(
+                        "Size mismatch in log_softmax backward: {} vs {}",
+                        a.get_size(),
+                        b.get_size()
+                    );
+                }
+                a.get_size()
+            }
+            LazyOp::BiasActivation(a, b, row_len, _) => {
+                if *row_len == 0 || a.get_size() % row_len != 0 || b.get_size() != *row_len {
+                    panic!(
+                        "Bias of size {} doesn't fit rows of {} in a buffer of size {}",
+                        b.get_size(),
+                        row_len,
+                        a.get_size()
+                    );
+                }
+                a.get_size()
+            }
+            LazyOp::ActivationBackward(a, b, _) => {
+                if a.get_size() != b.get_size() {
+                    panic!(
+                        "Size mismatch in activation backward: {} vs {}",
+                        a.get_size(),
+                        b.get_size()
+                    );
+                }
+                a.get_size()
+            }
+            LazyOp::Conv1dInputGrad(a, b, stride, len) => {
+                let output_size = Self::conv1d_size(*len, b.get_size(), *stride);
+                if a.get_size() != output_size {
+                    panic!(
+                        "Size mismatch in conv1d backward: {} vs {}",
+                        a.get_size(),
+                        output_size
+                    );
+                }
+                *len
+            }
+            LazyOp::Conv1dKernelGrad(a, b, stride, len) => {
+                let output_size = Self::conv1d_size(a.get_size(), *len, *stride);
+                if b.get_size() != output_size {
+                    panic!(
+                        "Size mismatch in conv1d backward: {} vs {}",
+                        b.get_size(),
+                        output_size
+                    );
+                }
+                *len
+            }
+            LazyOp::MaxPool1dBackward(a, b, kernel, stride) => {
+                let a_size = a.get_size();
+                if b.get_size() != Self::max_pool1d_size(*a, *kernel, *stride) {
+                    panic!(
+                        "Size mismatch in max_pool1d backward: {} vs {}",
+                        b.get_size(),
+                        Self::max_pool1d_size(*a, *kernel, *stride)
+                    );
+                }
+                a_size
+            }
+            LazyOp::InterpolateLinear(a, len) | LazyOp::InterpolateLinearBackward(a, len) => {
+                if a.get_size() == 0 || *len == 0 {
+                    panic!("Cannot interpolate size {} to {}", a.get_size(), len);
+                }
+                *len
+            }
+            LazyOp::Unary(a, _) | LazyOp::Affine(a, _, _) | LazyOp::ScaleGrad(a, _) => a.get_size(),
+            LazyOp::Sum(_) => 1,
+            LazyOp::Expand(a, len) => {
+                if a.get_size() != 1 {
+                    panic!(
+                        "Only single elements can be expanded, got size {}",
+                        a.get_size()
+                    );
+                }
+                *len
+            }
+            LazyOp::RepeatInterleave(a, n) => a.get_size() * n,
+            LazyOp::RepeatInterleaveBackward(a, n) => {
+                if *n == 0 || a.get_size() % n != 0 {
+                    panic!(
+                        "Gradient of size {} doesn't split into groups of {}",
+                        a.get_size(),
+                        n
+                    );
+                }
+                a.get_size() / n
+            }
+            LazyOp::BroadcastTo(a, n, inner) => {
+                if *inner == 0 || a.get_size() % inner != 0 {
+                    panic!(
+                        "Buffer of size {} doesn't split into blocks of {}",
+                        a.get_size(),
+                        inner
+                    );
+                }
+                a.get_size() * n
+            }
+            LazyOp::BroadcastToBackward(a, n, inner) => {
+                if *n == 0 || *inner == 0 || a.get_size() % (n * inner) != 0 {
+                    panic!(
+                        "Gradient of size {} doesn't split into {} copies of blocks of {}",
+                        a.get_size(),
+                        n,
+                        inner
+                    );
+                }
+                a.get_size() / n
+            }
+            LazyOp::Pad(a, left, right) => left + a.get_size() + right,
+            LazyOp::Slice(a, start, len) => {
+                if start + len > a.get_size() {
+                    panic!(
+                        "Slice {}..{} out of bounds for size {}",
+                        start,
+                        start + len,
+                        a.get_size()
+                    );
+                }
+                *len
+            }
+            _ => {
+                panic!("Unsupported operation for size calculation: {:?}", op);
+            }
+        }
+    }
+    fn where_size(cond: LazyBufferHandle, a: LazyBufferHandle, b: LazyBufferHandle) -> usize {
+        let (cond_size, a_size, b_size) = LAZYBUFFER_REGISTRY.with_borrow(|registry| {
+            (
+                registry.get(cond.0).unwrap().size,
+                registry.get(a.0).unwrap().size,
+                registry.get(b.0).unwrap().size,
+            )
+        });
+        if cond_size != a_size || a_size != b_size {
+            panic!(
+                "Size mismatch in operation: {} vs {} vs {}",
+                cond_size, a_size, b_size
+            );
+        }
+        a_size
+    }
+    // a tensor computed since the latest parameter update whose storage wasn't handed to another
+    // node, nor taken from one whose next realize would write into it again
+    fn holds_result(&self) -> bool {
+        let computed = !matches!(
+            self.operation,
+            LazyOp::Creation(_) | LazyOp::Clear(_) | LazyOp::Memset(_, _)
+        );
+        let generation = PARAMETER_GENERATION.with_borrow(|generation| *generation);
+        matches!(self.kind, LazybufferType::TensorData(_))
+            && computed
+            && self
+                .device_buffer
+                .as_ref()
+                .is_some_and(|handle| handle.id == self.id)
+            && !self.id.is_reused()
+            && REALIZED_GENERATION
+                .with_borrow(|realized| realized.get(&self.id) == Some(&generation))
+    }
+    fn transpose_size(a: LazyBufferHandle, rows: usize, cols: usize) -> usize {
+        let a_size = LAZYBUFFER_REGISTRY.with_borrow(|registry| registry.get(a.0).unwrap().size);
+        if a_size != rows * cols {
+            panic!(
+                "Size mismatch in transpose: {} vs {}x{}",
+                a_size, rows, cols
+            );
+        }
+        a_size
+    }
+    fn matmul_size(
+        a: LazyBufferHandle,
+        b: LazyBufferHandle,
+        m: usize,
+        k: usize,
+        n: usize,
+    ) -> usize {
+        let (a_size, b_size) = LAZYBUFFER_REGISTRY.with_borrow(|registry| {
+            (
+                registry.get(a.0).unwrap().size,
+                registry.get(b.0).unwrap().size,
+            )
+        });
+        if a_size != m * k || b_size != k * n {
+            panic!(
+                "Size mismatch in matmul: {} vs {}x{} and {} vs {}x{}",
+                a_size, m, k, b_size, k, n
+            );
+        }
+        m * n
+    }
+    fn conv1d_size(input_size: usize, kernel_size: usize, stride: usize) -> usize {
+        if kernel_size == 0 || stride == 0 || kernel_size > input_size {
+            panic!(
+                "Invalid conv1d of size {} with kernel {} and stride {}",
+                input_size, kernel_size, stride
+            );
+        }
+        (input_size - kernel_size) / stride + 1
+    }
+    fn gather_size(table_size: usize, count: usize, row_len: usize) -> usize {
+        if row_len == 0 || !table_size.is_multiple_of(row_len) {
+            panic!(
+                "Invalid gather from a table of size {} with rows of {}",
+                table_size, row_len
+            );
+        }
+        count * row_len
+    }
+    fn scatter_add_size(
+        table_size: usize,
+        count: usize,
+        updates_size: usize,
+        row_len: usize,
+    ) -> usize {
+        let expected = Self::gather_size(table_size, count, row_len);
+        if updates_size != expected {
+            panic!(
+                "Size mismatch in scatter add: {} updates for {} indices with rows of {}",
+                updates_size, count, row_len
+            );
+        }
+        table_size
+    }
+    fn max_pool1d_size(a: LazyBufferHandle, kernel: usize, stride: usize) -> usize {
+        let a_size = a.get_size();
+        if kernel == 0 || stride == 0 || kernel > a_size {
+            panic!(
+                "Invalid max_pool1d of size {} with kernel {} and stride {}",
+                a_size, kernel, stride
+            );
+        }
+        (a_size - kernel) / stride + 1
+    }
     pub fn get_comp_graph_viz(&self) -> String {
         match &self.operation {
             LazyOp::Memset(a, b) => {
@@ -329,15 +1512,158 @@ impl LazyBuffer {
             LazyOp::Divide(a, b) => {
                 format!("({}/{})", a.get_comp_graph_viz(), b.get_comp_graph_viz())
             }
+            LazyOp::CumSum(a, false) => format!("cumsum({})", a.get_comp_graph_viz()),
+            LazyOp::CumSum(a, true) => format!("rcumsum({})", a.get_comp_graph_viz()),
+            LazyOp::Where(cond, a, b) => format!(
+                "where({}, {}, {})",
+                cond.get_comp_graph_viz(),
+                a.get_comp_graph_viz(),
+                b.get_comp_graph_viz()
+            ),
+            LazyOp::Transpose(a, _, _) => format!("{}^T", a.get_comp_graph_viz()),
+            LazyOp::TriangleMask(a, _, diagonal, true) => {
+                format!("triu({}, {})", a.get_comp_graph_viz(), diagonal)
+            }
+            LazyOp::TriangleMask(a, _, diagonal, false) => {
+                format!("tril({}, {})", a.get_comp_graph_viz(), diagonal)
+            }
+            LazyOp::BatchTranspose(a, batch, _, _) => {
+                format!("{}^T[{}]", a.get_comp_graph_viz(), batch)
+            }
+            LazyOp::MatMul(a, b, _, _, _) => {
+                format!("({}@{})", a.get_comp_graph_viz(), b.get_comp_graph_viz())
+            }
+            LazyOp::MaxPool1d(a, kernel, stride) => {
+                format!(
+                    "maxpool({}, {}, {})",
+                    a.get_comp_graph_viz(),
+                    kernel,
+                    stride
+                )
+            }
+            LazyOp::Conv1d(a, b, stride) => format!(
+                "conv1d({}, {}, {})",
+                a.get_comp_graph_viz(),
+                b.get_comp_graph_viz(),
+                stride
+            ),
+            LazyOp::Conv1dInputGrad(a, b, stride, _) => format!(
+                "conv1d_input_grad({}, {}, {})",
+                a.get_comp_graph_viz(),
+                b.get_comp_graph_viz(),
+                stride
+            ),
+            LazyOp::Conv1dKernelGrad(a, b, stride, _) => format!(
+                "conv1d_kernel_grad({}, {}, {})",
+                a.get_comp_graph_viz(),
+                b.get_comp_graph_viz(),
+                stride
+            ),
+            LazyOp::Gather(a, indices, _) => format!(
+                "gather({}, {})",
+                a.get_comp_graph_viz(),
+                indices.get_comp_graph_viz()
+            ),
+            LazyOp::GatherBackward(a, indices, rows, _) => format!(
+                "gather_grad({}, {}, {})",
+                a.get_comp_graph_viz(),
+                indices.get_comp_graph_viz(),
+                rows
+            ),
+            LazyOp::ScatterAdd(a, indices, updates, _) => format!(
+                "scatter_add({}, {}, {})",
+                a.get_comp_graph_viz(),
+                indices.get_comp_graph_viz(),
+                updates.get_comp_graph_viz()
+            ),
+            LazyOp::LogSoftmax(a, row_len) => {
+                format!("log_softmax({}, {})", a.get_comp_graph_viz(), row_len)
+            }
+            LazyOp::LogSoftmaxBackward(a, b, row_len) => format!(
+                "log_softmax_grad({}, {}, {})",
+                a.get_comp_graph_viz(),
+                b.get_comp_graph_viz(),
+                row_len
+            ),
+            LazyOp::BiasActivation(a, b, _, activation) => format!(
+                "{:?}({} + {})",
+                activation,
+                a.get_comp_graph_viz(),
+                b.get_comp_graph_viz()
+            ),
+            LazyOp::ActivationBackward(a, b, activation) => format!(
+                "{:?}_grad({}, {})",
+                activation,
+                a.get_comp_graph_viz(),
+                b.get_comp_graph_viz()
+            ),
+            LazyOp::MaxPool1dBackward(a, b, kernel, stride) => format!(
+                "maxpool_grad({}, {}, {}, {})",
+                a.get_comp_graph_viz(),
+                b.get_comp_graph_viz(),
+                kernel,
+                stride
+            ),
+            LazyOp::InterpolateLinear(a, len) => {
+                format!("interpolate({}, {})", a.get_comp_graph_viz(), len)
+            }
+            LazyOp::InterpolateLinearBackward(a, len) => {
+                format!("interpolate_grad({}, {})", a.get_comp_graph_viz(), len)
+            }
+            LazyOp::RepeatInterleave(a, n) => {
+                format!("repeat_interleave({}, {})", a.get_comp_graph_viz(), n)
+            }
+            LazyOp::RepeatInterleaveBackward(a, n) => {
+                format!("repeat_interleave_grad({}, {})", a.get_comp_graph_viz(), n)
+            }
+            LazyOp::BroadcastTo(a, n, inner) => {
+                format!("broadcast_to({}, {}, {})", a.get_comp_graph_viz(), n, inner)
+            }
+            LazyOp::BroadcastToBackward(a, n, inner) => {
+                format!(
+                    "broadcast_to_grad({}, {}, {})",
+                    a.get_comp_graph_viz(),
+                    n,
+                    inner
+                )
+            }
+            LazyOp::Pad(a, left, right) => {
+                format!("pad({}, {}, {})", a.get_comp_graph_viz(), left, right)
+            }
+            LazyOp::Slice(a, start, len) => {
+                format!("slice({}, {}, {})", a.get_comp_graph_viz(), start, len)
+            }
+            LazyOp::Unary(a, UnaryOp::Abs) => format!("abs({})", a.get_comp_graph_viz()),
+            LazyOp::Unary(a, UnaryOp::Sqrt) => format!("sqrt({})", a.get_comp_graph_viz()),
+            LazyOp::Unary(a, UnaryOp::Sign) => format!("sign({})", a.get_comp_graph_viz()),
+            LazyOp::Unary(a, UnaryOp::Neg) => format!("-{}", a.get_comp_graph_viz()),
+            LazyOp::Unary(a, UnaryOp::Copy) => format!("copy({})", a.get_comp_graph_viz()),
+            LazyOp::Unary(a, UnaryOp::Sin) => format!("sin({})", a.get_comp_graph_viz()),
+            LazyOp::Unary(a, UnaryOp::Cos) => format!("cos({})", a.get_comp_graph_viz()),
+            LazyOp::Unary(a, UnaryOp::Round) => format!("round({})", a.get_comp_graph_viz()),
+            LazyOp::Unary(a, UnaryOp::Floor) => format!("floor({})", a.get_comp_graph_viz()),
+            LazyOp::Unary(a, UnaryOp::Ceil) => format!("ceil({})", a.get_comp_graph_viz()),
+            LazyOp::Affine(a, scale, shift) => {
+                format!("({}*{}+{})", a.get_comp_graph_viz(), scale, shift)
+            }
+            LazyOp::ScaleGrad(a, factor) => {
+                format!("scale_grad({}, {})", a.get_comp_graph_viz(), factor)
+            }
+            LazyOp::Sum(a) => format!("sum({})", a.get_comp_graph_viz()),
+            LazyOp::Expand(a, len) => format!("expand({}, {})", a.get_comp_graph_viz(), len),
         }
     }
 
-    fn collect_dependencies(&self) -> HashMap<LazyBufferHandle, LazyBuffer> {
+    // with reuse_realized, tensors below the root that still hold a valid result from an
+    // earlier realize are collected as data and their own operands are left out, so e.g. a
+    // loss built fresh every iteration doesn't compute the realized tensors it reads again
+    fn collect_dependencies(&self, reuse_realized: bool) -> HashMap<LazyBufferHandle, LazyBuffer> {
         let mut deps = HashMap::new();
         let mut visited = HashSet::new();
 
         fn collect_recursive(
             current_id: LazyBufferHandle,
+            reuse_from: Option<LazyBufferHandle>,
             deps: &mut HashMap<LazyBufferHandle, LazyBuffer>,
             visited: &mut HashSet<LazyBufferHandle>,
         ) {
@@ -349,27 +1675,47 @@ impl LazyBuffer {
 
             let current = LAZYBUFFER_REGISTRY
                 .with_borrow(|registry| registry.get(current_id.0).unwrap().clone());
+            if reuse_from.is_some_and(|root| root != current_id) && current.holds_result() {
+                let realized = LazyBuffer {
+                    operation: LazyOp::Creation(CreationType::Created),
+                    ..current
+                };
+                deps.insert(current_id, realized);
+                return;
+            }
 
-            match &current.operation {
-                LazyOp::Creation(_) | LazyOp::Clear(_) => {
-                    deps.insert(current_id, current);
-                }
-                LazyOp::Add(a, b)
-                | LazyOp::Subtract(a, b)
-                | LazyOp::Multiply(a, b)
-                | LazyOp::Memset(a, b)
-                | LazyOp::Divide(a, b) => {
-                    collect_recursive(*a, deps, visited);
-                    collect_recursive(*b, deps, visited);
-                    deps.insert(current_id, current);
-                }
+            for operand in operands(&current.operation) {
+                collect_recursive(operand, reuse_from, deps, visited);
             }
+            deps.insert(current_id, current);
         }
 
-        collect_recursive(self.id, &mut deps, &mut visited);
+        let reuse_from = reuse_realized.then_some(self.id);
+        collect_recursive(self.id, reuse_from, &mut deps, &mut visited);
         deps
     }
 
+    fn schedule(
+        root: LazyBufferHandle,
+        deps: &HashMap<LazyBufferHandle, LazyBuffer>,
+    ) -> Vec<LazyBufferHandle> {
+        let version = GRAPH_VERSION.with_borrow(|version| *version);
+        let cached = SCHEDULE_CACHE.with_borrow(|cache| cache.get(&root).cloned());
+        if let Some((cached_version, order)) = cached {
+            // reused results cut the graph short, so the same root can come with fewer nodes
+            if cached_version == version
+                && order.len() == deps.len()
+                && order.iter().all(|id| deps.contains_key(id))
+            {
+                return order;
+            }
+        }
+        let order = Self::topological_sort(deps);
+        SCHEDULE_CACHE.with_borrow_mut(|cache| {
+            cache.insert(root, (version, order.clone()));
+        });
+        order
+    }
     fn topological_sort(deps: &HashMap<LazyBufferHandle, LazyBuffer>) -> Vec<LazyBufferHandle> {
         let mut result = Vec::new();
         let mut temp_mark = HashSet::new();
@@ -390,18 +1736,8 @@ impl LazyBuffer {
                 temp_mark.insert(node_id);
 
                 let node = deps.get(&node_id).unwrap();
-                match &node.operation {
-                    LazyOp::Creation(_) | LazyOp::Clear(_) => {}
-                    LazyOp::Add(a, b)
-                    | LazyOp::Subtract(a, b)
-                    | LazyOp::Multiply(a, b)
-                    | LazyOp::Divide(a, b) => {
-                        visit(*a, deps, temp_mark, perm_mark, result);
-                        visit(*b, deps, temp_mark, perm_mark, result);
-                    }
-                    LazyOp::Memset(_, b) => {
-                        visit(*b, deps, temp_mark, perm_mark, result);
-                    }
+                for operand in operands(&node.operation) {
+                    visit(operand, deps, temp_mark, perm_mark, result);
                 }
 
                 temp_mark.remove(&node_id);
@@ -424,28 +1760,95 @@ impl LazyBuffer {
         backend: &dyn Backend,
         to_host: bool,
         deps: HashMap<LazyBufferHandle, LazyBuffer>,
-    ) -> HashMap<LazyBufferHandle, BufferHandle> {
-        let order = Self::topological_sort(&deps);
+        checkpointed: &HashSet<LazyBufferHandle>,
+        cancel: Option<&CancellationToken>,
+    ) -> (
+        HashMap<LazyBufferHandle, BufferHandle>,
+        HashSet<LazyBufferHandle>,
+        bool,
+    ) {
+        let order = Self::schedule(self.id, &deps);
         let mut buffer_handles = HashMap::new();
+        let mut reused = HashSet::new();
 
-        for &id in &order {
+        let reuse = BUFFER_REUSE.with_borrow(|reuse| *reuse);
+        let mut last_read = HashMap::new();
+        for (position, id) in order.iter().enumerate() {
+            for operand in operands(&deps.get(id).unwrap().operation) {
+                last_read.insert(operand, position);
+            }
+        }
+        // data buffers keep their values across realizes and in-place ops write into their own
+        // storage, neither can give it away or take someone else's
+        let computed = |node: &LazyBuffer| {
+            !matches!(
+                node.operation,
+                LazyOp::Creation(_) | LazyOp::Clear(_) | LazyOp::Memset(_, _)
+            )
+        };
+        let mut free: Vec<BufferHandle> = Vec::new();
+        for (position, &id) in order.iter().enumerate() {
             let node = deps.get(&id).unwrap();
-            let handle = backend.allocate_buffer(id, node.size);
+            let recycled = free.iter().position(|handle| handle.size == node.size);
+            let handle = match recycled {
+                Some(index) if computed(node) => free.swap_remove(index),
+                _ => backend.allocate_buffer(id, node.size),
+            };
             buffer_handles.insert(id, handle);
+            // checkpointed intermediates give their storage away even with reuse off
+            for operand in operands(&node.operation) {
+                let retained = RETAINED_BUFFERS.with_borrow(|retained| retained.contains(&operand));
+                if (reuse || checkpointed.contains(&operand))
+                    && last_read[&operand] == position
+                    && operand != self.id
+                    && !retained
+                    && computed(deps.get(&operand).unwrap())
+                    && reused.insert(operand)
+                {
+                    free.push(buffer_handles[&operand].clone());
+                }
+            }
         }
 
         for &id in &order {
+            // nothing after this gets dispatched, the caller discards what was computed so far
+            if cancel.is_some_and(|token| token.is_cancelled()) {
+                return (buffer_handles, reused, true);
+            }
             let node = deps.get(&id).unwrap();
             let result_handle = buffer_handles.get(&id).unwrap();
 
             match &node.operation {
                 LazyOp::Creation(creation_type) => match creation_type {
-                    CreationType::Random => {
-                        backend.to_device(&vec![0.0; node.size], result_handle);
+                    CreationType::Random(stream) => {
+                        let distribution = Distribution::Normal;
+                        fill_random(backend, result_handle, *stream, distribution, node.size);
+                    }
+                    CreationType::Uniform(stream, low, high) => {
+                        let distribution = Distribution::Uniform(*low, *high);
+                        fill_random(backend, result_handle, *stream, distribution, node.size);
+                    }
+                    CreationType::RandomMask(stream, p) => {
+                        let mut rng = Rng::new(*stream);
+                        let mut values = (0..node.size).map(|_| {
+                            if rng.next_f32() < *p {
+                                0.0
+                            } else {
+                                1.0 / (1.0 - p)
+                            }
+                        });
+                        backend.upload_iter(&mut values, result_handle, node.size);
                     }
                     CreationType::RawData(data) => {
                         backend.to_device(&data, result_handle);
                     }
+                    CreationType::Filled(value) => {
+                        backend.fill(result_handle, *value, node.size);
+                    }
+                    CreationType::Generated(Generator(f)) => {
+                        let mut values = (0..node.size).map(|i| f(i));
+                        backend.upload_iter(&mut values, result_handle, node.size);
+                    }
                     CreationType::Created => {
                         // buffer has been created already reuse it using handle now
                         continue;
@@ -476,40 +1879,484 @@ impl LazyBuffer {
                     let b_handle = buffer_handles.get(&b).unwrap();
                     backend.memset(a_handle, b_handle, node.size);
                 }
+                LazyOp::CumSum(a, reverse) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    backend.cumsum(a_handle, result_handle, node.size, *reverse);
+                }
+                LazyOp::Where(cond, a, b) => {
+                    let cond_handle = buffer_handles.get(cond).unwrap();
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    let b_handle = buffer_handles.get(b).unwrap();
+                    backend.where_mask(cond_handle, a_handle, b_handle, result_handle, node.size);
+                }
+                LazyOp::Transpose(a, rows, cols) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    backend.transpose(a_handle, result_handle, *rows, *cols);
+                }
+                LazyOp::TriangleMask(a, cols, diagonal, upper) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    let rows = node.size / cols;
+                    backend.triangle_mask(a_handle, result_handle, rows, *cols, *diagonal, *upper);
+                }
+                LazyOp::BatchTranspose(a, batch, rows, cols) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    backend.batch_transpose(a_handle, result_handle, *batch, *rows, *cols);
+                }
+                LazyOp::MatMul(a, b, m, k, n) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    let b_handle = buffer_handles.get(b).unwrap();
+                    backend.matmul(a_handle, b_handle, result_handle, *m, *k, *n);
+                }
+                LazyOp::MaxPool1d(a, kernel, stride) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    let a_size = deps.get(a).unwrap().size;
+                    backend.max_pool1d(a_handle, result_handle, a_size, *kernel, *stride);
+                }
+                LazyOp::MaxPool1dBackward(a, b, kernel, stride) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    let b_handle = buffer_handles.get(b).unwrap();
+                    backend.max_pool1d_backward(
+                        a_handle,
+                        b_handle,
+                        result_handle,
+                        node.size,
+                        *kernel,
+                        *stride,
+                    );
+                }
+                LazyOp::InterpolateLinear(a, len) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    let a_size = deps.get(a).unwrap().size;
+                    backend.interpolate_linear(a_handle, result_handle, a_size, *len);
+                }
+                LazyOp::InterpolateLinearBackward(a, len) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    let a_size = deps.get(a).unwrap().size;
+                    backend.interpolate_linear_backward(a_handle, result_handle, *len, a_size);
+                }
+                LazyOp::Unary(a, unary) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    backend.unary(a_handle, result_handle, node.size, *unary);
+                }
+                LazyOp::Affine(a, scale, shift) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    backend.affine(a_handle, result_handle, node.size, *scale, *shift);
+                }
+                LazyOp::ScaleGrad(a, _) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    backend.unary(a_handle, result_handle, node.size, UnaryOp::Copy);
+                }
+                LazyOp::Sum(a) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    let a_size = deps.get(a).unwrap().size;
+                    backend.sum(a_handle, result_handle, a_size);
+                }
+                LazyOp::Expand(a, len) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    backend.expand(a_handle, result_handle, *len);
+                }
+                LazyOp::RepeatInterleave(a, n) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    backend.repeat_interleave(a_handle, result_handle, node.size, *n);
+                }
+                LazyOp::RepeatInterleaveBackward(a, n) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    backend.repeat_interleave_backward(a_handle, result_handle, node.size, *n);
+                }
+                LazyOp::BroadcastTo(a, n, inner) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    backend.broadcast_to(a_handle, result_handle, node.size, *n, *inner);
+                }
+                LazyOp::BroadcastToBackward(a, n, inner) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    backend.broadcast_to_backward(a_handle, result_handle, node.size, *n, *inner);
+                }
+                LazyOp::Pad(a, left, right) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    let a_size = deps.get(a).unwrap().size;
+                    backend.pad(a_handle, result_handle, a_size, *left, *right);
+                }
+                LazyOp::Slice(a, start, len) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    backend.slice(a_handle, result_handle, *start, *len);
+                }
+                LazyOp::Conv1d(a, b, stride) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    let b_handle = buffer_handles.get(b).unwrap();
+                    let (a_size, b_size) = (deps.get(a).unwrap().size, deps.get(b).unwrap().size);
+                    backend.conv1d(a_handle, b_handle, result_handle, a_size, b_size, *stride);
+                }
+                LazyOp::Conv1dInputGrad(a, b, stride, len) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    let b_handle = buffer_handles.get(b).unwrap();
+                    let b_size = deps.get(b).unwrap().size;
+                    backend.conv1d_input_grad(
+                        a_handle,
+                        b_handle,
+                        result_handle,
+                        *len,
+                        b_size,
+                        *stride,
+                    );
+                }
+                LazyOp::Conv1dKernelGrad(a, b, stride, len) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    let b_handle = buffer_handles.get(b).unwrap();
+                    let a_size = deps.get(a).unwrap().size;
+                    backend.conv1d_kernel_grad(
+                        a_handle,
+                        b_handle,
+                        result_handle,
+                        a_size,
+                        *len,
+                        *stride,
+                    );
+                }
+                LazyOp::Gather(a, indices, row_len) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    let indices_handle = buffer_handles.get(indices).unwrap();
+                    let rows = deps.get(a).unwrap().size / row_len;
+                    let count = deps.get(indices).unwrap().size;
+                    backend.gather(
+                        a_handle,
+                        indices_handle,
+                        result_handle,
+                        rows,
+                        *row_len,
+                        count,
+                    );
+                }
+                LazyOp::GatherBackward(a, indices, rows, row_len) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    let indices_handle = buffer_handles.get(indices).unwrap();
+                    let count = deps.get(indices).unwrap().size;
+                    backend.gather_backward(
+                        a_handle,
+                        indices_handle,
+                        result_handle,
+                        *rows,
+                        *row_len,
+                        count,
+                    );
+                }
+                LazyOp::ScatterAdd(a, indices, updates, row_len) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    let indices_handle = buffer_handles.get(indices).unwrap();
+                    let updates_handle = buffer_handles.get(updates).unwrap();
+                    let count = deps.get(indices).unwrap().size;
+                    backend.scatter_add(
+                        a_handle,
+                        indices_handle,
+                        updates_handle,
+                        result_handle,
+                        node.size / row_len,
+                        *row_len,
+                        count,
+                    );
+                }
+                LazyOp::LogSoftmax(a, row_len) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    backend.log_softmax(a_handle, result_handle, node.size, *row_len);
+                }
+                LazyOp::LogSoftmaxBackward(a, b, row_len) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    let b_handle = buffer_handles.get(b).unwrap();
+                    backend.log_softmax_backward(
+                        a_handle,
+                        b_handle,
+                        result_handle,
+                        node.size,
+                        *row_len,
+                    );
+                }
+                LazyOp::BiasActivation(a, b, row_len, activation) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    let b_handle = buffer_handles.get(b).unwrap();
+                    backend.bias_activation(
+                        a_handle,
+                        b_handle,
+                        result_handle,
+                        node.size,
+                        *row_len,
+                        *activation,
+                    );
+                }
+                LazyOp::ActivationBackward(a, b, activation) => {
+                    let a_handle = buffer_handles.get(a).unwrap();
+                    let b_handle = buffer_handles.get(b).unwrap();
+                    backend.activation_backward(
+                        a_handle,
+                        b_handle,
+                        result_handle,
+                        node.size,
+                        *activation,
+                    );
+                }
                 _ => {
                     panic!("Unsupported operation: {:?}", node.operation);
                 }
             }
         }
-        return buffer_handles;
+        return (buffer_handles, reused, false);
     }
 }
 
 impl LazyBufferHandle {
     pub fn realize(&self, backend: &dyn Backend, to_host: bool) {
+        self.realize_with(backend, to_host, None)
+            .unwrap_or_else(|e| panic!("{}", e));
+    }
+    // like realize, but checks the token before every op and stops with Cancelled once it's
+    // set. the buffers this realize allocated are freed again and every computed node in the
+    // graph is left unrealized, in-place writes of Memset nodes that already ran are kept
+    pub fn realize_cancellable(
+        &self,
+        backend: &dyn Backend,
+        token: &CancellationToken,
+    ) -> Result<(), FlameError> {
+        self.realize_with(backend, false, Some(token))
+    }
+    fn realize_with(
+        &self,
+        backend: &dyn Backend,
+        to_host: bool,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<(), FlameError> {
         let deps = LAZYBUFFER_REGISTRY.with_borrow(|registry| {
             let buffer = registry.get(self.0).unwrap();
-            buffer.collect_dependencies()
+            buffer.collect_dependencies(true)
         });
+        let inside = checkpointed_intermediates(*self, &deps);
         let mut buffer_handles: HashMap<LazyBufferHandle, BufferHandle> = HashMap::new();
+        let mut reused = HashSet::new();
+        let mut cancelled = false;
         LAZYBUFFER_REGISTRY.with_borrow_mut(|registry| {
             // Then realize with the collected dependencies
             let buffer = registry.get_mut(self.0).unwrap();
-            buffer_handles = buffer.realize_impl(backend, to_host, deps);
+            (buffer_handles, reused, cancelled) =
+                buffer.realize_impl(backend, to_host, deps, &inside, cancel);
+        });
+        if cancelled {
+            Self::discard_partial(&buffer_handles, backend);
+            return Err(FlameError::Cancelled);
+        }
+        REUSED_BUFFERS.with_borrow_mut(|reused_buffers| {
+            for lazy_buffer in buffer_handles.keys() {
+                reused_buffers.remove(lazy_buffer);
+            }
+            reused_buffers.extend(reused);
         });
         for (lazy_buffer, device_handle) in buffer_handles.iter() {
             LAZYBUFFER_REGISTRY.with_borrow_mut(|registry| {
                 let buffer = registry.get_mut(lazy_buffer.0).unwrap();
                 buffer.device_buffer = Some(device_handle.clone());
                 match &mut buffer.operation {
-                    LazyOp::Creation(CreationType::Random)
-                    | LazyOp::Creation(CreationType::RawData(_)) => {
+                    LazyOp::Creation(CreationType::Random(_))
+                    | LazyOp::Creation(CreationType::Uniform(_, _, _))
+                    | LazyOp::Creation(CreationType::RandomMask(_, _))
+                    | LazyOp::Creation(CreationType::RawData(_))
+                    | LazyOp::Creation(CreationType::Generated(_))
+                    | LazyOp::Creation(CreationType::Filled(_)) => {
                         buffer.operation = LazyOp::Creation(CreationType::Created);
                     }
-                    _ => {}
+                    LazyOp::Creation(CreationType::Created) => {}
+                    // computed values, only valid until the parameters change
+                    _ => {
+                        let generation = PARAMETER_GENERATION.with_borrow(|generation| *generation);
+                        REALIZED_GENERATION.with_borrow_mut(|realized| {
+                            realized.insert(*lazy_buffer, generation);
+                        });
+                    }
+                }
+            });
+        }
+        self.release_checkpointed(&inside, &buffer_handles, backend);
+        Ok(())
+    }
+    // undoes the allocations of a cancelled realize. computed nodes lose their storage even if
+    // they had some before, it may have been overwritten or handed to another node. data keeps
+    // the device buffer it already had
+    fn discard_partial(
+        buffer_handles: &HashMap<LazyBufferHandle, BufferHandle>,
+        backend: &dyn Backend,
+    ) {
+        let mut freed = HashSet::new();
+        for (lazy_buffer, device_handle) in buffer_handles {
+            let discard = LAZYBUFFER_REGISTRY.with_borrow_mut(|registry| {
+                let buffer = registry.get_mut(lazy_buffer.0).unwrap();
+                let computed = !matches!(
+                    buffer.operation,
+                    LazyOp::Creation(_) | LazyOp::Clear(_) | LazyOp::Memset(_, _)
+                );
+                if computed {
+                    buffer.device_buffer = None;
                 }
+                buffer.device_buffer.is_none()
+            });
+            if !discard {
+                continue;
+            }
+            REALIZED_GENERATION.with_borrow_mut(|realized| realized.remove(lazy_buffer));
+            REUSED_BUFFERS.with_borrow_mut(|reused| reused.remove(lazy_buffer));
+            // recycled storage is freed through the node it was allocated for
+            if device_handle.id == *lazy_buffer && freed.insert(device_handle.id) {
+                backend.free_buffer(device_handle);
+            }
+        }
+    }
+    // frees what realize_impl left of the checkpointed intermediates, a later realize that reads
+    // one computes it again, the same way any unrealized node is
+    fn release_checkpointed(
+        &self,
+        inside: &HashSet<LazyBufferHandle>,
+        buffer_handles: &HashMap<LazyBufferHandle, BufferHandle>,
+        backend: &dyn Backend,
+    ) {
+        // storage handed to a node outside the checkpointed region belongs to that node now
+        let kept: HashSet<LazyBufferHandle> = buffer_handles
+            .iter()
+            .filter(|(handle, _)| !inside.contains(handle))
+            .map(|(_, device_buffer)| device_buffer.id)
+            .collect();
+        let mut freed = HashSet::new();
+        for &handle in inside {
+            let device_buffer = LAZYBUFFER_REGISTRY.with_borrow_mut(|registry| {
+                registry.get_mut(handle.0).unwrap().device_buffer.take()
             });
+            REALIZED_GENERATION.with_borrow_mut(|realized| realized.remove(&handle));
+            REUSED_BUFFERS.with_borrow_mut(|reused| reused.remove(&handle));
+            // recycled storage can be held by several intermediates, or given away and never
+            // taken when no later node had its size
+            if let Some(device_buffer) = device_buffer
+                && !kept.contains(&device_buffer.id)
+                && freed.insert(device_buffer.id)
+            {
+                backend.free_buffer(&device_buffer);
+            }
+        }
+    }
+    // marks this buffer as the output of a checkpointed subgraph, see CHECKPOINTS
+    pub fn checkpoint(&self) {
+        CHECKPOINTS.with_borrow_mut(|checkpoints| checkpoints.insert(*self));
+    }
+    // debugging aid: realizes the graph and downloads every buffer in it, intermediates
+    // included, keyed by handle so it can be matched up with get_comp_graph_viz. with buffer
+    // reuse on, intermediates that weren't retained are left out
+    pub fn dump_all(&self, backend: &dyn Backend) -> HashMap<LazyBufferHandle, Vec<f32>> {
+        self.realize(backend, false);
+        let deps = LAZYBUFFER_REGISTRY.with_borrow(|registry| {
+            let buffer = registry.get(self.0).unwrap();
+            buffer.collect_dependencies(false)
+        });
+        deps.keys()
+            .filter(|handle| !handle.is_reused())
+            .map(|handle| (*handle, handle.get_data(backend)))
+            .collect()
+    }
+    // keeps the storage of this buffer out of buffer reuse, so it can still be read after a
+    // graph that contains it is realized
+    pub fn retain(&self) {
+        RETAINED_BUFFERS.with_borrow_mut(|retained| retained.insert(*self));
+    }
+    // true if the last realize gave this buffer's storage to another node
+    pub fn is_reused(&self) -> bool {
+        REUSED_BUFFERS.with_borrow(|reused| reused.contains(self))
+    }
+    // true if this buffer was computed before the latest in-place parameter update, so its
+    // device values no longer match the graph. realizing it again recomputes it
+    pub fn is_stale(&self) -> bool {
+        let generation = PARAMETER_GENERATION.with_borrow(|generation| *generation);
+        REALIZED_GENERATION
+            .with_borrow(|realized| realized.get(self).is_some_and(|&r| r < generation))
+    }
+    // compares the graphs behind two handles by op types, sizes and how nodes are shared,
+    // ignoring ids and data, so (a + a) and (b + b) match but (a + b) does not
+    pub fn structural_eq(&self, other: &LazyBufferHandle) -> bool {
+        fn visit(
+            lhs: LazyBufferHandle,
+            rhs: LazyBufferHandle,
+            lhs_to_rhs: &mut HashMap<LazyBufferHandle, LazyBufferHandle>,
+            rhs_to_lhs: &mut HashMap<LazyBufferHandle, LazyBufferHandle>,
+        ) -> bool {
+            // a node seen before has to pair up with the same node as last time
+            match (lhs_to_rhs.get(&lhs), rhs_to_lhs.get(&rhs)) {
+                (Some(&mapped), Some(_)) => return mapped == rhs,
+                (None, None) => {}
+                _ => return false,
+            }
+            lhs_to_rhs.insert(lhs, rhs);
+            rhs_to_lhs.insert(rhs, lhs);
+            if lhs.get_size() != rhs.get_size() {
+                return false;
+            }
+            let (lhs_op, rhs_op) = (lhs.get_op(), rhs.get_op());
+            let same_kind = match (&lhs_op, &rhs_op) {
+                (LazyOp::CumSum(_, l), LazyOp::CumSum(_, r)) => l == r,
+                (LazyOp::Transpose(_, l_rows, l_cols), LazyOp::Transpose(_, r_rows, r_cols)) => {
+                    (l_rows, l_cols) == (r_rows, r_cols)
+                }
+                (
+                    LazyOp::TriangleMask(_, l_cols, l_diagonal, l_upper),
+                    LazyOp::TriangleMask(_, r_cols, r_diagonal, r_upper),
+                ) => (l_cols, l_diagonal, l_upper) == (r_cols, r_diagonal, r_upper),
+                (
+                    LazyOp::BatchTranspose(_, l_b, l_r, l_c),
+                    LazyOp::BatchTranspose(_, r_b, r_r, r_c),
+                ) => (l_b, l_r, l_c) == (r_b, r_r, r_c),
+                (LazyOp::MatMul(_, _, l_m, l_k, l_n), LazyOp::MatMul(_, _, r_m, r_k, r_n)) => {
+                    (l_m, l_k, l_n) == (r_m, r_k, r_n)
+                }
+                (
+                    LazyOp::MaxPool1d(_, l_kernel, l_stride),
+                    LazyOp::MaxPool1d(_, r_kernel, r_stride),
+                )
+                | (
+                    LazyOp::MaxPool1dBackward(_, _, l_kernel, l_stride),
+                    LazyOp::MaxPool1dBackward(_, _, r_kernel, r_stride),
+                ) => (l_kernel, l_stride) == (r_kernel, r_stride),
+                (LazyOp::Unary(_, l), LazyOp::Unary(_, r)) => l == r,
+                (LazyOp::Affine(_, l_scale, l_shift), LazyOp::Affine(_, r_scale, r_shift)) => {
+                    (l_scale.to_bits(), l_shift.to_bits()) == (r_scale.to_bits(), r_shift.to_bits())
+                }
+                (LazyOp::ScaleGrad(_, l), LazyOp::ScaleGrad(_, r)) => l.to_bits() == r.to_bits(),
+                (LazyOp::Pad(_, l_left, _), LazyOp::Pad(_, r_left, _)) => l_left == r_left,
+                (LazyOp::Slice(_, l_start, _), LazyOp::Slice(_, r_start, _)) => l_start == r_start,
+                (LazyOp::Conv1d(_, _, l), LazyOp::Conv1d(_, _, r))
+                | (LazyOp::Conv1dInputGrad(_, _, l, _), LazyOp::Conv1dInputGrad(_, _, r, _))
+                | (LazyOp::Conv1dKernelGrad(_, _, l, _), LazyOp::Conv1dKernelGrad(_, _, r, _)) => {
+                    l == r
+                }
+                (LazyOp::Gather(_, _, l), LazyOp::Gather(_, _, r))
+                | (LazyOp::ScatterAdd(_, _, _, l), LazyOp::ScatterAdd(_, _, _, r)) => l == r,
+                (LazyOp::BroadcastTo(_, l_n, l_inner), LazyOp::BroadcastTo(_, r_n, r_inner))
+                | (
+                    LazyOp::BroadcastToBackward(_, l_n, l_inner),
+                    LazyOp::BroadcastToBackward(_, r_n, r_inner),
+                ) => (l_n, l_inner) == (r_n, r_inner),
+                (
+                    LazyOp::GatherBackward(_, _, l_rows, l_len),
+                    LazyOp::GatherBackward(_, _, r_rows, r_len),
+                ) => (l_rows, l_len) == (r_rows, r_len),
+                (LazyOp::LogSoftmax(_, l), LazyOp::LogSoftmax(_, r))
+                | (LazyOp::LogSoftmaxBackward(_, _, l), LazyOp::LogSoftmaxBackward(_, _, r)) => {
+                    l == r
+                }
+                (
+                    LazyOp::BiasActivation(_, _, l_len, l_activation),
+                    LazyOp::BiasActivation(_, _, r_len, r_activation),
+                ) => (l_len, l_activation) == (r_len, r_activation),
+                (LazyOp::ActivationBackward(_, _, l), LazyOp::ActivationBackward(_, _, r)) => {
+                    l == r
+                }
+                _ => std::mem::discriminant(&lhs_op) == std::mem::discriminant(&rhs_op),
+            };
+            same_kind
+                && operands(&lhs_op)
+                    .into_iter()
+                    .zip(operands(&rhs_op))
+                    .all(|(l, r)| visit(l, r, lhs_to_rhs, rhs_to_lhs))
         }
+        visit(*self, *other, &mut HashMap::new(), &mut HashMap::new())
     }
     pub fn get_comp_graph_viz(&self) -> String {
         LAZYBUFFER_REGISTRY.with_borrow(|registry| {
@@ -517,12 +2364,25 @@ impl LazyBufferHandle {
             buffer.get_comp_graph_viz()
         })
     }
+    // panics on a stale buffer, see try_get_data
     pub fn get_data(&self, backend: &dyn Backend) -> Vec<f32> {
-        LAZYBUFFER_REGISTRY.with_borrow_mut(|registry| {
+        self.try_get_data(backend)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+    // reads the device values, refusing if they were computed from parameters that have been
+    // updated since, e.g. a loss read after apply_backward without realizing it again
+    pub fn try_get_data(&self, backend: &dyn Backend) -> Result<Vec<f32>, FlameError> {
+        if self.is_stale() {
+            return Err(FlameError::StaleBuffer(*self));
+        }
+        if self.is_reused() {
+            return Err(FlameError::ReusedBuffer(*self));
+        }
+        Ok(LAZYBUFFER_REGISTRY.with_borrow_mut(|registry| {
             let buffer = registry.get_mut(self.0).unwrap();
             let device_data = backend.read_buffer(&buffer.device_buffer.as_ref().unwrap());
             device_data
-        })
+        }))
     }
     pub fn get_size(&self) -> usize {
         LAZYBUFFER_REGISTRY.with_borrow(|registry| {
@@ -548,6 +2408,78 @@ impl LazyBufferHandle {
             }
         })
     }
+    // every node of the graph under this buffer, operands before the ops that read them
+    pub(crate) fn graph_nodes(&self) -> Vec<(LazyBufferHandle, usize, LazyOp)> {
+        let deps = LAZYBUFFER_REGISTRY.with_borrow(|registry| {
+            let buffer = registry.get(self.0).unwrap();
+            buffer.collect_dependencies(false)
+        });
+        LazyBuffer::topological_sort(&deps)
+            .into_iter()
+            .map(|id| {
+                let node = deps.get(&id).unwrap();
+                (id, node.size, node.operation.clone())
+            })
+            .collect()
+    }
+    // releases the device storage of a computed scratch buffer, a later realize that needs it
+    // computes it into a new allocation. data buffers are left alone, they can't be recomputed
+    pub(crate) fn free_scratch(&self, backend: &dyn Backend) {
+        let device_buffer = LAZYBUFFER_REGISTRY.with_borrow_mut(|registry| {
+            let buffer = registry.get_mut(self.0).unwrap();
+            match (&buffer.kind, &buffer.operation) {
+                (LazybufferType::Scratch, LazyOp::Creation(_))
+                | (LazybufferType::TensorData(_), _) => None,
+                _ => buffer.device_buffer.take(),
+            }
+        });
+        if let Some(device_buffer) = device_buffer {
+            backend.free_buffer(&device_buffer);
+        }
+    }
+    // releases the device storage whatever kind of buffer this is, a data buffer loses its values
+    // for good. storage handed to another node belongs to that node and is left alone
+    pub(crate) fn free_device_buffer(&self, backend: &dyn Backend) {
+        let device_buffer = LAZYBUFFER_REGISTRY
+            .with_borrow_mut(|registry| registry.get_mut(self.0).unwrap().device_buffer.take());
+        let reused = REUSED_BUFFERS.with_borrow_mut(|reused| reused.remove(self));
+        REALIZED_GENERATION.with_borrow_mut(|realized| realized.remove(self));
+        if let Some(device_buffer) = device_buffer
+            && !reused
+        {
+            backend.free_buffer(&device_buffer);
+        }
+    }
+    // moves the values of this buffer to another backend, realizing it on `from` first if
+    // needed. afterwards it's a data buffer on `to`, the ops that computed it are dropped
+    pub fn migrate(&self, from: &dyn Backend, to: &dyn Backend) {
+        if self.get_device_handle().is_none() || self.is_stale() || self.is_reused() {
+            self.realize(from, false);
+        }
+        let data = self.get_data(from);
+        let handle = to.allocate_buffer(*self, data.len());
+        to.to_device(&data, &handle);
+        let old = LAZYBUFFER_REGISTRY.with_borrow_mut(|registry| {
+            registry
+                .get_mut(self.0)
+                .unwrap()
+                .device_buffer
+                .replace(handle)
+        });
+        if let Some(old) = old {
+            from.free_buffer(&old);
+        }
+        self.make_leaf();
+    }
+    // turns a realized buffer into data, its current device values are kept and the ops that
+    // computed it are no longer part of any graph built on it
+    pub(crate) fn make_leaf(&self) {
+        LAZYBUFFER_REGISTRY.with_borrow_mut(|registry| {
+            registry.get_mut(self.0).unwrap().operation = LazyOp::Creation(CreationType::Created);
+        });
+        REALIZED_GENERATION.with_borrow_mut(|realized| realized.remove(self));
+        GRAPH_VERSION.with_borrow_mut(|version| *version += 1);
+    }
     pub fn get_device_handle(&self) -> Option<BufferHandle> {
         LAZYBUFFER_REGISTRY.with_borrow(|registry| {
             let buffer = registry.get(self.0).unwrap();
@@ -0,0 +1,19 @@
+pub mod backends;
+pub mod checkpoint;
+pub mod error;
+pub mod inspect;
+pub mod ir;
+pub mod lazybuffer;
+pub mod nn;
+pub mod optim;
+pub mod random;
+pub mod stats;
+pub mod tensor;
+pub mod vulkan;
+
+pub use checkpoint::{load_checkpoint, save_checkpoint};
+pub use inspect::{TensorInfo, all_tensors, tensor_info};
+pub use random::seed;
+#[cfg(feature = "test-utils")]
+pub use stats::reset_counters;
+pub use stats::{Stats, stats};
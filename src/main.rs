@@ -1,25 +1,144 @@
-pub mod backends;
-pub mod lazybuffer;
-pub mod tensor;
-pub mod vulkan;
+use std::time::{Duration, Instant};
 
-use lazybuffer::LazyBuffer;
+use flamer::backends::{CPUBackend, VulkanBackend};
+use flamer::lazybuffer::{Backend, get_next_buffer_id};
+use flamer::nn::{Linear, Module, Sequential};
+use flamer::tensor::Tensor;
+use flamer::vulkan::MemoryPreference;
+
+// times upload, compute and download of an add separately so it's visible where the time goes,
+// for small tensors the GPU loses to the CPU on transfer alone. run with `cargo run --release -- --bench`
+fn benchmark(backend: &dyn Backend, sizes: &[usize], kernel_time: &dyn Fn() -> Option<Duration>) {
+    for &size in sizes {
+        let data = vec![1.0; size];
+        let a = backend.allocate_buffer(get_next_buffer_id(), size);
+        let b = backend.allocate_buffer(get_next_buffer_id(), size);
+        let result = backend.allocate_buffer(get_next_buffer_id(), size);
+
+        let start = Instant::now();
+        backend.to_device(&data, &a);
+        backend.to_device(&data, &b);
+        let upload = start.elapsed();
+
+        let start = Instant::now();
+        backend.add(&a, &b, &result, size);
+        let compute = start.elapsed();
+        let kernel = kernel_time().map_or("n/a".to_string(), |t| format!("{:?}", t));
+
+        let start = Instant::now();
+        backend.read_buffer(&result);
+        let download = start.elapsed();
+
+        println!(
+            "{} add of {} elements: upload {:?}, compute {:?} (kernel {}), download {:?}",
+            backend.name(),
+            size,
+            upload,
+            compute,
+            kernel,
+            download
+        );
+        backend.free_buffer(&a);
+        backend.free_buffer(&b);
+        backend.free_buffer(&result);
+    }
+}
+
+// the 100M element add on device local and on host visible memory, compute is where device local
+// should win while host visible saves the staging copies. each buffer is 400MB
+fn benchmark_memory_preferences() {
+    for preference in [MemoryPreference::DeviceLocal, MemoryPreference::HostVisible] {
+        let backend = VulkanBackend::with_memory_preference("Vulkano Test", preference);
+        println!("{:?} memory:", preference);
+        benchmark(&backend, &[100_000_000], &|| backend.last_kernel_time());
+    }
+}
+
+// times a 1024x1024 matmul for a range of workgroup tile shapes, the fastest one can be passed to
+// VulkanBackend::set_matmul_tile. run with `cargo run --release -- --bench-matmul`
+fn benchmark_matmul_tiles(backend: &VulkanBackend) {
+    let size = 1024;
+    let data = vec![1.0; size * size];
+    let a = backend.allocate_buffer(get_next_buffer_id(), size * size);
+    let b = backend.allocate_buffer(get_next_buffer_id(), size * size);
+    let result = backend.allocate_buffer(get_next_buffer_id(), size * size);
+    backend.to_device(&data, &a);
+    backend.to_device(&data, &b);
+    for (rows, cols) in [
+        (8, 8),
+        (16, 8),
+        (8, 16),
+        (16, 16),
+        (32, 8),
+        (8, 32),
+        (32, 32),
+    ] {
+        if let Err(e) = backend.set_matmul_tile(rows, cols) {
+            println!("skipping tile {}x{}: {}", rows, cols, e);
+            continue;
+        }
+        // the first run compiles the shader for this tile shape
+        backend.matmul(&a, &b, &result, size, size, size);
+        let start = Instant::now();
+        backend.matmul(&a, &b, &result, size, size, size);
+        let elapsed = start.elapsed();
+        let kernel = backend
+            .last_kernel_time()
+            .map_or("n/a".to_string(), |t| format!("{:?}", t));
+        println!(
+            "matmul {}x{} with tile {}x{}: {:?} (kernel {})",
+            size, size, rows, cols, elapsed, kernel
+        );
+    }
+    backend.free_buffer(&a);
+    backend.free_buffer(&b);
+    backend.free_buffer(&result);
+}
 
-use crate::backends::{CPUBackend, VulkanBackend};
-use crate::tensor::Tensor;
 fn main() {
     let vulkan_backend = VulkanBackend::new("Vulkano Test");
     let cpu_backend = CPUBackend::new();
-    let mut a = Tensor::new(vec![1.0, 2.0, 3.0]);
-    let mut w = Tensor::new(vec![0.5, 0.5, 0.5]);
+    if std::env::args().any(|arg| arg == "--bench") {
+        let sizes = [1_000, 100_000, 10_000_000];
+        benchmark(&cpu_backend, &sizes, &|| None);
+        let allocations = vulkan_backend.allocation_count();
+        benchmark(&vulkan_backend, &sizes, &|| {
+            vulkan_backend.last_kernel_time()
+        });
+        println!(
+            "Vulkan device allocations during benchmark: {}",
+            vulkan_backend.allocation_count() - allocations
+        );
+        // every op reuses the same command buffer, this should stay at 1
+        println!(
+            "Vulkan command buffers allocated: {}",
+            vulkan_backend.command_buffer_count()
+        );
+        benchmark_memory_preferences();
+        return;
+    }
+    if std::env::args().any(|arg| arg == "--device-info") {
+        println!("{:#?}", vulkan_backend.device_info());
+        return;
+    }
+    if std::env::args().any(|arg| arg == "--bench-matmul") {
+        benchmark_matmul_tiles(&vulkan_backend);
+        return;
+    }
+    let input = Tensor::without_grad(vec![1.0, 2.0, 3.0]);
     let target = Tensor::without_grad(vec![2.0, 4.0, 6.0]);
+    let model = Sequential::new(vec![
+        Box::new(Linear::new(3, 8)),
+        Box::new(Linear::new(8, 3)),
+    ]);
 
     for _ in 0..35 {
-        let predictions = a * w;
+        let predictions = model.forward(&input);
         let mut loss = (target - predictions) * (target - predictions);
         loss.buffer.realize(&vulkan_backend, false);
-        loss.apply_backward(&vulkan_backend, 0.1);
-        println!("A {:?}", a);
+        // read before the update, afterwards the loss is stale until realized again
         println!("Loss: {:?}", loss.buffer.get_data(&vulkan_backend));
+        loss.apply_backward(&vulkan_backend, 0.01);
+        println!("Parameters {:?}", model.parameters());
     }
 }
@@ -0,0 +1,170 @@
+use crate::lazybuffer::Activation;
+use crate::tensor::Tensor;
+
+pub trait Module {
+    fn forward(&self, x: &Tensor) -> Tensor;
+    // every trainable tensor of the module, in a stable order. these have to be leaves
+    // (Tensor::is_leaf), computed tensors are never updated by the optimizer
+    fn parameters(&self) -> Vec<Tensor>;
+    // switches between training and evaluation behaviour, e.g. dropout only drops while
+    // training. modules start out training, the ones that behave the same either way ignore it
+    fn set_training(&mut self, _training: bool) {}
+}
+
+// a dense layer, x @ weight + bias for x of shape [batch, in] or a single sample [in]. weight
+// is [in, out] and bias has one element per output
+pub struct Linear {
+    pub weight: Tensor,
+    pub bias: Tensor,
+    // the [in, out] view matmul needs, gradients of the view land on weight
+    matrix: Tensor,
+}
+
+impl Linear {
+    // weight uniform in +-1/sqrt(in) and bias zero, reproducible through random::seed
+    pub fn new(in_features: usize, out_features: usize) -> Self {
+        let bound = 1.0 / (in_features as f32).sqrt();
+        Self::from_tensors(
+            Tensor::uniform(in_features * out_features, -bound, bound),
+            Tensor::new(vec![0.0; out_features]),
+        )
+    }
+
+    // weight holds the in rows of out values back to back
+    pub fn from_parameters(weight: Vec<f32>, bias: Vec<f32>) -> Self {
+        Self::from_tensors(Tensor::new(weight), Tensor::new(bias))
+    }
+
+    fn from_tensors(weight: Tensor, bias: Tensor) -> Self {
+        let (size, out_features) = (weight.buffer.get_size(), bias.buffer.get_size());
+        assert!(
+            out_features > 0 && size.is_multiple_of(out_features),
+            "Linear weight of size {} doesn't split into rows of {} outputs",
+            size,
+            out_features
+        );
+        let matrix = weight.reshape(&[size / out_features, out_features]);
+        Linear {
+            weight,
+            bias,
+            matrix,
+        }
+    }
+}
+
+impl Module for Linear {
+    fn forward(&self, x: &Tensor) -> Tensor {
+        if x.shape().len() == 1 {
+            return self.forward(&x.unsqueeze(0)).squeeze(0);
+        }
+        x.linear_activation(&self.matrix, &self.bias, Activation::None)
+    }
+
+    fn parameters(&self) -> Vec<Tensor> {
+        vec![self.weight, self.bias]
+    }
+}
+
+// zeroes elements with probability p while training and scales the rest up by 1 / (1 - p),
+// passes the input through unchanged in evaluation
+pub struct Dropout {
+    p: f32,
+    training: bool,
+}
+
+impl Dropout {
+    pub fn new(p: f32) -> Self {
+        Dropout { p, training: true }
+    }
+}
+
+impl Module for Dropout {
+    fn forward(&self, x: &Tensor) -> Tensor {
+        if self.training { x.dropout(self.p) } else { *x }
+    }
+
+    fn parameters(&self) -> Vec<Tensor> {
+        vec![]
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+}
+
+// runs its modules one after the other, feeding each output into the next
+pub struct Sequential {
+    modules: Vec<Box<dyn Module>>,
+}
+
+impl Sequential {
+    pub fn new(modules: Vec<Box<dyn Module>>) -> Self {
+        Sequential { modules }
+    }
+}
+
+impl Module for Sequential {
+    fn forward(&self, x: &Tensor) -> Tensor {
+        self.modules
+            .iter()
+            .fold(*x, |output, module| module.forward(&output))
+    }
+
+    fn parameters(&self) -> Vec<Tensor> {
+        let parameters: Vec<Tensor> = self
+            .modules
+            .iter()
+            .flat_map(|module| module.parameters())
+            .collect();
+        // the optimizer only steps leaves, a computed tensor here would silently never train
+        debug_assert!(
+            parameters.iter().all(|parameter| parameter.is_leaf()),
+            "Module::parameters returned a tensor that is computed by an op"
+        );
+        parameters
+    }
+
+    fn set_training(&mut self, training: bool) {
+        for module in &mut self.modules {
+            module.set_training(training);
+        }
+    }
+}
+
+// a trainable [vocab, dim] table, forward looks up one row per index, e.g. token ids of a text
+pub struct Embedding {
+    pub weight: Tensor,
+    table: Tensor,
+}
+
+impl Embedding {
+    // rows start out standard normal, reproducible through random::seed
+    pub fn new(vocab: usize, dim: usize) -> Self {
+        Self::from_weight(Tensor::randn(vocab * dim), dim)
+    }
+
+    // weight holds the rows back to back, vocab of them with dim elements each
+    pub fn from_weight(weight: Tensor, dim: usize) -> Self {
+        let size = weight.buffer.get_size();
+        assert!(
+            dim > 0 && size.is_multiple_of(dim),
+            "Embedding weight of size {} doesn't split into rows of {}",
+            size,
+            dim
+        );
+        // the 2D view gather needs, gradients of the view land on weight
+        let table = weight.reshape(&[size / dim, dim]);
+        Embedding { weight, table }
+    }
+}
+
+impl Module for Embedding {
+    // x holds the row indices as floats, the output has one more dimension of size dim
+    fn forward(&self, x: &Tensor) -> Tensor {
+        self.table.gather(x)
+    }
+
+    fn parameters(&self) -> Vec<Tensor> {
+        vec![self.weight]
+    }
+}
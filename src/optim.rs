@@ -0,0 +1,43 @@
+use crate::error::FlameError;
+use crate::lazybuffer::Backend;
+use crate::tensor::Tensor;
+
+// clips the gradients of params elementwise to [-clip, clip], a simpler alternative to
+// clipping by norm. call it between backward and step
+pub fn clip_grad_value(params: &[Tensor], clip: f32, backend: &dyn Backend) {
+    for param in params {
+        param.clamp_grad(clip, backend);
+    }
+}
+
+// updates every leaf tensor that has a gradient, after backward or accumulate_grad
+pub struct SGD {
+    lr: f32,
+    accumulate_steps: usize,
+}
+
+impl SGD {
+    pub fn new(lr: f32) -> Self {
+        SGD {
+            lr,
+            accumulate_steps: 1,
+        }
+    }
+
+    // for gradients summed over n micro-batches with Tensor::accumulate_grad, scales the update
+    // by 1/n so it matches a single batch n times the size
+    pub fn accumulate_steps(mut self, n: usize) -> Self {
+        assert!(n > 0, "accumulate_steps needs at least one step");
+        self.accumulate_steps = n;
+        self
+    }
+
+    pub fn step(&self, backend: &dyn Backend) {
+        Tensor::step(backend, self.lr / self.accumulate_steps as f32);
+    }
+
+    // like step, but refuses to update anything if a parameter would become NaN/Inf
+    pub fn step_checked(&self, backend: &dyn Backend) -> Result<(), FlameError> {
+        Tensor::step_checked(backend, self.lr / self.accumulate_steps as f32)
+    }
+}
@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+
+thread_local! {
+    // master seed and how many streams have been handed out since it was set
+    static MASTER_SEED: RefCell<(u64, u64)> = const { RefCell::new((0, 0)) };
+}
+
+// seeds every random op created afterwards. each random buffer gets its own stream, fixed when
+// the op is created, so the same sequence of ops after the same seed gives the same values no
+// matter when or in which order the buffers are realized
+pub fn seed(seed: u64) {
+    MASTER_SEED.with_borrow_mut(|master| *master = (seed, 0));
+}
+
+pub(crate) fn next_stream_seed() -> u64 {
+    let (master, stream) = MASTER_SEED.with_borrow_mut(|master| {
+        master.1 += 1;
+        *master
+    });
+    Rng::new(master ^ stream.wrapping_mul(0x9E37_79B9_7F4A_7C15)).next_u64()
+}
+
+// splitmix64, small and good enough for stream seeds and dropout masks
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // uniform in [0, 1)
+    pub(crate) fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Distribution {
+    Normal,            // standard normal
+    Uniform(f32, f32), // uniform in [low, high)
+}
+
+// counter based: element i of a stream depends only on the stream's key and i, so a backend can
+// draw every element independently, e.g. one GPU invocation each, and gets the same values as
+// the host. the hash is PCG's output function, the Vulkan fill_random shader has a copy of it
+pub(crate) fn pcg_hash(value: u32) -> u32 {
+    let state = value.wrapping_mul(747_796_405).wrapping_add(2_891_336_453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277_803_737);
+    (word >> 22) ^ word
+}
+
+pub(crate) fn stream_key(seed: u64) -> u32 {
+    pcg_hash(seed as u32 ^ pcg_hash((seed >> 32) as u32))
+}
+
+// uniform in [0, 1)
+fn counter_uniform(key: u32, counter: u32) -> f32 {
+    (pcg_hash(pcg_hash(counter) ^ key) >> 8) as f32 / (1u32 << 24) as f32
+}
+
+// element index of the stream with the given key. a normal takes two uniforms per element
+// (Box-Muller), so it uses counters 2i and 2i + 1
+pub(crate) fn sample(key: u32, index: usize, distribution: Distribution) -> f32 {
+    let index = index as u32;
+    match distribution {
+        Distribution::Normal => {
+            let u1 = 1.0 - counter_uniform(key, index.wrapping_mul(2));
+            let u2 = counter_uniform(key, index.wrapping_mul(2).wrapping_add(1));
+            (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos()
+        }
+        Distribution::Uniform(low, high) => low + (high - low) * counter_uniform(key, index),
+    }
+}
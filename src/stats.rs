@@ -0,0 +1,29 @@
+use crate::lazybuffer::buffer_counts;
+use crate::tensor::tensors_created;
+
+// counts of this thread's tensors and lazy buffers. the registries never shrink, so these only
+// grow until reset_counters, a training loop whose counts keep growing builds new nodes each step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    pub tensors_created: usize,
+    pub buffers_created: usize, // lazy buffer ids handed out, temporary device buffers included
+    pub registry_len: usize,
+}
+
+pub fn stats() -> Stats {
+    let (buffers_created, registry_len) = buffer_counts();
+    Stats {
+        tensors_created: tensors_created(),
+        buffers_created,
+        registry_len,
+    }
+}
+
+// drops every tensor and lazy buffer of this thread and restarts the ids, so a test doesn't see
+// what earlier tests on the same thread built. handles from before are invalid afterwards and
+// backends holding buffers from before have to be replaced, their ids would collide
+#[cfg(feature = "test-utils")]
+pub fn reset_counters() {
+    crate::tensor::reset_tensors();
+    crate::lazybuffer::reset_buffers();
+}
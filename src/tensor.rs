@@ -1,19 +1,60 @@
-use crate::lazybuffer::{Backend, LazyBuffer, LazyBufferHandle, LazyOp};
+use crate::error::FlameError;
+use crate::lazybuffer::{
+    Activation, Backend, CreationType, LazyBuffer, LazyBufferHandle, LazyOp, UnaryOp,
+    get_next_buffer_id, mark_parameters_updated,
+};
 use std::{
     cell::RefCell,
-    collections::{HashMap, VecDeque},
+    collections::{HashMap, HashSet},
     fmt::Debug,
-    ops::{Add, Div, Mul, Sub},
+    iter::{Product, Sum},
+    mem::{Discriminant, discriminant},
+    ops::{Add, Deref, DerefMut, Div, Mul, Neg, Sub},
+    rc::Rc,
 };
+// see Tensor::format
+const FORMAT_EDGE_ITEMS: usize = 3;
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct TensorId(usize);
+// keyed by the op kind too, a + b and a * b are different tensors
+type OpCacheKey = (Discriminant<LazyOp>, LazyBufferHandle, LazyBufferHandle);
 thread_local! {
     static  TENSOR_REGISTRY: RefCell<Vec<Tensor>> = RefCell::new(Vec::new());
-    static  OP_CACHE : RefCell<HashMap<(LazyBufferHandle, LazyBufferHandle), TensorId>> = RefCell::new(HashMap::new());
+    static  OP_CACHE : RefCell<HashMap<OpCacheKey, TensorId>> = RefCell::new(HashMap::new());
+}
+// shapes only live here, a tensor without an entry is 1D over its whole buffer
+thread_local! {
+    static TENSOR_SHAPES: RefCell<HashMap<TensorId, Vec<usize>>> = RefCell::new(HashMap::new());
+}
+// whether backward keeps the gradients of intermediate tensors, by default only leaves get one
+// and the intermediate chain rule gradients are freed once they have reached the leaves
+thread_local! {
+    static RETAIN_INTERMEDIATE_GRADS: RefCell<bool> = const { RefCell::new(false) };
+}
+pub fn set_retain_intermediate_grads(retain: bool) {
+    RETAIN_INTERMEDIATE_GRADS.with_borrow_mut(|r| *r = retain);
+}
+fn needs_stored_gradient(tensor: &Tensor) -> bool {
+    tensor.requires_grad
+        && (tensor.is_leaf() || RETAIN_INTERMEDIATE_GRADS.with_borrow(|retain| *retain))
 }
 thread_local! {
     static TENSOR_ID_COUNTER: RefCell<usize> = RefCell  ::new(0);
 }
+pub(crate) fn tensors_created() -> usize {
+    TENSOR_ID_COUNTER.with_borrow(|c| *c)
+}
+// copies of every tensor registered on this thread, in creation order, see inspect
+pub(crate) fn registered_tensors() -> Vec<Tensor> {
+    TENSOR_REGISTRY.with_borrow(|r| r.clone())
+}
+#[cfg(feature = "test-utils")]
+pub(crate) fn reset_tensors() {
+    TENSOR_REGISTRY.with_borrow_mut(|r| r.clear());
+    OP_CACHE.with_borrow_mut(|cache| cache.clear());
+    TENSOR_SHAPES.with_borrow_mut(|shapes| shapes.clear());
+    TENSOR_ID_COUNTER.with_borrow_mut(|c| *c = 0);
+}
 fn get_next_tensor_id() -> TensorId {
     TENSOR_ID_COUNTER.with_borrow_mut(|c| {
         let id = *c;
@@ -21,6 +62,11 @@ fn get_next_tensor_id() -> TensorId {
         TensorId(id)
     })
 }
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NormKind {
+    L1, // sum(abs(x))
+    L2, // sqrt(sum(x * x))
+}
 #[derive(Clone, Copy)]
 pub struct Tensor {
     pub id: TensorId,
@@ -47,6 +93,21 @@ impl Tensor {
         });
         t
     }
+    // same as Tensor::new((0..len).map(f).collect()), but the values are only computed while
+    // uploading, so huge tensors don't need a host copy
+    pub fn from_fn(len: usize, f: impl Fn(usize) -> f32 + 'static) -> Self {
+        let id = get_next_tensor_id();
+        let t = Tensor {
+            id,
+            buffer: LazyBuffer::from_fn(id, len, f),
+            gradient: None,
+            requires_grad: true,
+        };
+        TENSOR_REGISTRY.with_borrow_mut(|r| {
+            r.push(t);
+        });
+        t
+    }
     pub fn without_grad(data: Vec<f32>) -> Self {
         let id = get_next_tensor_id();
         let t = Tensor {
@@ -61,14 +122,107 @@ impl Tensor {
         t
     }
 
+    // standard normal values, reproducible through random::seed
+    pub fn randn(size: usize) -> Self {
+        let id = get_next_tensor_id();
+        let t = Tensor {
+            id,
+            buffer: LazyBuffer::random(Some(id), size, CreationType::Random),
+            gradient: None,
+            requires_grad: true,
+        };
+        TENSOR_REGISTRY.with_borrow_mut(|r| {
+            r.push(t);
+        });
+        t
+    }
+    // values uniform in [low, high), reproducible through random::seed like randn
+    pub fn uniform(size: usize, low: f32, high: f32) -> Self {
+        if low.is_nan() || high.is_nan() || low >= high {
+            panic!("uniform needs low < high, got {} and {}", low, high);
+        }
+        let id = get_next_tensor_id();
+        let t = Tensor {
+            id,
+            buffer: LazyBuffer::random(Some(id), size, |stream| {
+                CreationType::Uniform(stream, low, high)
+            }),
+            gradient: None,
+            requires_grad: true,
+        };
+        TENSOR_REGISTRY.with_borrow_mut(|r| {
+            r.push(t);
+        });
+        t
+    }
+    // constant tensors, they never get a gradient so ops between them are folded at build time
+    pub fn full(size: usize, value: f32) -> Self {
+        Self::without_grad(vec![value; size])
+    }
+    pub fn ones(size: usize) -> Self {
+        Self::full(size, 1.0)
+    }
+    // a constant tensor of the same shape as self, with a buffer of its own so it can be
+    // written to like any other data tensor
+    pub fn full_like(&self, value: f32) -> Self {
+        let t = Self::full(self.buffer.get_size(), value);
+        t.set_shape(self.shape());
+        t
+    }
+    pub fn zeros_like(&self) -> Self {
+        self.full_like(0.0)
+    }
+    pub fn ones_like(&self) -> Self {
+        self.full_like(1.0)
+    }
+    // [indices.len(), num_classes] rows with a 1 at each index, e.g. classification targets
+    pub fn one_hot(indices: &[usize], num_classes: usize) -> Self {
+        let mut data = vec![0.0; indices.len() * num_classes];
+        for (row, &index) in indices.iter().enumerate() {
+            if index >= num_classes {
+                panic!(
+                    "one_hot index {} out of range for {} classes",
+                    index, num_classes
+                );
+            }
+            data[row * num_classes + index] = 1.0;
+        }
+        let t = Self::without_grad(data);
+        t.set_shape(vec![indices.len(), num_classes]);
+        t
+    }
+    // marks this tensor as an output, its buffer is never reused so it can be read after a
+    // graph that depends on it is realized
+    pub fn retain(&mut self) {
+        self.buffer.retain();
+    }
+    // created from data rather than computed by an op, only leaves are parameters
+    pub fn is_leaf(&self) -> bool {
+        matches!(self.buffer.get_op(), LazyOp::Creation(_))
+    }
+    pub(crate) fn requires_grad_for(id: TensorId) -> bool {
+        TENSOR_REGISTRY.with_borrow(|r| r[id.0].requires_grad)
+    }
+
     pub fn prealloc_gradients(backend: &dyn Backend) {
         TENSOR_REGISTRY.with_borrow_mut(|r| {
             for tensor in r.iter_mut() {
-                if tensor.requires_grad && tensor.gradient.is_none() {
-                    tensor.gradient = Some(LazyBuffer::new(
-                        tensor.id,
-                        vec![0.0; tensor.buffer.get_size()],
-                    ));
+                if needs_stored_gradient(tensor) && tensor.gradient.is_none() {
+                    tensor.gradient =
+                        Some(LazyBuffer::filled(tensor.id, tensor.buffer.get_size(), 0.0));
+                    tensor.gradient.as_ref().unwrap().realize(backend, false);
+                }
+            }
+        });
+    }
+    // gives every tensor that requires grad a new zeroed gradient buffer, leaving the previous
+    // gradient buffers (and anyone still holding their handles) untouched
+    fn fresh_gradients(backend: &dyn Backend) {
+        TENSOR_REGISTRY.with_borrow_mut(|r| {
+            for tensor in r.iter_mut() {
+                if needs_stored_gradient(tensor) {
+                    tensor.gradient =
+                        Some(LazyBuffer::filled(tensor.id, tensor.buffer.get_size(), 0.0));
                     tensor.gradient.as_ref().unwrap().realize(backend, false);
                 }
             }
@@ -80,29 +234,35 @@ impl Tensor {
             | LazyOp::Subtract(a, b)
             | LazyOp::Multiply(a, b)
             | LazyOp::Divide(a, b) => {
-                if let Some(id) = OP_CACHE.with_borrow_mut(|c| c.get(&(a, b)).cloned()) {
+                let key = (discriminant(&op), a, b);
+                if let Some(id) = OP_CACHE.with_borrow_mut(|c| c.get(&key).cloned()) {
                     return TENSOR_REGISTRY.with_borrow(|r| r[id.0].clone());
                 }
             }
             _ => {}
         }
         let id = get_next_tensor_id();
+        let buffer = LazyBuffer::from_tensor_op(id, op);
+        // an op on constants comes back folded into plain data, which is a constant as well and
+        // must not turn into a trainable leaf
+        let folded = matches!(buffer.get_op(), LazyOp::Creation(_));
         let t = Tensor {
             id,
-            buffer: LazyBuffer::from_tensor_op(id, op),
+            buffer,
             gradient: None,
-            requires_grad: true,
+            requires_grad: !folded,
         };
         TENSOR_REGISTRY.with_borrow_mut(|r| {
             r.push(t.clone());
         });
-        match t.buffer.get_op() {
+        let op = t.buffer.get_op();
+        match op {
             LazyOp::Add(a, b)
             | LazyOp::Subtract(a, b)
             | LazyOp::Multiply(a, b)
             | LazyOp::Divide(a, b) => {
                 OP_CACHE.with_borrow_mut(|c| {
-                    c.insert((a, b), id);
+                    c.insert((discriminant(&op), a, b), id);
                 });
             }
             _ => {}
@@ -110,21 +270,749 @@ impl Tensor {
 
         t
     }
+    // fallible versions of the operator overloads, they report operand size mismatches at the
+    // point the graph is built instead of panicking
+    pub fn try_add(&self, other: &Tensor) -> Result<Tensor, FlameError> {
+        Self::check_sizes(self, other)?;
+        Ok(Tensor::from_operation(LazyOp::Add(
+            self.buffer,
+            other.buffer,
+        )))
+    }
+    pub fn try_sub(&self, other: &Tensor) -> Result<Tensor, FlameError> {
+        Self::check_sizes(self, other)?;
+        Ok(Tensor::from_operation(LazyOp::Subtract(
+            self.buffer,
+            other.buffer,
+        )))
+    }
+    pub fn try_mul(&self, other: &Tensor) -> Result<Tensor, FlameError> {
+        Self::check_sizes(self, other)?;
+        Ok(Tensor::from_operation(LazyOp::Multiply(
+            self.buffer,
+            other.buffer,
+        )))
+    }
+    pub fn try_div(&self, other: &Tensor) -> Result<Tensor, FlameError> {
+        Self::check_sizes(self, other)?;
+        Ok(Tensor::from_operation(LazyOp::Divide(
+            self.buffer,
+            other.buffer,
+        )))
+    }
+    fn check_sizes(a: &Tensor, b: &Tensor) -> Result<(), FlameError> {
+        let (a_size, b_size) = (a.buffer.get_size(), b.buffer.get_size());
+        if let Some(empty) = [a, b].into_iter().find(|t| t.buffer.get_size() == 0) {
+            return Err(FlameError::EmptyOperand(empty.id));
+        }
+        if a_size != b_size {
+            return Err(FlameError::SizeMismatch(a_size, b_size));
+        }
+        Ok(())
+    }
+    // running total along dim, tensors are 1D for now so dim has to be 0
+    pub fn cumsum(&self, dim: usize) -> Tensor {
+        assert_eq!(dim, 0, "cumsum only supports 1D tensors for now");
+        Tensor::from_operation(LazyOp::CumSum(self.buffer, false))
+    }
+    // picks a where cond is non-zero and b everywhere else
+    pub fn where_mask(cond: &Tensor, a: &Tensor, b: &Tensor) -> Tensor {
+        Tensor::from_operation(LazyOp::Where(cond.buffer, a.buffer, b.buffer))
+    }
+    // picks a where self is greater than 0 and b everywhere else, zero included. the gradient
+    // goes to the picked branch only, self gets none
+    pub fn where_positive(&self, a: &Tensor, b: &Tensor) -> Tensor {
+        // sign + |sign| is 2 for positive elements and 0 otherwise
+        let sign = self.sign();
+        Tensor::where_mask(&(sign + sign.abs()), a, b)
+    }
+    // 1 where self is greater than other and 0 everywhere else, ties included. it's a mask, so
+    // neither side gets a gradient
+    pub fn gt(&self, other: &Tensor) -> Tensor {
+        let sign = (*self - *other).sign();
+        (sign + sign.abs()).affine(0.5, 0.0)
+    }
+    // value where mask is non-zero and self everywhere else, e.g. to hide padded positions. the
+    // filled elements pass no gradient back to self
+    pub fn masked_fill(&self, mask: &Tensor, value: f32) -> Tensor {
+        Tensor::where_mask(mask, &self.full_like(value), self)
+    }
+    // elementwise max. a tie gives the mean of both, so each side gets half the gradient there
+    pub fn maximum(&self, other: &Tensor) -> Tensor {
+        let tie = (*self + *other).affine(0.5, 0.0);
+        let other_or_tie = Tensor::where_mask(&other.gt(self), other, &tie);
+        Tensor::where_mask(&self.gt(other), self, &other_or_tie)
+    }
+    pub fn shape(&self) -> Vec<usize> {
+        TENSOR_SHAPES
+            .with_borrow(|shapes| shapes.get(&self.id).cloned())
+            .unwrap_or_else(|| vec![self.buffer.get_size()])
+    }
+    // same data under a different shape, nothing is copied and the view shares the buffer, so
+    // gradients of anything built on the view land on the original tensor unchanged.
+    // the view itself never gets a gradient of its own
+    pub fn reshape(&self, shape: &[usize]) -> Tensor {
+        let size = self.buffer.get_size();
+        if shape.iter().product::<usize>() != size {
+            panic!("Cannot reshape tensor of size {} into {:?}", size, shape);
+        }
+        let id = get_next_tensor_id();
+        let t = Tensor {
+            id,
+            buffer: self.buffer,
+            gradient: None,
+            requires_grad: false,
+        };
+        TENSOR_REGISTRY.with_borrow_mut(|r| {
+            r.push(t);
+        });
+        t.set_shape(shape.to_vec());
+        t
+    }
+    // same values in a new buffer that backward doesn't propagate through, anything built on
+    // it treats it as a constant
+    pub fn detach(&self) -> Tensor {
+        let id = get_next_tensor_id();
+        let t = Tensor {
+            id,
+            buffer: LazyBuffer::from_tensor_op(id, LazyOp::Unary(self.buffer, UnaryOp::Copy)),
+            gradient: None,
+            requires_grad: false,
+        };
+        TENSOR_REGISTRY.with_borrow_mut(|r| {
+            r.push(t);
+        });
+        t.set_shape(self.shape());
+        t
+    }
+    // realizes the tensor and returns a leaf holding its values without the graph behind it,
+    // e.g. the hidden state between steps of truncated backprop through time, so the graph
+    // doesn't grow with every step. unlike detach nothing stays lazy
+    pub fn detach_into_leaf(&self, backend: &dyn Backend) -> Tensor {
+        let leaf = self.detach();
+        leaf.buffer.realize(backend, false);
+        leaf.buffer.make_leaf();
+        leaf
+    }
+    // has the values of forward, but its gradient goes to grad_source as if it were
+    // grad_source itself, e.g. for straight-through estimators
+    pub fn with_grad_of(forward: &Tensor, grad_source: &Tensor) -> Tensor {
+        *grad_source + (*forward - *grad_source).detach()
+    }
+    // the same values, but the gradient flowing back through it is multiplied by factor, e.g.
+    // for loss scaling or to slow down the learning of one part of a model
+    pub fn scale_grad(&self, factor: f32) -> Tensor {
+        Tensor::from_operation(LazyOp::ScaleGrad(self.buffer, factor))
+    }
+    fn set_shape(&self, shape: Vec<usize>) {
+        TENSOR_SHAPES.with_borrow_mut(|shapes| {
+            shapes.insert(self.id, shape);
+        });
+    }
+    // the tensor build returns, with the given shape. an elementwise result can come out of
+    // OP_CACHE and belong to an earlier caller, that tensor keeps its own shape and this one
+    // gets a view of it instead
+    fn build_shaped(shape: Vec<usize>, build: impl FnOnce() -> Tensor) -> Tensor {
+        let first_new_id = TENSOR_ID_COUNTER.with_borrow(|c| *c);
+        let t = build();
+        if t.id.0 < first_new_id {
+            return t.reshape(&shape);
+        }
+        t.set_shape(shape);
+        t
+    }
+    fn matrix_dims(&self, op_name: &str) -> (usize, usize) {
+        match self.shape()[..] {
+            [rows, cols] => (rows, cols),
+            ref shape => panic!("{} needs a 2D tensor, got shape {:?}", op_name, shape),
+        }
+    }
+    // swaps the two axes of a 2D tensor, the data is actually rearranged
+    pub fn transpose(&self) -> Tensor {
+        let (rows, cols) = self.matrix_dims("transpose");
+        Tensor::build_shaped(vec![cols, rows], || {
+            Tensor::from_operation(LazyOp::Transpose(self.buffer, rows, cols))
+        })
+    }
+    pub fn t(&self) -> Tensor {
+        self.transpose()
+    }
+    // the upper triangle of a 2D tensor, elements below the given diagonal are zeroed. 0 is the
+    // main diagonal, positive ones lie above it. masked elements get a zero gradient
+    pub fn triu(&self, diagonal: i32) -> Tensor {
+        self.triangle_mask("triu", diagonal, true)
+    }
+    // the lower triangle, zeroing the elements above the diagonal, e.g. a causal attention mask
+    pub fn tril(&self, diagonal: i32) -> Tensor {
+        self.triangle_mask("tril", diagonal, false)
+    }
+    fn triangle_mask(&self, op_name: &str, diagonal: i32, upper: bool) -> Tensor {
+        let (_, cols) = self.matrix_dims(op_name);
+        Tensor::build_shaped(self.shape(), || {
+            Tensor::from_operation(LazyOp::TriangleMask(self.buffer, cols, diagonal, upper))
+        })
+    }
+    // swaps the last two axes, [.., m, n] becomes [.., n, m] and every leading index keeps
+    // its own matrix, e.g. to get B^T for each batch of a batched matmul
+    pub fn transpose_last_two(&self) -> Tensor {
+        let shape = self.shape();
+        if shape.len() < 2 {
+            panic!(
+                "transpose_last_two needs at least 2 dimensions, got shape {:?}",
+                shape
+            );
+        }
+        let (rows, cols) = (shape[shape.len() - 2], shape[shape.len() - 1]);
+        let batch = shape[..shape.len() - 2].iter().product();
+        let mut transposed = shape[..shape.len() - 2].to_vec();
+        transposed.extend([cols, rows]);
+        Tensor::build_shaped(transposed, || {
+            Tensor::from_operation(LazyOp::BatchTranspose(self.buffer, batch, rows, cols))
+        })
+    }
+    // matrix product of (m x k) and (k x n) tensors, `*` stays elementwise
+    pub fn matmul(&self, other: &Tensor) -> Tensor {
+        let (m, k) = self.matrix_dims("matmul");
+        let (other_k, n) = other.matrix_dims("matmul");
+        if k != other_k {
+            panic!(
+                "Shape mismatch in matmul: {:?} vs {:?}",
+                self.shape(),
+                other.shape()
+            );
+        }
+        Tensor::build_shaped(vec![m, n], || {
+            Tensor::from_operation(LazyOp::MatMul(self.buffer, other.buffer, m, k, n))
+        })
+    }
+    pub fn mm(&self, other: &Tensor) -> Tensor {
+        self.matmul(other)
+    }
+    // a dense layer, activation(self @ weight + bias) for self [m, k], weight [k, n] and bias
+    // [n]. the bias add and the activation run as one fused op after the matmul, so the sum is
+    // never written out on its own
+    pub fn linear_activation(
+        &self,
+        weight: &Tensor,
+        bias: &Tensor,
+        activation: Activation,
+    ) -> Tensor {
+        let product = self.matmul(weight);
+        let (m, n) = product.matrix_dims("linear_activation");
+        Tensor::build_shaped(vec![m, n], || {
+            Tensor::from_operation(LazyOp::BiasActivation(
+                product.buffer,
+                bias.buffer,
+                n,
+                activation,
+            ))
+        })
+    }
+    pub fn flatten(&self) -> Tensor {
+        self.reshape(&[self.buffer.get_size()])
+    }
+    // inserts a size 1 axis before dim, dim can be one past the last axis
+    pub fn unsqueeze(&self, dim: usize) -> Tensor {
+        let mut shape = self.shape();
+        if dim > shape.len() {
+            panic!("Cannot unsqueeze dim {} of shape {:?}", dim, shape);
+        }
+        shape.insert(dim, 1);
+        self.reshape(&shape)
+    }
+    // removes axis dim, which has to be of size 1
+    pub fn squeeze(&self, dim: usize) -> Tensor {
+        let mut shape = self.shape();
+        if shape.get(dim) != Some(&1) {
+            panic!("Cannot squeeze dim {} of shape {:?}", dim, shape);
+        }
+        shape.remove(dim);
+        self.reshape(&shape)
+    }
+    // zeroes each element with probability p and scales the rest by 1 / (1 - p). every call
+    // draws a new mask, reproducible through random::seed
+    pub fn dropout(&self, p: f32) -> Tensor {
+        if !(0.0..1.0).contains(&p) {
+            panic!("Dropout probability has to be in [0, 1), got {}", p);
+        }
+        let mask = LazyBuffer::random(None, self.buffer.get_size(), |stream| {
+            CreationType::RandomMask(stream, p)
+        });
+        Tensor::from_operation(LazyOp::Multiply(self.buffer, mask))
+    }
+    pub fn abs(&self) -> Tensor {
+        Tensor::from_operation(LazyOp::Unary(self.buffer, UnaryOp::Abs))
+    }
+    // self * scale + shift as a single op instead of a multiply and an add with constant tensors
+    pub fn affine(&self, scale: f32, shift: f32) -> Tensor {
+        Tensor::from_operation(LazyOp::Affine(self.buffer, scale, shift))
+    }
+    // -1, 0 or 1 per element, 0 for both zeros. its gradient is zero everywhere
+    pub fn sign(&self) -> Tensor {
+        Tensor::from_operation(LazyOp::Unary(self.buffer, UnaryOp::Sign))
+    }
+    pub fn sqrt(&self) -> Tensor {
+        Tensor::from_operation(LazyOp::Unary(self.buffer, UnaryOp::Sqrt))
+    }
+    // round, floor and ceil have a zero gradient, Tensor::with_grad_of(&x.round(), &x) passes
+    // the gradient straight through instead. round takes halfway cases to the even neighbour
+    pub fn round(&self) -> Tensor {
+        Tensor::from_operation(LazyOp::Unary(self.buffer, UnaryOp::Round))
+    }
+    pub fn floor(&self) -> Tensor {
+        Tensor::from_operation(LazyOp::Unary(self.buffer, UnaryOp::Floor))
+    }
+    pub fn ceil(&self) -> Tensor {
+        Tensor::from_operation(LazyOp::Unary(self.buffer, UnaryOp::Ceil))
+    }
+    pub fn sin(&self) -> Tensor {
+        Tensor::from_operation(LazyOp::Unary(self.buffer, UnaryOp::Sin))
+    }
+    pub fn cos(&self) -> Tensor {
+        Tensor::from_operation(LazyOp::Unary(self.buffer, UnaryOp::Cos))
+    }
+    // single element tensor
+    pub fn sum(&self) -> Tensor {
+        Tensor::from_operation(LazyOp::Sum(self.buffer))
+    }
+    // single element tensor
+    pub fn mean(&self) -> Tensor {
+        let scale = 1.0 / self.buffer.get_size() as f32;
+        Tensor::from_operation(LazyOp::Multiply(
+            self.sum().buffer,
+            LazyBuffer::scratch(vec![scale]),
+        ))
+    }
+    // single element tensor, the mean squared distance from the mean. unbiased divides by n - 1
+    // instead of n (Bessel's correction), which makes a single element NaN. an empty tensor panics
+    pub fn var(&self, unbiased: bool) -> Tensor {
+        let size = self.buffer.get_size();
+        if size == 0 {
+            panic!("var of an empty tensor");
+        }
+        let mean = Tensor::from_operation(LazyOp::Expand(self.mean().buffer, size));
+        let diff = *self - mean;
+        let count = if unbiased { size - 1 } else { size };
+        Tensor::from_operation(LazyOp::Multiply(
+            (diff * diff).sum().buffer,
+            LazyBuffer::scratch(vec![1.0 / count as f32]),
+        ))
+    }
+    // single element tensor, the square root of the unbiased variance
+    pub fn std(&self) -> Tensor {
+        self.var(true).sqrt()
+    }
+    // single element tensor. the L1 gradient at 0 is taken as 0, the L2 gradient of an all
+    // zero tensor is NaN
+    pub fn norm(&self, kind: NormKind) -> Tensor {
+        match kind {
+            NormKind::L1 => self.abs().sum(),
+            NormKind::L2 => (self * self).sum().sqrt(),
+        }
+    }
+    // x / (||x|| + eps) with the L2 norm taken over the last axis, so every row of an
+    // embedding batch ends up with unit length. eps keeps a tiny row from blowing up, an all
+    // zero row stays zero but gets a NaN gradient like norm(NormKind::L2)
+    pub fn l2_normalize(&self, eps: f32) -> Tensor {
+        let shape = self.shape();
+        let len = *shape.last().unwrap();
+        let rows = self.buffer.get_size() / len;
+        // row sums of the squares as a matmul with a column of ones
+        let norms = (self * self)
+            .reshape(&[rows, len])
+            .matmul(&Tensor::full(len, 1.0).reshape(&[len, 1]))
+            .sqrt()
+            .affine(1.0, eps);
+        Tensor::build_shaped(shape, || {
+            *self / norms.reshape(&[rows, 1]).broadcast_to(vec![rows, len])
+        })
+    }
+    // log of the softmax over the last axis, x - max - ln(sum(exp(x - max))) per row in one op.
+    // stays finite for logits in the hundreds where exp alone overflows, the preferred input to
+    // a cross-entropy loss
+    pub fn log_softmax(&self) -> Tensor {
+        let shape = self.shape();
+        let row_len = *shape.last().unwrap();
+        Tensor::build_shaped(shape, || {
+            Tensor::from_operation(LazyOp::LogSoftmax(self.buffer, row_len))
+        })
+    }
+    // max over windows of kernel elements, starting every stride elements. a trailing partial
+    // window is dropped
+    pub fn max_pool1d(&self, kernel: usize, stride: usize) -> Tensor {
+        Tensor::from_operation(LazyOp::MaxPool1d(self.buffer, kernel, stride))
+    }
+    // resamples to new_len elements, keeping the first and last ones in place
+    pub fn interpolate_linear(&self, new_len: usize) -> Tensor {
+        Tensor::from_operation(LazyOp::InterpolateLinear(self.buffer, new_len))
+    }
+    // moves a realized tensor between backends, e.g. parameters trained on the CPU to Vulkan.
+    // a computed tensor becomes a leaf holding its current values. the gradient moves along
+    pub fn to_backend(&mut self, from: &dyn Backend, to: &dyn Backend) {
+        self.buffer.migrate(from, to);
+        let gradient = TENSOR_REGISTRY.with_borrow(|r| r[self.id.0].gradient);
+        if let Some(gradient) = gradient.filter(|g| g.get_device_handle().is_some()) {
+            // the stored values move as they are, the step after backward made them stale and
+            // migrate would otherwise recompute them
+            gradient.make_leaf();
+            gradient.migrate(from, to);
+        }
+    }
+    // dot products of the kernel with every window it fits entirely inside, starting every stride
+    // elements. like max_pool1d a trailing partial window is dropped, and the kernel isn't flipped
+    pub fn conv1d(&self, kernel: &Tensor, stride: usize) -> Tensor {
+        Tensor::from_operation(LazyOp::Conv1d(self.buffer, kernel.buffer, stride))
+    }
+    // the rows of a 2D table picked by indices, an index tensor of shape s gives s + [row_len].
+    // the indices are floats holding row numbers and get no gradient, the table's gradient
+    // adds up the rows for indices that repeat
+    pub fn gather(&self, indices: &Tensor) -> Tensor {
+        let (_, row_len) = self.matrix_dims("gather");
+        let mut shape = indices.shape();
+        shape.push(row_len);
+        Tensor::build_shaped(shape, || {
+            Tensor::from_operation(LazyOp::Gather(self.buffer, indices.buffer, row_len))
+        })
+    }
+    // the 2D table with the rows of updates added at the given indices, the opposite of gather.
+    // updates has one row per index and repeated indices accumulate. the indices get no
+    // gradient, the table's passes through and the updates' is gathered back from the rows
+    pub fn scatter_add(&self, indices: &Tensor, updates: &Tensor) -> Tensor {
+        let (_, row_len) = self.matrix_dims("scatter_add");
+        Tensor::build_shaped(self.shape(), || {
+            Tensor::from_operation(LazyOp::ScatterAdd(
+                self.buffer,
+                indices.buffer,
+                updates.buffer,
+                row_len,
+            ))
+        })
+    }
+    // left zeros, then the tensor, then right zeros
+    pub fn pad(&self, left: usize, right: usize) -> Tensor {
+        Tensor::from_operation(LazyOp::Pad(self.buffer, left, right))
+    }
+    // every element repeated n times in a row, [1, 2] -> [1, 1, 2, 2] for n = 2
+    pub fn repeat_interleave(&self, n: usize) -> Tensor {
+        if n == 0 {
+            panic!("repeat_interleave needs at least one repeat");
+        }
+        Tensor::from_operation(LazyOp::RepeatInterleave(self.buffer, n))
+    }
+    // self repeated along the axes where it has size 1, [3] -> [4, 3] puts it in every row.
+    // the shapes are lined up from the last axis and missing leading axes count as 1, every
+    // other axis has to match. unlike the elementwise ops nothing is broadcast implicitly
+    pub fn broadcast_to(&self, shape: Vec<usize>) -> Tensor {
+        let source = self.shape();
+        if source.len() > shape.len()
+            || source
+                .iter()
+                .rev()
+                .zip(shape.iter().rev())
+                .any(|(&from, &to)| from != 1 && from != to)
+        {
+            panic!(
+                "Cannot broadcast tensor of shape {:?} to {:?}",
+                source, shape
+            );
+        }
+        let mut current = vec![1; shape.len() - source.len()];
+        current.extend(source);
+        // one op per broadcast axis, from the last one so inner already covers the axes after it
+        let mut t = *self;
+        for axis in (0..shape.len()).rev() {
+            if current[axis] == 1 && shape[axis] != 1 {
+                let inner = current[axis + 1..].iter().product();
+                t = Tensor::from_operation(LazyOp::BroadcastTo(t.buffer, shape[axis], inner));
+                current[axis] = shape[axis];
+            }
+        }
+        if t.buffer == self.buffer {
+            return self.reshape(&shape);
+        }
+        t.set_shape(shape);
+        t
+    }
+    // batch norm with the running statistics, for evaluation. the last axis holds the features,
+    // so x is either a single sample of them or a [batch, features] tensor, and every
+    // parameter has one element per feature
+    pub fn batch_norm(
+        &self,
+        gamma: &Tensor,
+        beta: &Tensor,
+        running_mean: &Tensor,
+        running_var: &Tensor,
+        eps: f32,
+    ) -> Tensor {
+        let shape = self.shape();
+        let features = *shape.last().unwrap();
+        for parameter in [gamma, beta, running_mean, running_var] {
+            if parameter.buffer.get_size() != features {
+                panic!(
+                    "batch_norm parameters need {} elements, got {}",
+                    features,
+                    parameter.buffer.get_size()
+                );
+            }
+        }
+        // folded per feature before broadcasting, gamma * (x - mean) / std + beta is
+        // x * scale + shift
+        let scale = *gamma / (*running_var + Tensor::full(features, eps)).sqrt();
+        let shift = *beta - *running_mean * scale;
+        let rows = self.buffer.get_size() / features;
+        Tensor::build_shaped(shape, || {
+            *self * scale.broadcast_rows(rows) + shift.broadcast_rows(rows)
+        })
+    }
+    // a [rows, len] tensor with self in every row
+    fn broadcast_rows(&self, rows: usize) -> Tensor {
+        if rows == 1 {
+            return *self;
+        }
+        self.reshape(&[self.buffer.get_size()])
+            .broadcast_to(vec![rows, self.buffer.get_size()])
+    }
+    // gradient checkpointing: the intermediates this tensor is computed from are freed after
+    // every realize instead of staying on the device, down to the data tensors or earlier
+    // checkpoints. backward computes them again from those inputs when a gradient reads them,
+    // so the gradients are unchanged and fewer buffers are alive at once
+    pub fn checkpoint(&self) -> Tensor {
+        self.buffer.checkpoint();
+        *self
+    }
     pub fn realize(&mut self, backend: &dyn Backend) {
         self.buffer.realize(backend, false);
     }
     pub fn realize_to_host(&mut self, backend: &dyn Backend) {
         self.buffer.realize(backend, true);
     }
+    // computes an elementwise op tile by tile so that only a tile of each operand, the result
+    // and the upload staging buffer live on the device at once, instead of the whole tensors.
+    // the operands are read from host data where possible and the result is returned on the host
+    pub fn chunk_realize(&self, backend: &dyn Backend) -> Vec<f32> {
+        self.chunk_realize_sliced(backend, usize::MAX, |_| {})
+    }
+    // chunk_realize with no tile longer than slice elements, so a huge op goes out as many short
+    // dispatches instead of one that holds the queue and the host until it's done. yield_point
+    // is called after every tile with the number of elements done so far, e.g. to poll other
+    // work or std::thread::yield_now. the result is the same as a single dispatch
+    pub fn chunk_realize_sliced(
+        &self,
+        backend: &dyn Backend,
+        slice: usize,
+        mut yield_point: impl FnMut(usize),
+    ) -> Vec<f32> {
+        if slice == 0 {
+            panic!("chunk_realize_sliced needs slices of at least one element");
+        }
+        let (a, b) = match self.buffer.get_op() {
+            LazyOp::Add(a, b)
+            | LazyOp::Subtract(a, b)
+            | LazyOp::Multiply(a, b)
+            | LazyOp::Divide(a, b) => (a, b),
+            op => panic!("chunk_realize only supports elementwise ops, got {:?}", op),
+        };
+        let (a_data, b_data) = (Self::host_data(a, backend), Self::host_data(b, backend));
+        let size = self.buffer.get_size();
+        // lhs, rhs, result and staging per tile
+        let tile = match backend.memory_budget() {
+            Some(bytes) => (bytes / (4 * std::mem::size_of::<f32>())).clamp(1, size),
+            None => size,
+        }
+        .min(slice);
+
+        // registered like any buffer, ids have to stay in step with the registry slots
+        let allocate_tile = || {
+            let id = get_next_buffer_id();
+            LazyBuffer::push_scratch(id, tile, LazyOp::Creation(CreationType::Created));
+            backend.allocate_buffer(id, tile)
+        };
+        let a_tile = allocate_tile();
+        let b_tile = allocate_tile();
+        let result_tile = allocate_tile();
+        let mut result = Vec::with_capacity(size);
+        for start in (0..size).step_by(tile) {
+            let end = (start + tile).min(size);
+            backend.to_device(&a_data[start..end], &a_tile);
+            backend.to_device(&b_data[start..end], &b_tile);
+            let n = end - start;
+            match self.buffer.get_op() {
+                LazyOp::Add(_, _) => backend.add(&a_tile, &b_tile, &result_tile, n),
+                LazyOp::Subtract(_, _) => backend.subtract(&a_tile, &b_tile, &result_tile, n),
+                LazyOp::Multiply(_, _) => backend.multiply(&a_tile, &b_tile, &result_tile, n),
+                LazyOp::Divide(_, _) => backend.divide(&a_tile, &b_tile, &result_tile, n),
+                _ => unreachable!(),
+            }
+            result.extend_from_slice(&backend.read_buffer(&result_tile)[..n]);
+            yield_point(end);
+        }
+        backend.free_buffer(&a_tile);
+        backend.free_buffer(&b_tile);
+        backend.free_buffer(&result_tile);
+        result
+    }
+    fn host_data(buffer: LazyBufferHandle, backend: &dyn Backend) -> Vec<f32> {
+        if let LazyOp::Creation(CreationType::RawData(data)) = buffer.get_op() {
+            return data.to_vec();
+        }
+        if buffer.get_device_handle().is_none() || buffer.is_stale() || buffer.is_reused() {
+            buffer.realize(backend, false);
+        }
+        buffer.get_data(backend)
+    }
+    // reads a single element, realizing the tensor first if it has no device buffer yet or its
+    // values predate the last parameter update.
+    // nothing is cached host-side, so every call is its own download; use get_data for bulk reads
+    pub fn get(&mut self, backend: &dyn Backend, i: usize) -> f32 {
+        if self.buffer.get_device_handle().is_none()
+            || self.buffer.is_stale()
+            || self.buffer.is_reused()
+        {
+            self.realize(backend);
+        }
+        let size = self.buffer.get_size();
+        if i >= size {
+            panic!("Index {} out of bounds for tensor of size {}", i, size);
+        }
+        backend.read_element(&self.buffer.get_device_handle().unwrap(), i)
+    }
+    // the value of a single element tensor such as a loss, e.g. for logging
+    pub fn item(&mut self, backend: &dyn Backend) -> f32 {
+        let size = self.buffer.get_size();
+        if size != 1 {
+            panic!("item needs a single element tensor, got size {}", size);
+        }
+        self.get(backend, 0)
+    }
+    // bit-exact comparison of shape and values, e.g. for deterministic runs or round trips
+    // through a checkpoint. -0.0 and 0.0 differ, NaNs are equal if their bits are
+    pub fn equal(&mut self, other: &mut Tensor, backend: &dyn Backend) -> bool {
+        if self.shape() != other.shape() {
+            return false;
+        }
+        let (lhs, rhs) = (
+            Self::host_data(self.buffer, backend),
+            Self::host_data(other.buffer, backend),
+        );
+        lhs.iter()
+            .zip(&rhs)
+            .all(|(l, r)| l.to_bits() == r.to_bits())
+    }
+    // the values laid out by shape like numpy prints them, a 2D tensor as aligned rows and higher
+    // ranks as nested brackets. an axis longer than 2 * FORMAT_EDGE_ITEMS shows its first and
+    // last FORMAT_EDGE_ITEMS entries around a "..."
+    pub fn format(&mut self, backend: &dyn Backend) -> String {
+        let data = Self::host_data(self.buffer, backend);
+        let shape = self.shape();
+        let width = Self::format_width(&data, &shape);
+        Self::format_block(&data, &shape, 1, width)
+    }
+    // indices of an axis of length n that get printed, None where the rest is elided
+    fn shown_indices(n: usize) -> Vec<Option<usize>> {
+        if n <= 2 * FORMAT_EDGE_ITEMS {
+            return (0..n).map(Some).collect();
+        }
+        (0..FORMAT_EDGE_ITEMS)
+            .map(Some)
+            .chain([None])
+            .chain((n - FORMAT_EDGE_ITEMS..n).map(Some))
+            .collect()
+    }
+    // widest printed value, every value is padded to it so the columns line up
+    fn format_width(data: &[f32], shape: &[usize]) -> usize {
+        let stride: usize = shape[1..].iter().product();
+        Self::shown_indices(shape[0])
+            .into_iter()
+            .flatten()
+            .map(|i| match shape.len() {
+                1 => data[i].to_string().len(),
+                _ => Self::format_width(&data[i * stride..(i + 1) * stride], &shape[1..]),
+            })
+            .max()
+            .unwrap_or(0)
+    }
+    fn format_block(data: &[f32], shape: &[usize], indent: usize, width: usize) -> String {
+        let stride: usize = shape[1..].iter().product();
+        let items: Vec<String> = Self::shown_indices(shape[0])
+            .into_iter()
+            .map(|i| match (i, shape.len()) {
+                (None, _) => "...".to_string(),
+                (Some(i), 1) => format!("{:>width$}", data[i]),
+                (Some(i), _) => Self::format_block(
+                    &data[i * stride..(i + 1) * stride],
+                    &shape[1..],
+                    indent + 1,
+                    width,
+                ),
+            })
+            .collect();
+        // rows end their line, and every further axis adds a blank line between its blocks
+        let separator = match shape.len() {
+            1 => ", ".to_string(),
+            rank => format!(",{}{}", "\n".repeat(rank - 1), " ".repeat(indent)),
+        };
+        format!("[{}]", items.join(&separator))
+    }
+    // realizes the tensor if needed and yields its values from a single download, e.g.
+    // tensor.iter_realized(backend).enumerate().max_by(..) for an argmax
+    pub fn iter_realized(&self, backend: &dyn Backend) -> impl Iterator<Item = f32> + use<> {
+        Self::host_data(self.buffer, backend).into_iter()
+    }
 
     pub fn apply_backward(&mut self, backend: &dyn Backend, lr: f32) {
         self.realize(backend);
         self.backward(backend);
-        let temp_buffer = backend
-            .allocate_temporary_buffer(&vec![lr; self.buffer.get_size()], self.buffer.get_size());
+        Self::step(backend, lr);
+    }
+    // like apply_backward, but refuses to update anything if the step would turn any parameter
+    // into NaN/Inf so the caller can retry with a smaller learning rate. the check downloads every
+    // parameter and gradient, so it is noticeably slower than apply_backward on the GPU
+    pub fn apply_backward_checked(
+        &mut self,
+        backend: &dyn Backend,
+        lr: f32,
+    ) -> Result<(), FlameError> {
+        self.realize(backend);
+        self.backward(backend);
+        Self::step_checked(backend, lr)
+    }
+    // step, but nothing is updated if any parameter would become NaN/Inf, see
+    // apply_backward_checked
+    pub(crate) fn step_checked(backend: &dyn Backend, lr: f32) -> Result<(), FlameError> {
+        let non_finite = TENSOR_REGISTRY.with_borrow(|r| {
+            r.iter()
+                .filter(|tensor| tensor.gradient.is_some() && tensor.is_leaf())
+                .find(|tensor| {
+                    let values = tensor.buffer.get_data(backend);
+                    let gradient = tensor.gradient.unwrap().get_data(backend);
+                    values
+                        .iter()
+                        .zip(gradient.iter())
+                        .any(|(value, grad)| !(value - lr * grad).is_finite())
+                })
+                .map(|tensor| tensor.id)
+        });
+        if let Some(id) = non_finite {
+            return Err(FlameError::NonFiniteUpdate(id));
+        }
+        Self::step(backend, lr);
+        Ok(())
+    }
+    // plain SGD over every leaf tensor that has a gradient. intermediates get gradients during
+    // backward as well but are recomputed from the leaves, so writing into them would be wrong
+    pub(crate) fn step(backend: &dyn Backend, lr: f32) {
+        // one lr buffer has to cover the largest parameter
+        let size = TENSOR_REGISTRY.with_borrow(|r| {
+            r.iter()
+                .filter(|tensor| tensor.gradient.is_some() && tensor.is_leaf())
+                .map(|tensor| tensor.buffer.get_size())
+                .max()
+                .unwrap_or(0)
+        });
+        let temp_buffer = backend.allocate_temporary_buffer(&vec![lr; size], size);
         TENSOR_REGISTRY.with_borrow_mut(|r| {
             for tensor in r {
-                if tensor.gradient.is_some() {
+                if tensor.gradient.is_some() && tensor.is_leaf() {
                     backend.multiply(
                         &tensor
                             .gradient
@@ -155,139 +1043,658 @@ impl Tensor {
                 }
             }
         });
+        backend.free_buffer(&temp_buffer);
+        mark_parameters_updated();
+    }
+    // limits every element of the stored gradient to [-clip, clip] in place, e.g. between
+    // backward and the optimizer step. a tensor without a gradient is left alone
+    pub fn clamp_grad(&self, clip: f32, backend: &dyn Backend) {
+        if clip.is_nan() || clip < 0.0 {
+            panic!("Gradient clip value has to be non-negative, got {}", clip);
+        }
+        let Some(gradient) = TENSOR_REGISTRY.with_borrow(|r| r[self.id.0].gradient) else {
+            return;
+        };
+        if gradient.get_device_handle().is_none() {
+            gradient.realize(backend, false);
+        }
+        let handle = gradient.get_device_handle().unwrap();
+        backend.clamp(&handle, &handle, gradient.get_size(), -clip, clip);
+    }
+    // values of the gradient the last backward stored for this tensor, None without one
+    pub fn grad(&self, backend: &dyn Backend) -> Option<Vec<f32>> {
+        let gradient = TENSOR_REGISTRY.with_borrow(|r| r[self.id.0].gradient)?;
+        if gradient.get_device_handle().is_none() {
+            gradient.realize(backend, false);
+        }
+        Some(backend.to_host(&gradient.get_device_handle().unwrap(), gradient.get_size()))
+    }
+    // L2 norm of the stored gradient, e.g. to log per layer whether gradients vanish or explode.
+    // reduced on the device from the gradient's storage the same way as norm(NormKind::L2),
+    // 0.0 for a tensor without a gradient
+    pub fn grad_norm(&self, backend: &dyn Backend) -> f32 {
+        let Some(gradient) = TENSOR_REGISTRY.with_borrow(|r| r[self.id.0].gradient) else {
+            return 0.0;
+        };
+        if gradient.get_device_handle().is_none() {
+            gradient.realize(backend, false);
+        }
+        let handle = gradient.get_device_handle().unwrap();
+        let size = gradient.get_size();
+        let squares = backend.allocate_temporary_buffer(&vec![0.0; size], size);
+        let total = backend.allocate_temporary_buffer(&[0.0], 1);
+        backend.multiply(&handle, &handle, &squares, size);
+        backend.sum(&squares, &total, size);
+        let norm = backend.read_element(&total, 0).sqrt();
+        backend.free_buffer(&squares);
+        backend.free_buffer(&total);
+        norm
+    }
+    // adds other_grad onto the gradient currently stored for this tensor, e.g. the saved gradient
+    // of an earlier micro-batch, since every backward overwrites the previous gradients
+    pub fn accumulate_grad(&mut self, other_grad: &[f32], backend: &dyn Backend) {
+        let size = self.buffer.get_size();
+        if other_grad.len() != size {
+            panic!(
+                "Size mismatch in accumulate_grad: {} vs {}",
+                other_grad.len(),
+                size
+            );
+        }
+        let gradient = TENSOR_REGISTRY.with_borrow_mut(|r| {
+            let tensor = &mut r[self.id.0];
+            *tensor.gradient.get_or_insert_with(|| {
+                let gradient = LazyBuffer::filled(tensor.id, size, 0.0);
+                gradient.realize(backend, false);
+                gradient
+            })
+        });
+        self.gradient = Some(gradient);
+        let gradient_handle = gradient.get_device_handle().unwrap();
+        let temp_buffer = backend.allocate_temporary_buffer(other_grad, size);
+        backend.add(&gradient_handle, &temp_buffer, &gradient_handle, size);
+        backend.free_buffer(&temp_buffer);
+    }
+    // adds other into this tensor's own storage, so a running total built up in a loop stays
+    // a single data buffer instead of a chain of adds that grows every iteration. only a
+    // tensor's own data can be written: a computed one would lose the sum on its next realize,
+    // and a reshape view or a shared constant buffer would change other tensors too. like an
+    // optimizer step this mutates self in place and graphs that read self are recomputed, except
+    // ones built while self was still an unrealized tensor without grad. those were constant
+    // folded and keep the value self had back then
+    pub fn accumulate_into(&mut self, other: &Tensor, backend: &dyn Backend) {
+        let size = self.buffer.get_size();
+        if other.buffer.get_size() != size {
+            panic!(
+                "Size mismatch in accumulate_into: {} vs {}",
+                size,
+                other.buffer.get_size()
+            );
+        }
+        if !self.is_leaf() || self.buffer.get_tensor_id() != Some(self.id) {
+            panic!("accumulate_into needs a data tensor that owns its buffer");
+        }
+        if self.buffer.get_device_handle().is_none() {
+            self.buffer.realize(backend, false);
+        }
+        other.buffer.realize(backend, false);
+        let handle = self.buffer.get_device_handle().unwrap();
+        backend.add(
+            &handle,
+            &other.buffer.get_device_handle().unwrap(),
+            &handle,
+            size,
+        );
+        mark_parameters_updated();
     }
-    // TODO! need to allow accumulate here otherwise gradient gets overwritten
     pub fn backward(&mut self, backend: &dyn Backend) {
-        // currTensor, chainRule gradient
-        let mut queue = VecDeque::<(Tensor, LazyBufferHandle)>::new();
-        Self::prealloc_gradients(backend);
-        queue.push_back((
-            self.clone(),
-            LazyBuffer::scratch(vec![1.0; self.buffer.get_size()]),
-        ));
+        let seed = self.ones_seed();
+        self.backward_impl(backend, false, seed);
+    }
+    // backward starting from seed instead of ones, so the stored gradients are the
+    // vector-Jacobian product seed^T * d(self)/d(input) of a non-scalar root
+    pub fn backward_with(&mut self, backend: &dyn Backend, seed: &Tensor) {
+        let (size, seed_size) = (self.buffer.get_size(), seed.buffer.get_size());
+        if seed_size != size {
+            panic!(
+                "backward seed has {} elements, the root has {}",
+                seed_size, size
+            );
+        }
+        self.backward_impl(backend, false, seed.buffer);
+    }
+    // same as backward but the graph is left reusable: every call writes into a fresh gradient
+    // set instead of rewriting the gradient buffers of the previous call, so calling it twice on
+    // the same loss yields the same gradients
+    pub fn backward_retained(&mut self, backend: &dyn Backend) {
+        let seed = self.ones_seed();
+        self.backward_impl(backend, true, seed);
+    }
+    // the gradient of self with respect to each of inputs from a single backward pass, e.g. for
+    // attribution. nothing is stored or updated, inputs that self doesn't depend on get zeros
+    pub fn backward_grads(&mut self, inputs: &[Tensor], backend: &dyn Backend) -> Vec<Vec<f32>> {
+        let gradients = self.chain_rule_gradients(self.ones_seed());
+        inputs
+            .iter()
+            .map(|input| match gradients.get(&input.id) {
+                Some(gradient) => {
+                    gradient.realize(backend, false);
+                    let data = gradient.get_data(backend);
+                    gradient.free_scratch(backend);
+                    data
+                }
+                None => vec![0.0; input.buffer.get_size()],
+            })
+            .collect()
+    }
+    fn ones_seed(&self) -> LazyBufferHandle {
+        self.ones_like().buffer
+    }
+    fn backward_impl(&mut self, backend: &dyn Backend, retain_graph: bool, seed: LazyBufferHandle) {
+        if retain_graph {
+            Self::fresh_gradients(backend);
+        } else {
+            Self::prealloc_gradients(backend);
+        }
+        let gradients = self.chain_rule_gradients(seed);
+        TENSOR_REGISTRY.with_borrow_mut(|r| {
+            for (&id, &gradient) in &gradients {
+                let tensor = &mut r[id.0];
+                if !needs_stored_gradient(tensor) {
+                    tensor.gradient = None;
+                    continue;
+                }
+                tensor.gradient = Some(LazyBuffer::from_tensor_op(
+                    tensor.id,
+                    LazyOp::Memset(
+                        tensor
+                            .gradient
+                            .expect("Requires grad requires gradient buffer preallocated"),
+                        gradient,
+                    ),
+                ));
+            }
+        });
+        TENSOR_REGISTRY.with_borrow(|r| {
+            for tensor in r {
+                if let Some(gradient) = tensor.gradient {
+                    gradient.realize(backend, false);
+                }
+            }
+        });
+        for (id, gradient) in gradients {
+            let tensor = TENSOR_REGISTRY.with_borrow(|r| r[id.0]);
+            if tensor.gradient.is_none() {
+                gradient.free_scratch(backend);
+            }
+        }
+    }
+    // the chain rule gradient of every tensor self depends on, starting from seed as the
+    // gradient of self, as lazy buffers that are not realized or stored anywhere yet
+    fn chain_rule_gradients(&self, seed: LazyBufferHandle) -> HashMap<TensorId, LazyBufferHandle> {
+        // chain rule gradient of every tensor, summed over all of its consumers. tensors are
+        // visited consumers-first so a shared subexpression (e.g. a cached (a+b) used twice) has
+        // received every contribution before it propagates to its own operands
+        let mut gradients = HashMap::<TensorId, LazyBufferHandle>::new();
+        gradients.insert(self.id, seed);
 
-        while let Some((curr_tensor, chain_rule_gradient)) = queue.pop_front() {
+        for curr_tensor in self.reverse_topological_order() {
             if !curr_tensor.requires_grad {
                 continue;
             }
+            let Some(chain_rule_gradient) = gradients.get(&curr_tensor.id).cloned() else {
+                continue;
+            };
             match curr_tensor.buffer.get_op() {
                 LazyOp::Add(a, b) => {
-                    TENSOR_REGISTRY.with_borrow_mut(|r| {
-                        let a_tensor = &mut r[a.get_tensor_id().unwrap().0];
-                        if !a_tensor.requires_grad {
-                            return;
-                        }
-                        a_tensor.gradient = Some(LazyBuffer::from_tensor_op(
-                            a_tensor.id,
-                            LazyOp::Memset(
-                                a_tensor
-                                    .gradient
-                                    .expect("Requires grad requires gradient buffer preallocated"),
-                                chain_rule_gradient,
-                            ),
-                        ));
-                        queue.push_back((a_tensor.clone(), chain_rule_gradient.clone()));
-                    });
-
-                    TENSOR_REGISTRY.with_borrow_mut(|r| {
-                        let b_tensor = &mut r[b.get_tensor_id().unwrap().0];
-                        if !b_tensor.requires_grad {
-                            return;
-                        }
-                        b_tensor.gradient = Some(LazyBuffer::from_tensor_op(
-                            b_tensor.id,
-                            LazyOp::Memset(
-                                b_tensor
-                                    .gradient
-                                    .expect("Requires grad requires gradient buffer preallocated"),
-                                chain_rule_gradient,
-                            ),
-                        ));
-                        queue.push_back((b_tensor.clone(), chain_rule_gradient));
-                    });
+                    Self::accumulate_gradient(&mut gradients, a, chain_rule_gradient);
+                    Self::accumulate_gradient(&mut gradients, b, chain_rule_gradient);
                 }
                 LazyOp::Subtract(a, b) => {
-                    TENSOR_REGISTRY.with_borrow_mut(|r| {
-                        let a_tensor = &mut r[a.get_tensor_id().unwrap().0];
-                        if a_tensor.requires_grad {
-                            a_tensor.gradient = Some(LazyBuffer::from_tensor_op(
-                                a_tensor.id,
-                                LazyOp::Memset(
-                                    a_tensor.gradient.expect(
-                                        "Requires grad requires gradient buffer preallocated",
-                                    ),
-                                    chain_rule_gradient,
-                                ),
-                            ));
-                            queue.push_back((a_tensor.clone(), chain_rule_gradient));
-                        }
-
-                        let b_tensor = &mut r[b.get_tensor_id().unwrap().0];
-                        if !b_tensor.requires_grad {
-                            return;
-                        }
-                        b_tensor.gradient = Some(LazyBuffer::from_tensor_op(
-                            b_tensor.id,
-                            LazyOp::Memset(
-                                b_tensor
-                                    .gradient
-                                    .expect("Requires grad requires gradient buffer preallocated"),
-                                LazyBuffer::scratch_op(LazyOp::Multiply(
-                                    chain_rule_gradient,
-                                    LazyBuffer::scratch(vec![-1.0; chain_rule_gradient.get_size()]),
-                                )),
-                            ),
-                        ));
-                        queue.push_back((b_tensor.clone(), b_tensor.gradient.clone().unwrap()));
-                    });
+                    Self::accumulate_gradient(&mut gradients, a, chain_rule_gradient);
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        b,
+                        LazyBuffer::scratch_op(LazyOp::Unary(chain_rule_gradient, UnaryOp::Neg)),
+                    );
                 }
                 LazyOp::Multiply(a, b) => {
-                    TENSOR_REGISTRY.with_borrow_mut(|r| {
-                        let a_tensor = &mut r[a.get_tensor_id().unwrap().0];
-                        if !a_tensor.requires_grad {
-                            return;
-                        }
-                        a_tensor.gradient = Some(LazyBuffer::from_tensor_op(
-                            a_tensor.id,
-                            LazyOp::Memset(
-                                a_tensor
-                                    .gradient
-                                    .expect("Requires grad requires gradient buffer preallocated"),
-                                LazyBuffer::scratch_op(LazyOp::Multiply(
-                                    b,
-                                    chain_rule_gradient.clone(),
-                                )),
-                            ),
-                        ));
-                        queue.push_back((a_tensor.clone(), a_tensor.gradient.clone().unwrap()));
-                    });
-                    TENSOR_REGISTRY.with_borrow_mut(|r| {
-                        let b_tensor = &mut r[b.get_tensor_id().unwrap().0];
-                        if !b_tensor.requires_grad {
-                            return;
-                        }
-                        b_tensor.gradient = Some(LazyBuffer::from_tensor_op(
-                            b_tensor.id,
-                            LazyOp::Memset(
-                                b_tensor
-                                    .gradient
-                                    .expect("Requires grad requires gradient buffer preallocated"),
-                                LazyBuffer::scratch_op(LazyOp::Multiply(a, chain_rule_gradient)),
-                            ),
-                        ));
-                        queue.push_back((b_tensor.clone(), b_tensor.gradient.clone().unwrap()));
-                    });
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::Multiply(b, chain_rule_gradient)),
+                    );
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        b,
+                        LazyBuffer::scratch_op(LazyOp::Multiply(a, chain_rule_gradient)),
+                    );
+                }
+                // dA = dC / B and dB = -dC * A / B^2, which is -(dC / B) * C
+                LazyOp::Divide(a, b) => {
+                    let scaled = LazyBuffer::scratch_op(LazyOp::Divide(chain_rule_gradient, b));
+                    Self::accumulate_gradient(&mut gradients, a, scaled);
+                    let product =
+                        LazyBuffer::scratch_op(LazyOp::Multiply(scaled, curr_tensor.buffer));
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        b,
+                        LazyBuffer::scratch_op(LazyOp::Unary(product, UnaryOp::Neg)),
+                    );
+                }
+                // every input element feeds all outputs at or after it, so its gradient is the
+                // cumulative sum of the chain gradient taken from the other end
+                LazyOp::CumSum(a, reverse) => {
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::CumSum(chain_rule_gradient, !reverse)),
+                    );
+                }
+                // the gradient goes to whichever branch was selected, cond gets none
+                LazyOp::Where(cond, a, b) => {
+                    let zeros = LazyBuffer::scratch(vec![0.0; chain_rule_gradient.get_size()]);
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::Where(cond, chain_rule_gradient, zeros)),
+                    );
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        b,
+                        LazyBuffer::scratch_op(LazyOp::Where(cond, zeros, chain_rule_gradient)),
+                    );
+                }
+                LazyOp::Transpose(a, rows, cols) => {
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::Transpose(chain_rule_gradient, cols, rows)),
+                    );
+                }
+                // the same mask, masked elements didn't reach the output
+                LazyOp::TriangleMask(a, cols, diagonal, upper) => {
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::TriangleMask(
+                            chain_rule_gradient,
+                            cols,
+                            diagonal,
+                            upper,
+                        )),
+                    );
+                }
+                LazyOp::BatchTranspose(a, batch, rows, cols) => {
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::BatchTranspose(
+                            chain_rule_gradient,
+                            batch,
+                            cols,
+                            rows,
+                        )),
+                    );
+                }
+                // dA = dC @ B^T and dB = A^T @ dC
+                LazyOp::MatMul(a, b, m, k, n) => {
+                    let b_t = LazyBuffer::scratch_op(LazyOp::Transpose(b, k, n));
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::MatMul(chain_rule_gradient, b_t, m, n, k)),
+                    );
+                    let a_t = LazyBuffer::scratch_op(LazyOp::Transpose(a, m, k));
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        b,
+                        LazyBuffer::scratch_op(LazyOp::MatMul(a_t, chain_rule_gradient, k, m, n)),
+                    );
+                }
+                // each window's gradient goes to the element it picked
+                LazyOp::MaxPool1d(a, kernel, stride) => {
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::MaxPool1dBackward(
+                            a,
+                            chain_rule_gradient,
+                            kernel,
+                            stride,
+                        )),
+                    );
+                }
+                // each input element gets the kernel weights it was multiplied with, each kernel
+                // weight the input elements it saw, both times scaled by the window's gradient
+                LazyOp::Conv1d(a, b, stride) => {
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::Conv1dInputGrad(
+                            chain_rule_gradient,
+                            b,
+                            stride,
+                            a.get_size(),
+                        )),
+                    );
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        b,
+                        LazyBuffer::scratch_op(LazyOp::Conv1dKernelGrad(
+                            a,
+                            chain_rule_gradient,
+                            stride,
+                            b.get_size(),
+                        )),
+                    );
+                }
+                // every picked row's gradient goes back to the row it was read from
+                LazyOp::Gather(a, indices, row_len) => {
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::GatherBackward(
+                            chain_rule_gradient,
+                            indices,
+                            a.get_size() / row_len,
+                            row_len,
+                        )),
+                    );
+                }
+                // the table's gradient passes through, every update gets the gradient of the
+                // row it was added into
+                LazyOp::ScatterAdd(a, indices, updates, row_len) => {
+                    Self::accumulate_gradient(&mut gradients, a, chain_rule_gradient);
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        updates,
+                        LazyBuffer::scratch_op(LazyOp::Gather(
+                            chain_rule_gradient,
+                            indices,
+                            row_len,
+                        )),
+                    );
+                }
+                // each output's gradient is split over its two source samples by their weights
+                LazyOp::InterpolateLinear(a, _) => {
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::InterpolateLinearBackward(
+                            chain_rule_gradient,
+                            a.get_size(),
+                        )),
+                    );
+                }
+                // the padding doesn't depend on the input, only the middle flows back
+                LazyOp::Pad(a, left, _) => {
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::Slice(
+                            chain_rule_gradient,
+                            left,
+                            a.get_size(),
+                        )),
+                    );
+                }
+                // each element gets the summed gradient of its n copies
+                LazyOp::RepeatInterleave(a, n) => {
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::RepeatInterleaveBackward(
+                            chain_rule_gradient,
+                            n,
+                        )),
+                    );
+                }
+                // chain - softmax * sum(chain) per row, softmax being exp of this tensor
+                LazyOp::LogSoftmax(a, row_len) => {
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::LogSoftmaxBackward(
+                            chain_rule_gradient,
+                            curr_tensor.buffer,
+                            row_len,
+                        )),
+                    );
+                }
+                // through the activation at the values before it, recomputed from the operands.
+                // the bias was added to every row, so it gets the column sums
+                LazyOp::BiasActivation(a, b, row_len, activation) => {
+                    let pre_activation = LazyBuffer::scratch_op(LazyOp::BiasActivation(
+                        a,
+                        b,
+                        row_len,
+                        Activation::None,
+                    ));
+                    let gradient = LazyBuffer::scratch_op(LazyOp::ActivationBackward(
+                        chain_rule_gradient,
+                        pre_activation,
+                        activation,
+                    ));
+                    Self::accumulate_gradient(&mut gradients, a, gradient);
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        b,
+                        LazyBuffer::scratch_op(LazyOp::BroadcastToBackward(
+                            gradient,
+                            a.get_size() / row_len,
+                            row_len,
+                        )),
+                    );
+                }
+                // summed over the copies along the broadcast axis
+                LazyOp::BroadcastTo(a, n, inner) => {
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::BroadcastToBackward(
+                            chain_rule_gradient,
+                            n,
+                            inner,
+                        )),
+                    );
+                }
+                LazyOp::Unary(a, UnaryOp::Abs) => {
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::Multiply(
+                            chain_rule_gradient,
+                            LazyBuffer::scratch_op(LazyOp::Unary(a, UnaryOp::Sign)),
+                        )),
+                    );
+                }
+                LazyOp::Unary(a, UnaryOp::Copy) => {
+                    Self::accumulate_gradient(&mut gradients, a, chain_rule_gradient);
+                }
+                // piecewise constant, nothing flows back
+                LazyOp::Unary(
+                    _,
+                    UnaryOp::Sign | UnaryOp::Round | UnaryOp::Floor | UnaryOp::Ceil,
+                ) => {}
+                LazyOp::Unary(a, UnaryOp::Sin) => {
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::Multiply(
+                            chain_rule_gradient,
+                            LazyBuffer::scratch_op(LazyOp::Unary(a, UnaryOp::Cos)),
+                        )),
+                    );
+                }
+                LazyOp::Unary(a, UnaryOp::Cos) => {
+                    let negative_sin = LazyBuffer::scratch_op(LazyOp::Unary(
+                        LazyBuffer::scratch_op(LazyOp::Unary(a, UnaryOp::Sin)),
+                        UnaryOp::Neg,
+                    ));
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::Multiply(chain_rule_gradient, negative_sin)),
+                    );
+                }
+                LazyOp::Unary(a, UnaryOp::Neg) => {
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::Unary(chain_rule_gradient, UnaryOp::Neg)),
+                    );
+                }
+                LazyOp::Affine(a, scale, _) => {
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::Affine(chain_rule_gradient, scale, 0.0)),
+                    );
+                }
+                LazyOp::ScaleGrad(a, factor) => {
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::Affine(chain_rule_gradient, factor, 0.0)),
+                    );
+                }
+                // d sqrt(a) = 1 / (2 sqrt(a)), sqrt(a) being this tensor
+                LazyOp::Unary(a, UnaryOp::Sqrt) => {
+                    let twice = LazyBuffer::scratch_op(LazyOp::Multiply(
+                        LazyBuffer::scratch(vec![2.0; curr_tensor.buffer.get_size()]),
+                        curr_tensor.buffer,
+                    ));
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::Divide(chain_rule_gradient, twice)),
+                    );
+                }
+                LazyOp::Sum(a) => {
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::Expand(chain_rule_gradient, a.get_size())),
+                    );
+                }
+                LazyOp::Expand(a, _) => {
+                    Self::accumulate_gradient(
+                        &mut gradients,
+                        a,
+                        LazyBuffer::scratch_op(LazyOp::Sum(chain_rule_gradient)),
+                    );
                 }
                 _ => {}
             }
         }
-        TENSOR_REGISTRY.with_borrow_mut(|r| {
-            for tensor in r {
-                if tensor.gradient.is_some() {
-                    tensor.gradient.as_mut().unwrap().realize(backend, false);
+        gradients
+    }
+    fn accumulate_gradient(
+        gradients: &mut HashMap<TensorId, LazyBufferHandle>,
+        operand: LazyBufferHandle,
+        contribution: LazyBufferHandle,
+    ) {
+        let Some(id) = operand.get_tensor_id() else {
+            return;
+        };
+        if !TENSOR_REGISTRY.with_borrow(|r| r[id.0].requires_grad) {
+            return;
+        }
+        gradients
+            .entry(id)
+            .and_modify(|gradient| {
+                *gradient = LazyBuffer::scratch_op(LazyOp::Add(*gradient, contribution))
+            })
+            .or_insert(contribution);
+    }
+    // every tensor self depends on, each listed after all of the tensors that consume it
+    fn reverse_topological_order(&self) -> Vec<Tensor> {
+        fn visit(tensor: Tensor, visited: &mut HashSet<TensorId>, order: &mut Vec<Tensor>) {
+            if !visited.insert(tensor.id) {
+                return;
+            }
+            let operands = match tensor.buffer.get_op() {
+                LazyOp::Add(a, b)
+                | LazyOp::Subtract(a, b)
+                | LazyOp::Multiply(a, b)
+                | LazyOp::Divide(a, b)
+                | LazyOp::MatMul(a, b, _, _, _)
+                | LazyOp::Conv1d(a, b, _)
+                | LazyOp::BiasActivation(a, b, _, _) => vec![a, b],
+                LazyOp::CumSum(a, _)
+                | LazyOp::Transpose(a, _, _)
+                | LazyOp::TriangleMask(a, _, _, _)
+                | LazyOp::BatchTranspose(a, _, _, _)
+                | LazyOp::MaxPool1d(a, _, _)
+                | LazyOp::InterpolateLinear(a, _)
+                | LazyOp::Unary(a, _)
+                | LazyOp::Affine(a, _, _)
+                | LazyOp::ScaleGrad(a, _)
+                | LazyOp::Sum(a)
+                | LazyOp::Expand(a, _)
+                | LazyOp::RepeatInterleave(a, _)
+                | LazyOp::BroadcastTo(a, _, _)
+                | LazyOp::LogSoftmax(a, _)
+                | LazyOp::Pad(a, _, _)
+                | LazyOp::Gather(a, _, _) => vec![a],
+                LazyOp::Where(cond, a, b) => vec![cond, a, b],
+                LazyOp::ScatterAdd(a, _, updates, _) => vec![a, updates],
+                _ => vec![],
+            };
+            for operand in operands {
+                if let Some(id) = operand.get_tensor_id() {
+                    let operand_tensor = TENSOR_REGISTRY.with_borrow(|r| r[id.0]);
+                    visit(operand_tensor, visited, order);
                 }
             }
-        });
+            order.push(tensor);
+        }
+        let mut order = Vec::new();
+        visit(*self, &mut HashSet::new(), &mut order);
+        order.reverse();
+        order
+    }
+}
+// a Tensor that frees its device buffers once the last clone of it is dropped. Tensor itself is
+// Copy and never frees anything. copies taken out of it and reshape views of it share the
+// buffers, they must not be read after the drop
+#[derive(Clone)]
+pub struct OwnedTensor<'a> {
+    tensor: Tensor,
+    backend: &'a dyn Backend,
+    owners: Rc<()>,
+}
+impl<'a> OwnedTensor<'a> {
+    pub fn new(tensor: Tensor, backend: &'a dyn Backend) -> Self {
+        OwnedTensor {
+            tensor,
+            backend,
+            owners: Rc::new(()),
+        }
+    }
+}
+impl Deref for OwnedTensor<'_> {
+    type Target = Tensor;
+    fn deref(&self) -> &Tensor {
+        &self.tensor
+    }
+}
+impl DerefMut for OwnedTensor<'_> {
+    fn deref_mut(&mut self) -> &mut Tensor {
+        &mut self.tensor
+    }
+}
+impl Drop for OwnedTensor<'_> {
+    fn drop(&mut self) {
+        if Rc::strong_count(&self.owners) > 1 {
+            return;
+        }
+        self.tensor.buffer.free_device_buffer(self.backend);
+        let gradient = TENSOR_REGISTRY.with_borrow(|r| r[self.tensor.id.0].gradient);
+        if let Some(gradient) = gradient {
+            gradient.free_device_buffer(self.backend);
+        }
     }
 }
 impl Debug for Tensor {
@@ -306,37 +1713,60 @@ impl Debug for Tensor {
 impl Add for Tensor {
     type Output = Self;
     fn add(self, other: Self) -> Self::Output {
-        Tensor::from_operation(LazyOp::Add(self.buffer, other.buffer))
+        self.try_add(&other).unwrap_or_else(|e| panic!("{}", e))
     }
 }
 impl Add for &Tensor {
     type Output = Tensor;
     fn add(self, other: Self) -> Self::Output {
-        Tensor::from_operation(LazyOp::Add(self.buffer, other.buffer))
+        self.try_add(other).unwrap_or_else(|e| panic!("{}", e))
     }
 }
 impl Sub for Tensor {
     type Output = Self;
     fn sub(self, other: Self) -> Self::Output {
-        Tensor::from_operation(LazyOp::Subtract(self.buffer, other.buffer))
+        self.try_sub(&other).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+impl Neg for Tensor {
+    type Output = Self;
+    fn neg(self) -> Self::Output {
+        Tensor::from_operation(LazyOp::Unary(self.buffer, UnaryOp::Neg))
     }
 }
 
 impl Mul for Tensor {
     type Output = Self;
     fn mul(self, other: Self) -> Self::Output {
-        Tensor::from_operation(LazyOp::Multiply(self.buffer, other.buffer))
+        self.try_mul(&other).unwrap_or_else(|e| panic!("{}", e))
     }
 }
 impl Mul for &Tensor {
     type Output = Tensor;
     fn mul(self, other: Self) -> Self::Output {
-        Tensor::from_operation(LazyOp::Multiply(self.buffer, other.buffer))
+        self.try_mul(other).unwrap_or_else(|e| panic!("{}", e))
     }
 }
 impl Div for Tensor {
     type Output = Self;
     fn div(self, other: Self) -> Self::Output {
-        Tensor::from_operation(LazyOp::Divide(self.buffer, other.buffer))
+        self.try_div(&other).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+// elementwise over all tensors, which need the same size. there's no shape to build a zero or one
+// tensor from, so an empty iterator panics
+impl Sum for Tensor {
+    fn sum<I: Iterator<Item = Tensor>>(iter: I) -> Self {
+        iter.reduce(|acc, t| acc + t)
+            .unwrap_or_else(|| panic!("Cannot sum an empty iterator of tensors"))
+    }
+}
+
+impl Product for Tensor {
+    fn product<I: Iterator<Item = Tensor>>(iter: I) -> Self {
+        iter.reduce(|acc, t| acc * t)
+            .unwrap_or_else(|| panic!("Cannot multiply an empty iterator of tensors"))
     }
 }
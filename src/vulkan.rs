@@ -2,12 +2,60 @@ use ash::{
     Entry,
     vk::{self},
 };
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::ffi::CString;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryPreference {
+    // fastest for compute, uploads and downloads go through a staging buffer
+    DeviceLocal,
+    // mappable from the host, skips the staging copy but compute is slower on discrete GPUs
+    HostVisible,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceType {
+    Discrete,
+    Integrated,
+    Virtual,
+    Cpu,
+    Other,
+}
+
+// what the device reports about itself, for bug reports and picking dispatch parameters
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceInfo {
+    pub name: String,
+    pub device_type: DeviceType,
+    // encoded the vendor's way, not necessarily as vk::make_api_version
+    pub driver_version: u32,
+    // bytes over all device-local heaps
+    pub device_local_memory: u64,
+    pub max_compute_work_group_count: [u32; 3],
+    pub max_compute_work_group_size: [u32; 3],
+    pub max_compute_work_group_invocations: u32,
+    // nanoseconds per timestamp tick
+    pub timestamp_period: f32,
+}
+
 pub struct Buffer {
     pub buffer: vk::Buffer,
     pub memory: vk::DeviceMemory,
+    // logical size in bytes as requested by the caller
     pub size: u64,
+    // actual size of the memory allocation, the driver may round this up past `size`
+    pub allocation_size: u64,
+}
+
+impl Buffer {
+    // number of T elements that fit in the logical size, reads must never go past this
+    pub fn len<T>(&self) -> usize {
+        self.size as usize / std::mem::size_of::<T>()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
 }
 
 pub struct VulkanBackend {
@@ -24,10 +72,33 @@ pub struct VulkanBackend {
     pub descriptor_pool: vk::DescriptorPool,
     pub descriptor_set: vk::DescriptorSet,
     pub memory_properties: vk::PhysicalDeviceMemoryProperties,
+    pub memory_preference: MemoryPreference,
+    // two timestamps bracketing the most recent dispatch, only written if the queue supports it
+    pub timestamp_query_pool: vk::QueryPool,
+    pub timestamps_supported: bool,
+    pub timestamp_period: f32,
+    // device memory allocations made through create_buffer, staging buffers included
+    pub allocation_count: Cell<usize>,
+    // command buffers are reset and reused instead of allocated per op. a submitted one is kept
+    // with its fence until wait_for_fence, then it goes back to the free list
+    pub free_command_buffers: RefCell<Vec<vk::CommandBuffer>>,
+    pub pending_command_buffers: RefCell<HashMap<vk::Fence, vk::CommandBuffer>>,
+    pub command_buffer_count: Cell<usize>,
+    // workgroup width of the flat compute shaders, also what dispatches are divided by
+    pub local_size: Cell<u32>,
+    // compute limits generated shaders have to stay within
+    pub max_compute_work_group_invocations: u32,
+    pub max_compute_work_group_size: [u32; 3],
+    pub max_compute_shared_memory_size: u32,
+    pub device_info: DeviceInfo,
 }
 
 impl VulkanBackend {
     pub fn new(app_name: &str) -> Self {
+        Self::with_memory_preference(app_name, MemoryPreference::DeviceLocal)
+    }
+
+    pub fn with_memory_preference(app_name: &str, memory_preference: MemoryPreference) -> Self {
         unsafe {
             let entry = Entry::load().expect("Failed to load Vulkan");
 
@@ -63,6 +134,7 @@ impl VulkanBackend {
             // Find a physical device with compute support
             let mut selected_device = None;
             let mut selected_queue_family = 0;
+            let mut timestamp_valid_bits = 0;
 
             for physical_device in physical_devices {
                 let queue_family_properties =
@@ -72,6 +144,7 @@ impl VulkanBackend {
                     if properties.queue_flags.contains(vk::QueueFlags::COMPUTE) {
                         selected_device = Some(physical_device);
                         selected_queue_family = index as u32;
+                        timestamp_valid_bits = properties.timestamp_valid_bits;
                         break;
                     }
                 }
@@ -122,6 +195,28 @@ impl VulkanBackend {
 
             // Get memory properties
             let memory_properties = instance.get_physical_device_memory_properties(physical_device);
+            let limits = &device_properties.limits;
+            let device_info = DeviceInfo {
+                name: device_name,
+                device_type: match device_properties.device_type {
+                    vk::PhysicalDeviceType::DISCRETE_GPU => DeviceType::Discrete,
+                    vk::PhysicalDeviceType::INTEGRATED_GPU => DeviceType::Integrated,
+                    vk::PhysicalDeviceType::VIRTUAL_GPU => DeviceType::Virtual,
+                    vk::PhysicalDeviceType::CPU => DeviceType::Cpu,
+                    _ => DeviceType::Other,
+                },
+                driver_version: device_properties.driver_version,
+                device_local_memory: memory_properties.memory_heaps
+                    [..memory_properties.memory_heap_count as usize]
+                    .iter()
+                    .filter(|heap| heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+                    .map(|heap| heap.size)
+                    .sum(),
+                max_compute_work_group_count: limits.max_compute_work_group_count,
+                max_compute_work_group_size: limits.max_compute_work_group_size,
+                max_compute_work_group_invocations: limits.max_compute_work_group_invocations,
+                timestamp_period: limits.timestamp_period,
+            };
 
             // Create descriptor set layout
             let bindings = [
@@ -152,11 +247,11 @@ impl VulkanBackend {
                 .create_descriptor_set_layout(&descriptor_layout_info, None)
                 .expect("Failed to create descriptor set layout");
 
-            // Create push constant range
+            // Create push constant range (size followed by three shader specific params)
             let push_constant_range = vk::PushConstantRange::builder()
                 .stage_flags(vk::ShaderStageFlags::COMPUTE)
                 .offset(0)
-                .size(std::mem::size_of::<u32>() as u32)
+                .size(4 * std::mem::size_of::<u32>() as u32)
                 .build();
 
             // Create pipeline layout
@@ -258,6 +353,15 @@ impl VulkanBackend {
             // Clean up shader module as it's no longer needed
             device.destroy_shader_module(shader_module, None);
 
+            // Create timestamp query pool for timing dispatches
+            let query_pool_info = vk::QueryPoolCreateInfo::builder()
+                .query_type(vk::QueryType::TIMESTAMP)
+                .query_count(2);
+
+            let timestamp_query_pool = device
+                .create_query_pool(&query_pool_info, None)
+                .expect("Failed to create query pool");
+
             VulkanBackend {
                 entry,
                 instance,
@@ -272,6 +376,24 @@ impl VulkanBackend {
                 descriptor_pool,
                 descriptor_set,
                 memory_properties,
+                memory_preference,
+                timestamp_query_pool,
+                timestamps_supported: timestamp_valid_bits > 0
+                    && device_properties.limits.timestamp_period > 0.0,
+                timestamp_period: device_properties.limits.timestamp_period,
+                allocation_count: Cell::new(0),
+                free_command_buffers: RefCell::new(Vec::new()),
+                pending_command_buffers: RefCell::new(HashMap::new()),
+                command_buffer_count: Cell::new(0),
+                local_size: Cell::new(256),
+                max_compute_work_group_invocations: device_properties
+                    .limits
+                    .max_compute_work_group_invocations,
+                max_compute_work_group_size: device_properties.limits.max_compute_work_group_size,
+                max_compute_shared_memory_size: device_properties
+                    .limits
+                    .max_compute_shared_memory_size,
+                device_info,
             }
         }
     }
@@ -332,6 +454,7 @@ impl VulkanBackend {
                 .device
                 .allocate_memory(&alloc_info, None)
                 .expect("Failed to allocate buffer memory");
+            self.allocation_count.set(self.allocation_count.get() + 1);
 
             self.device
                 .bind_buffer_memory(buffer, buffer_memory, 0)
@@ -341,20 +464,31 @@ impl VulkanBackend {
                 buffer,
                 memory: buffer_memory,
                 size,
+                allocation_size: memory_requirements.size,
             }
         }
     }
 
     pub fn create_gpu_buffer(&self, size: u64) -> Buffer {
+        let properties = match self.memory_preference {
+            MemoryPreference::DeviceLocal => vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            MemoryPreference::HostVisible => {
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT
+            }
+        };
         self.create_buffer(
             size,
             vk::BufferUsageFlags::STORAGE_BUFFER
                 | vk::BufferUsageFlags::TRANSFER_DST
                 | vk::BufferUsageFlags::TRANSFER_SRC,
-            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            properties,
         )
     }
 
+    pub fn is_host_visible(&self) -> bool {
+        self.memory_preference == MemoryPreference::HostVisible
+    }
+
     pub fn create_staging_buffer(&self, size: u64) -> Buffer {
         self.create_buffer(
             size,
@@ -386,13 +520,52 @@ impl VulkanBackend {
         }
     }
 
+    // writes the values straight into the mapped memory, so they never exist as a slice on the host
+    pub fn upload_iter_to_buffer(
+        &self,
+        values: &mut dyn Iterator<Item = f32>,
+        len: usize,
+        buffer: &Buffer,
+    ) {
+        let size_in_bytes = (len * std::mem::size_of::<f32>()) as u64;
+        assert!(
+            size_in_bytes <= buffer.size,
+            "Data size exceeds buffer size"
+        );
+
+        unsafe {
+            let mapped_ptr = self
+                .device
+                .map_memory(buffer.memory, 0, buffer.size, vk::MemoryMapFlags::empty())
+                .expect("Failed to map memory");
+
+            let mapped = std::slice::from_raw_parts_mut(mapped_ptr as *mut f32, len);
+            for (dst, value) in mapped.iter_mut().zip(values) {
+                *dst = value;
+            }
+
+            self.device.unmap_memory(buffer.memory);
+        }
+    }
+
     pub fn copy_buffer(&self, src_buffer: &Buffer, dst_buffer: &Buffer, size: u64) -> vk::Fence {
+        self.copy_buffer_region(src_buffer, dst_buffer, 0, 0, size)
+    }
+
+    pub fn copy_buffer_region(
+        &self,
+        src_buffer: &Buffer,
+        dst_buffer: &Buffer,
+        src_offset: u64,
+        dst_offset: u64,
+        size: u64,
+    ) -> vk::Fence {
         unsafe {
             let command_buffer = self.begin_single_time_command();
 
             let copy_region = vk::BufferCopy::builder()
-                .src_offset(0)
-                .dst_offset(0)
+                .src_offset(src_offset)
+                .dst_offset(dst_offset)
                 .size(size)
                 .build();
 
@@ -407,17 +580,40 @@ impl VulkanBackend {
         }
     }
 
-    pub fn begin_single_time_command(&self) -> vk::CommandBuffer {
+    // repeats a 32 bit word over the first size bytes, the bits of an f32 fill it with that value
+    pub fn fill_buffer(&self, buffer: &Buffer, data: u32, size: u64) -> vk::Fence {
         unsafe {
-            let command_buffer_allocate_info = vk::CommandBufferAllocateInfo::builder()
-                .command_pool(self.command_pool)
-                .level(vk::CommandBufferLevel::PRIMARY)
-                .command_buffer_count(1);
+            let command_buffer = self.begin_single_time_command();
 
-            let command_buffer = self
-                .device
-                .allocate_command_buffers(&command_buffer_allocate_info)
-                .expect("Failed to allocate command buffer")[0];
+            self.device
+                .cmd_fill_buffer(command_buffer, buffer.buffer, 0, size, data);
+
+            self.end_single_time_command(command_buffer)
+        }
+    }
+
+    pub fn begin_single_time_command(&self) -> vk::CommandBuffer {
+        unsafe {
+            let free = self.free_command_buffers.borrow_mut().pop();
+            let command_buffer = match free {
+                Some(command_buffer) => {
+                    self.device
+                        .reset_command_buffer(command_buffer, vk::CommandBufferResetFlags::empty())
+                        .expect("Failed to reset command buffer");
+                    command_buffer
+                }
+                None => {
+                    let command_buffer_allocate_info = vk::CommandBufferAllocateInfo::builder()
+                        .command_pool(self.command_pool)
+                        .level(vk::CommandBufferLevel::PRIMARY)
+                        .command_buffer_count(1);
+                    self.command_buffer_count
+                        .set(self.command_buffer_count.get() + 1);
+                    self.device
+                        .allocate_command_buffers(&command_buffer_allocate_info)
+                        .expect("Failed to allocate command buffer")[0]
+                }
+            };
 
             let command_buffer_begin_info = vk::CommandBufferBeginInfo::builder()
                 .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
@@ -458,6 +654,9 @@ impl VulkanBackend {
                 Ok(_) => {}
                 Err(e) => panic!("Failed to submit queue: {:?}", e),
             }
+            self.pending_command_buffers
+                .borrow_mut()
+                .insert(fence, command_buffer);
             fence
         }
     }
@@ -469,13 +668,19 @@ impl VulkanBackend {
                 .expect("Failed to wait for fence");
             self.device.destroy_fence(fence, None);
         }
+        if let Some(command_buffer) = self.pending_command_buffers.borrow_mut().remove(&fence) {
+            self.free_command_buffers.borrow_mut().push(command_buffer);
+        }
     }
 
     pub fn read_buffer<T: Copy>(&self, buffer: &Buffer, count: usize) -> Vec<T> {
+        // bound by the logical size, not the (possibly padded) allocation
         let size_in_bytes = (count * std::mem::size_of::<T>()) as u64;
         assert!(
-            size_in_bytes <= buffer.size,
-            "Read size exceeds buffer size"
+            count <= buffer.len::<T>(),
+            "Read of {} elements exceeds logical buffer length {}",
+            count,
+            buffer.len::<T>()
         );
 
         let mut result = Vec::with_capacity(count);
@@ -483,7 +688,7 @@ impl VulkanBackend {
         unsafe {
             let mapped_ptr = self
                 .device
-                .map_memory(buffer.memory, 0, buffer.size, vk::MemoryMapFlags::empty())
+                .map_memory(buffer.memory, 0, size_in_bytes, vk::MemoryMapFlags::empty())
                 .expect("Failed to map memory") as *const T;
 
             result.extend_from_slice(std::slice::from_raw_parts(mapped_ptr, count));
@@ -541,7 +746,26 @@ impl VulkanBackend {
         buffer_b: &Buffer,
         result_buffer: &Buffer,
         tensor_size: u32,
+        params: [u32; 3],
         pipeline: vk::Pipeline,
+    ) -> vk::Fence {
+        let workgroup_size = self.local_size.get();
+        let dispatch_x = tensor_size.div_ceil(workgroup_size);
+        self.execute_compute_with_groups(
+            [buffer_a, buffer_b, result_buffer],
+            [tensor_size, params[0], params[1], params[2]],
+            pipeline,
+            [dispatch_x, 1, 1],
+        )
+    }
+    // like execute_compute_with_pipeline, for shaders that don't use a flat local_size wide
+    // workgroup
+    pub fn execute_compute_with_groups(
+        &self,
+        [buffer_a, buffer_b, result_buffer]: [&Buffer; 3],
+        push_constants: [u32; 4],
+        pipeline: vk::Pipeline,
+        groups: [u32; 3],
     ) -> vk::Fence {
         unsafe {
             // Update descriptor sets for the buffers
@@ -605,19 +829,78 @@ impl VulkanBackend {
                 self.pipeline_layout,
                 vk::ShaderStageFlags::COMPUTE,
                 0,
-                bytemuck::cast_slice(&[tensor_size]),
+                bytemuck::cast_slice(&push_constants),
             );
 
-            let workgroup_size = 256;
-            let dispatch_x = (tensor_size + workgroup_size - 1) / workgroup_size;
-            self.device.cmd_dispatch(command_buffer, dispatch_x, 1, 1);
+            if self.timestamps_supported {
+                self.device
+                    .cmd_reset_query_pool(command_buffer, self.timestamp_query_pool, 0, 2);
+                self.device.cmd_write_timestamp(
+                    command_buffer,
+                    vk::PipelineStageFlags::TOP_OF_PIPE,
+                    self.timestamp_query_pool,
+                    0,
+                );
+            }
+            self.device
+                .cmd_dispatch(command_buffer, groups[0], groups[1], groups[2]);
+            if self.timestamps_supported {
+                self.device.cmd_write_timestamp(
+                    command_buffer,
+                    vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                    self.timestamp_query_pool,
+                    1,
+                );
+            }
 
             self.end_single_time_command(command_buffer)
         }
     }
 
+    // GPU time of the last dispatch in nanoseconds, excluding submission and transfers.
+    // only valid once that dispatch's fence has been waited on
+    pub fn last_dispatch_nanos(&self) -> Option<f64> {
+        if !self.timestamps_supported {
+            return None;
+        }
+        let mut timestamps = [0u64; 2];
+        unsafe {
+            self.device
+                .get_query_pool_results(
+                    self.timestamp_query_pool,
+                    0,
+                    2,
+                    &mut timestamps,
+                    vk::QueryResultFlags::TYPE_64,
+                )
+                .ok()?;
+        }
+        Some(timestamps[1].wrapping_sub(timestamps[0]) as f64 * self.timestamp_period as f64)
+    }
+
     pub fn cleanup(&self) {
         unsafe {
+            let command_buffers: Vec<vk::CommandBuffer> = self
+                .free_command_buffers
+                .borrow_mut()
+                .drain(..)
+                .chain(
+                    self.pending_command_buffers
+                        .borrow_mut()
+                        .drain()
+                        .map(|(_, c)| c),
+                )
+                .collect();
+            // pending ones may still run if their fence was never waited on
+            if !command_buffers.is_empty() {
+                self.device
+                    .device_wait_idle()
+                    .expect("Failed to wait for device idle");
+                self.device
+                    .free_command_buffers(self.command_pool, &command_buffers);
+            }
+            self.device
+                .destroy_query_pool(self.timestamp_query_pool, None);
             self.device.destroy_pipeline(self.compute_pipeline, None);
             self.device
                 .destroy_pipeline_layout(self.pipeline_layout, None);
@@ -0,0 +1,50 @@
+use flamer::backends::CPUBackend;
+use flamer::stats;
+use flamer::tensor::Tensor;
+
+#[test]
+fn a_hundred_accumulations_keep_the_graph_and_registries_the_same_size() {
+    let backend = CPUBackend::new();
+    let mut total = Tensor::without_grad(vec![0.0; 3]);
+    let increment = Tensor::new(vec![1.0, 2.0, 3.0]) * Tensor::new(vec![2.0, 2.0, 2.0]);
+    total.accumulate_into(&increment, &backend);
+    let after_first = stats();
+    for _ in 1..100 {
+        total.accumulate_into(&increment, &backend);
+        assert_eq!(stats(), after_first);
+    }
+    assert_eq!(total.buffer.to_ir(&backend).nodes.len(), 1);
+    assert_eq!(total.buffer.get_data(&backend), vec![200.0, 400.0, 600.0]);
+}
+
+#[test]
+fn graphs_reading_the_total_see_the_new_value() {
+    let backend = CPUBackend::new();
+    let mut total = Tensor::new(vec![1.0, 2.0]);
+    let doubled = total * Tensor::new(vec![2.0, 2.0]);
+    assert_eq!(
+        doubled.iter_realized(&backend).collect::<Vec<_>>(),
+        vec![2.0, 4.0]
+    );
+    total.accumulate_into(&Tensor::new(vec![10.0, 10.0]), &backend);
+    assert_eq!(
+        doubled.iter_realized(&backend).collect::<Vec<_>>(),
+        vec![22.0, 24.0]
+    );
+}
+
+#[test]
+#[should_panic(expected = "accumulate_into needs a data tensor that owns its buffer")]
+fn a_computed_tensor_is_rejected() {
+    let backend = CPUBackend::new();
+    let mut sum = Tensor::new(vec![1.0]) + Tensor::new(vec![2.0]);
+    sum.accumulate_into(&Tensor::new(vec![1.0]), &backend);
+}
+
+#[test]
+#[should_panic(expected = "accumulate_into needs a data tensor that owns its buffer")]
+fn a_view_is_rejected() {
+    let backend = CPUBackend::new();
+    let mut view = Tensor::new(vec![1.0, 2.0]).reshape(&[1, 2]);
+    view.accumulate_into(&Tensor::new(vec![1.0, 1.0]), &backend);
+}
@@ -0,0 +1,29 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+#[test]
+fn affine_matches_a_multiply_and_an_add() {
+    let backend = CPUBackend::new();
+    let data = vec![1.5, -2.0, 0.0, 3.25];
+    let x = Tensor::new(data.clone());
+    let fused = x.affine(-0.5, 2.0);
+    let unfused = x * x.full_like(-0.5) + x.full_like(2.0);
+    assert_eq!(
+        fused.iter_realized(&backend).collect::<Vec<_>>(),
+        unfused.iter_realized(&backend).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        fused.iter_realized(&backend).collect::<Vec<_>>(),
+        data.iter().map(|v| v * -0.5 + 2.0).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn affine_gradient_is_the_scale() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![1.5, -2.0, 0.0]);
+    let weights = Tensor::without_grad(vec![1.0, 2.0, 3.0]);
+    let mut loss = (x.affine(-0.5, 2.0) * weights).sum();
+    let grads = loss.backward_grads(&[x], &backend);
+    assert_eq!(grads[0], vec![-0.5, -1.0, -1.5]);
+}
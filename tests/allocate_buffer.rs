@@ -0,0 +1,22 @@
+use flamer::backends::CPUBackend;
+use flamer::lazybuffer::{Backend, get_next_buffer_id};
+
+#[test]
+fn allocating_an_existing_id_again_returns_its_buffer() {
+    let backend = CPUBackend::new();
+    let id = get_next_buffer_id();
+    let handle = backend.allocate_buffer(id, 3);
+    backend.to_device(&[1.0, 2.0, 3.0], &handle);
+    let again = backend.allocate_buffer(id, 3);
+    assert_eq!((again.id, again.size), (id, 3));
+    assert_eq!(backend.read_buffer(&again), vec![1.0, 2.0, 3.0]);
+}
+
+#[test]
+#[should_panic(expected = "is already allocated with 3 elements, requested 5")]
+fn a_conflicting_size_for_an_existing_id_is_caught() {
+    let backend = CPUBackend::new();
+    let id = get_next_buffer_id();
+    backend.allocate_buffer(id, 3);
+    backend.allocate_buffer(id, 5);
+}
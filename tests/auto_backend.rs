@@ -0,0 +1,43 @@
+use flamer::backends::auto_backend::CostModel;
+use flamer::backends::{AutoBackend, CPUBackend};
+use flamer::lazybuffer::Backend;
+use flamer::tensor::Tensor;
+
+// with these costs a 32x32x32 matmul (32768 element ops) is cheaper on the second backend,
+// 10000 + 32768 / 100 + 2048 moved, while the small elementwise ops and the sum stay on the CPU
+const COSTS: CostModel = CostModel {
+    dispatch_overhead: 10_000.0,
+    gpu_speedup: 100.0,
+    transfer_cost: 1.0,
+};
+
+// a matmul that goes to the GPU side, elementwise ops and a reduction that stay on the CPU
+fn outputs(backend: &dyn Backend) -> Vec<Vec<f32>> {
+    let a = Tensor::new((0..1024).map(|i| (i % 13) as f32 - 6.0).collect()).reshape(&[32, 32]);
+    let b = Tensor::new((0..1024).map(|i| (i % 7) as f32 * 0.5).collect()).reshape(&[32, 32]);
+    let product = a.matmul(&b);
+    let small = Tensor::new(vec![1.0, 2.0, 3.0]) * Tensor::new(vec![4.0, 5.0, 6.0]);
+    let total = (product * product).sum();
+    [product, small, total]
+        .iter()
+        .map(|tensor| tensor.iter_realized(backend).collect())
+        .collect()
+}
+
+// one backend per thread, the registries are per thread
+fn outputs_on(backend: impl FnOnce() -> Box<dyn Backend> + Send + 'static) -> Vec<Vec<f32>> {
+    std::thread::spawn(move || outputs(backend().as_ref()))
+        .join()
+        .unwrap()
+}
+
+#[test]
+fn auto_backend_results_match_a_single_backend() {
+    let alone = outputs_on(|| Box::new(CPUBackend::new()));
+    // a second CPU backend stands in for the GPU, the ops and transfers are the same
+    let auto = outputs_on(|| Box::new(AutoBackend::new(CPUBackend::new()).with_cost_model(COSTS)));
+    assert_eq!(auto, alone);
+    // and with the defaults, where everything this small stays on the CPU
+    let default = outputs_on(|| Box::new(AutoBackend::new(CPUBackend::new())));
+    assert_eq!(default, alone);
+}
@@ -0,0 +1,38 @@
+mod common;
+
+use common::{assert_close, numeric_grad};
+use flamer::backends::CPUBackend;
+use flamer::inspect::tensor_info;
+use flamer::tensor::Tensor;
+
+fn loss(a: &Tensor, b: &Tensor) -> Tensor {
+    (*a * *b + (*a / *b).sqrt()).sum()
+}
+
+#[test]
+fn backward_grads_match_finite_differences() {
+    let backend = CPUBackend::new();
+    let (a_data, b_data) = (vec![0.5, 1.5, 2.0], vec![1.0, 0.25, 3.0]);
+    let value = |a: &[f32], b: &[f32]| {
+        loss(&Tensor::new(a.to_vec()), &Tensor::new(b.to_vec())).item(&backend)
+    };
+
+    let (a, b) = (Tensor::new(a_data.clone()), Tensor::new(b_data.clone()));
+    let grads = loss(&a, &b).backward_grads(&[a, b], &backend);
+    let expected_a = numeric_grad(|x| value(x, &b_data), &a_data, 1e-2);
+    let expected_b = numeric_grad(|x| value(&a_data, x), &b_data, 1e-2);
+    assert_close(&grads[0], &expected_a, 1e-2);
+    assert_close(&grads[1], &expected_b, 1e-2);
+}
+
+#[test]
+fn backward_grads_stores_nothing_and_zeroes_unused_inputs() {
+    let backend = CPUBackend::new();
+    let a = Tensor::new(vec![1.0, 2.0]);
+    let unused = Tensor::new(vec![3.0, 4.0]);
+    let grads = (a * a).sum().backward_grads(&[a, unused], &backend);
+
+    assert_eq!(grads, vec![vec![2.0, 4.0], vec![0.0, 0.0]]);
+    assert!(!tensor_info(a.id).has_grad);
+    assert!(!tensor_info(unused.id).has_grad);
+}
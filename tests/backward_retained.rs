@@ -0,0 +1,28 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+#[test]
+fn two_retained_backwards_give_the_same_gradients() {
+    let backend = CPUBackend::new();
+    let a = Tensor::new(vec![0.5, -1.0, 2.0]);
+    let b = Tensor::new(vec![1.5, 0.25, -0.75]);
+    let shared = a * b;
+    let mut loss = (shared * shared + shared.sin() + a).sum();
+    loss.realize(&backend);
+
+    loss.backward_retained(&backend);
+    let first = (a.grad(&backend).unwrap(), b.grad(&backend).unwrap());
+    loss.backward_retained(&backend);
+    let second = (a.grad(&backend).unwrap(), b.grad(&backend).unwrap());
+    assert_eq!(first, second);
+
+    // and they are the gradients, not just the same wrong ones twice
+    let expected = loss.backward_grads(&[a, b], &backend);
+    assert_eq!(vec![first.0, first.1], expected);
+}
+
+#[test]
+fn a_tensor_without_a_gradient_has_none() {
+    let backend = CPUBackend::new();
+    assert_eq!(Tensor::new(vec![1.0]).grad(&backend), None);
+}
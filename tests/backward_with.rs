@@ -0,0 +1,39 @@
+use flamer::backends::CPUBackend;
+use flamer::optim::SGD;
+use flamer::tensor::Tensor;
+
+// a step with lr 1 from zeros leaves the parameter at minus its stored gradient
+fn stored_gradient(parameter: &Tensor, backend: &CPUBackend) -> Vec<f32> {
+    SGD::new(1.0).step(backend);
+    parameter.iter_realized(backend).map(|v| -v).collect()
+}
+
+#[test]
+fn backward_with_stores_the_vector_jacobian_product() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![0.0; 3]);
+    let w = Tensor::without_grad(vec![2.0, -1.0, 0.5]);
+    // d(x * w)/dx is diag(w), so the product with the seed is seed * w
+    let mut y = x * w;
+    y.realize(&backend);
+    y.backward_with(&backend, &Tensor::without_grad(vec![1.0, 3.0, -4.0]));
+    assert_eq!(stored_gradient(&x, &backend), vec![2.0, -3.0, -2.0]);
+}
+
+#[test]
+fn a_seed_of_ones_gives_the_plain_gradient() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![0.0; 2]);
+    let mut y = x.affine(3.0, 1.0);
+    y.realize(&backend);
+    y.backward_with(&backend, &Tensor::ones(2));
+    assert_eq!(stored_gradient(&x, &backend), vec![3.0, 3.0]);
+}
+
+#[test]
+#[should_panic(expected = "backward seed has 2 elements, the root has 3")]
+fn the_seed_has_the_size_of_the_root() {
+    let backend = CPUBackend::new();
+    let mut y = Tensor::new(vec![1.0, 2.0, 3.0]).affine(2.0, 0.0);
+    y.backward_with(&backend, &Tensor::ones(2));
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use common::{assert_close, numeric_grad};
+use flamer::backends::CPUBackend;
+use flamer::inspect::tensor_info;
+use flamer::tensor::Tensor;
+
+const X: [f32; 6] = [1.0, -2.0, 0.5, 3.0, 4.0, -1.0];
+const MEAN: [f32; 3] = [0.5, 1.0, -0.5];
+const VAR: [f32; 3] = [4.0, 0.25, 1.0];
+const EPS: f32 = 1e-3;
+
+fn input() -> Tensor {
+    let x = Tensor::without_grad(X.to_vec());
+    x.reshape(&[2, 3])
+}
+
+fn normalized(gamma: &[f32], beta: &[f32]) -> Tensor {
+    input().batch_norm(
+        &Tensor::new(gamma.to_vec()),
+        &Tensor::new(beta.to_vec()),
+        &Tensor::without_grad(MEAN.to_vec()),
+        &Tensor::without_grad(VAR.to_vec()),
+        EPS,
+    )
+}
+
+#[test]
+fn batch_norm_matches_reference() {
+    let backend = CPUBackend::new();
+    let (gamma, beta) = ([2.0, 0.5, -1.0], [0.1, 0.2, 0.3]);
+    let out = normalized(&gamma, &beta);
+    let expected: Vec<f32> = X
+        .iter()
+        .enumerate()
+        .map(|(i, &x)| {
+            let f = i % 3;
+            gamma[f] * (x - MEAN[f]) / (VAR[f] + EPS).sqrt() + beta[f]
+        })
+        .collect();
+
+    assert_eq!(out.shape(), vec![2, 3]);
+    assert_close(
+        &out.iter_realized(&backend).collect::<Vec<_>>(),
+        &expected,
+        1e-5,
+    );
+}
+
+#[test]
+fn batch_norm_gradients_reach_gamma_and_beta_only() {
+    let backend = CPUBackend::new();
+    let (gamma_data, beta_data) = (vec![2.0, 0.5, -1.0], vec![0.1, 0.2, 0.3]);
+    // weighted so every output element contributes differently
+    let weights = Tensor::without_grad(vec![1.0, -2.0, 0.5, 3.0, 1.5, -1.0]);
+    let loss_of = |gamma: &[f32], beta: &[f32]| (normalized(gamma, beta) * weights).sum();
+
+    let gamma = Tensor::new(gamma_data.clone());
+    let beta = Tensor::new(beta_data.clone());
+    let running_mean = Tensor::without_grad(MEAN.to_vec());
+    let running_var = Tensor::without_grad(VAR.to_vec());
+    let out = input().batch_norm(&gamma, &beta, &running_mean, &running_var, EPS);
+    let mut loss = (out * weights).sum();
+    let grads = loss.backward_grads(&[gamma, beta], &backend);
+
+    let value = |gamma: &[f32], beta: &[f32]| loss_of(gamma, beta).item(&backend);
+    let expected_gamma = numeric_grad(|g| value(g, &beta_data), &gamma_data, 1e-2);
+    let expected_beta = numeric_grad(|b| value(&gamma_data, b), &beta_data, 1e-2);
+    assert_close(&grads[0], &expected_gamma, 1e-2);
+    assert_close(&grads[1], &expected_beta, 1e-2);
+
+    // a training step moves gamma and beta but leaves the running statistics alone
+    loss.apply_backward(&backend, 0.1);
+    assert!(tensor_info(gamma.id).has_grad && tensor_info(beta.id).has_grad);
+    assert!(!tensor_info(running_mean.id).has_grad);
+    assert!(!tensor_info(running_var.id).has_grad);
+}
@@ -0,0 +1,37 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+fn values(tensor: &Tensor, backend: &CPUBackend) -> Vec<f32> {
+    tensor.iter_realized(backend).collect()
+}
+
+#[test]
+fn broadcast_to_repeats_a_row() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![1.0, 2.0, 3.0]);
+    let rows = x.broadcast_to(vec![4, 3]);
+    assert_eq!(rows.shape(), vec![4, 3]);
+    assert_eq!(values(&rows, &backend), [1.0, 2.0, 3.0].repeat(4));
+
+    // the gradient of each element adds up over the rows it was copied into
+    let weights = Tensor::without_grad((1..=12).map(|i| i as f32).collect()).reshape(&[4, 3]);
+    let grads = (rows * weights).sum().backward_grads(&[x], &backend);
+    assert_eq!(grads[0], vec![22.0, 26.0, 30.0]);
+}
+
+#[test]
+fn broadcast_to_repeats_a_column() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![1.0, 2.0]).reshape(&[2, 1]);
+    let columns = x.broadcast_to(vec![2, 3]);
+    assert_eq!(
+        values(&columns, &backend),
+        vec![1.0, 1.0, 1.0, 2.0, 2.0, 2.0]
+    );
+}
+
+#[test]
+#[should_panic(expected = "Cannot broadcast tensor of shape [3] to [4, 2]")]
+fn broadcast_to_rejects_mismatched_axes() {
+    Tensor::new(vec![1.0, 2.0, 3.0]).broadcast_to(vec![4, 2]);
+}
@@ -0,0 +1,35 @@
+use flamer::backends::CPUBackend;
+use flamer::lazybuffer::set_buffer_reuse;
+use flamer::tensor::Tensor;
+
+#[test]
+fn retained_intermediate_keeps_its_values_with_reuse() {
+    let backend = CPUBackend::new();
+    set_buffer_reuse(true);
+    let x = Tensor::new(vec![1.0, 2.0, 3.0]);
+    let mut squared = x * x;
+    squared.retain();
+    // every intermediate has the same size, so without retain the squares would be overwritten
+    let shifted = squared + x;
+    let mut root = (shifted * shifted).sum();
+    root.realize(&backend);
+
+    assert_eq!(root.item(&backend), 4.0 + 36.0 + 144.0);
+    assert_eq!(squared.buffer.get_data(&backend), vec![1.0, 4.0, 9.0]);
+}
+
+#[test]
+fn gradients_match_with_and_without_reuse() {
+    let backend = CPUBackend::new();
+    let grads = |reuse: bool| {
+        set_buffer_reuse(reuse);
+        let x = Tensor::new(vec![0.5, -1.0, 2.0]);
+        let mut loss = ((x * x + x) * x).sum();
+        loss.realize(&backend);
+        loss.backward_grads(&[x], &backend).remove(0)
+    };
+    let plain = grads(false);
+    // d/dx (x^3 + x^2) = 3x^2 + 2x
+    assert_eq!(plain, vec![1.75, 1.0, 16.0]);
+    assert_eq!(grads(true), plain);
+}
@@ -0,0 +1,40 @@
+use flamer::backends::CPUBackend;
+use flamer::error::FlameError;
+use flamer::lazybuffer::CancellationToken;
+use flamer::tensor::Tensor;
+
+#[test]
+fn a_realize_cancelled_mid_graph_can_be_run_again() {
+    let backend = CPUBackend::new();
+    let token = CancellationToken::new();
+    // the generator runs in the middle of the schedule and cancels everything after it
+    let canceller = token.clone();
+    let generated = Tensor::from_fn(3, move |i| {
+        canceller.cancel();
+        i as f32
+    });
+    let x = Tensor::new(vec![1.0, 2.0, 3.0]);
+    let product = x * x;
+    let out = (product + generated) * Tensor::new(vec![2.0, 2.0, 2.0]);
+
+    assert_eq!(
+        out.buffer.realize_cancellable(&backend, &token),
+        Err(FlameError::Cancelled)
+    );
+    assert!(out.buffer.get_device_handle().is_none());
+    assert!(product.buffer.get_device_handle().is_none());
+
+    // nothing of the cancelled run is left behind, a plain realize computes the whole graph
+    assert_eq!(
+        out.iter_realized(&backend).collect::<Vec<_>>(),
+        vec![2.0, 10.0, 22.0]
+    );
+    // a fresh token lets the cancellable realize finish
+    let y = x + x;
+    assert_eq!(
+        y.buffer
+            .realize_cancellable(&backend, &CancellationToken::new()),
+        Ok(())
+    );
+    assert_eq!(y.buffer.get_data(&backend), vec![2.0, 4.0, 6.0]);
+}
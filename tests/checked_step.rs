@@ -0,0 +1,49 @@
+use flamer::backends::CPUBackend;
+use flamer::error::FlameError;
+use flamer::optim::SGD;
+use flamer::tensor::Tensor;
+
+#[test]
+fn a_finite_step_is_applied() {
+    let backend = CPUBackend::new();
+    let w = Tensor::new(vec![1.0, 2.0]);
+    let mut loss = (w * Tensor::without_grad(vec![3.0, -1.0])).sum();
+    assert!(loss.apply_backward_checked(&backend, 0.5).is_ok());
+    assert_eq!(w.buffer.get_data(&backend), vec![-0.5, 2.5]);
+}
+
+#[test]
+fn a_non_finite_step_is_refused_and_nothing_moves() {
+    let backend = CPUBackend::new();
+    let (a, b) = (Tensor::new(vec![1.0, 2.0]), Tensor::new(vec![1.0]));
+    // b's gradient times the learning rate overflows f32
+    let huge = Tensor::without_grad(vec![f32::MAX]);
+    let mut loss = (a * Tensor::without_grad(vec![1.0, 1.0])).sum() + (b * huge).sum();
+    match loss.apply_backward_checked(&backend, 10.0) {
+        Err(FlameError::NonFiniteUpdate(id)) => assert_eq!(id, b.id),
+        other => panic!("expected NonFiniteUpdate, got {:?}", other),
+    }
+    assert_eq!(a.buffer.get_data(&backend), vec![1.0, 2.0]);
+    assert_eq!(b.buffer.get_data(&backend), vec![1.0]);
+}
+
+#[test]
+fn sgd_step_checked_uses_the_accumulated_learning_rate() {
+    let backend = CPUBackend::new();
+    let mut w = Tensor::new(vec![1.0]);
+    w.realize(&backend);
+    w.accumulate_grad(&[f32::MAX / 2.0], &backend);
+
+    // lr 4 would overshoot to -inf, the parameter is left alone
+    let sgd = SGD::new(4.0);
+    assert_eq!(
+        sgd.step_checked(&backend),
+        Err(FlameError::NonFiniteUpdate(w.id))
+    );
+    assert_eq!(w.buffer.get_data(&backend), vec![1.0]);
+
+    // over 8 accumulated steps the update is lr / 8 times the gradient, which is finite
+    let sgd = SGD::new(4.0).accumulate_steps(8);
+    assert_eq!(sgd.step_checked(&backend), Ok(()));
+    assert_eq!(w.buffer.get_data(&backend), vec![1.0 - f32::MAX / 4.0]);
+}
@@ -0,0 +1,43 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+// a residual sin chain, realized and differentiated on a thread and backend of its own, cached
+// scratch buffers are per thread. returns the gradient of the input and the most buffers that
+// were alive at once
+fn residual_chain(checkpoint_every: Option<usize>) -> (Vec<f32>, usize) {
+    std::thread::spawn(move || residual_chain_on_this_thread(checkpoint_every))
+        .join()
+        .unwrap()
+}
+
+fn residual_chain_on_this_thread(checkpoint_every: Option<usize>) -> (Vec<f32>, usize) {
+    let backend = CPUBackend::new();
+    let x = Tensor::new((0..8).map(|i| i as f32 * 0.1).collect());
+    let mut h = x;
+    for layer in 1..=12 {
+        h = h + h.sin().affine(0.25, 0.0);
+        if checkpoint_every.is_some_and(|every| layer % every == 0) {
+            h = h.checkpoint();
+        }
+    }
+    let mut loss = h.sum();
+    loss.realize(&backend);
+    let grads = loss.backward_grads(&[x], &backend).remove(0);
+    (grads, backend.peak_buffer_count())
+}
+
+#[test]
+fn checkpointing_keeps_gradients_and_lowers_peak_buffers() {
+    let (plain_grads, plain_peak) = residual_chain(None);
+    let (checkpointed_grads, checkpointed_peak) = residual_chain(Some(4));
+
+    // every layer scales the gradient by 1 + cos(h) / 4, which is at least 0.75
+    assert!(plain_grads.iter().all(|&g| g > 0.0));
+    assert_eq!(checkpointed_grads, plain_grads);
+    assert!(
+        checkpointed_peak < plain_peak,
+        "checkpointed peak {} vs {}",
+        checkpointed_peak,
+        plain_peak
+    );
+}
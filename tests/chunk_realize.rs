@@ -0,0 +1,52 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+const SIZE: usize = 10_007;
+
+fn operands() -> (Vec<f32>, Vec<f32>) {
+    let a = (0..SIZE).map(|i| i as f32 * 0.5).collect();
+    let b = (0..SIZE).map(|i| (i % 17) as f32 + 1.0).collect();
+    (a, b)
+}
+
+#[test]
+fn chunked_results_match_a_single_realize() {
+    let backend = CPUBackend::new();
+    let (a, b) = operands();
+    let (a, b) = (Tensor::new(a), Tensor::new(b));
+    for op in [a + b, a - b, a * b, a / b] {
+        let mut ends = Vec::new();
+        // tiles of 1000 elements and a last short one
+        let chunked = op.chunk_realize_sliced(&backend, 1000, |done| ends.push(done));
+        assert_eq!(ends.len(), 11);
+        assert_eq!(ends.last(), Some(&SIZE));
+        assert_eq!(chunked, op.iter_realized(&backend).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+#[should_panic(expected = "chunk_realize only supports elementwise ops")]
+fn chunk_realize_needs_an_elementwise_op() {
+    Tensor::new(vec![1.0, 2.0])
+        .sum()
+        .chunk_realize(&CPUBackend::new());
+}
+
+#[test]
+fn the_yield_point_runs_after_every_slice() {
+    let backend = CPUBackend::new();
+    let (a, b) = operands();
+    let mut ends = Vec::new();
+    (Tensor::new(a) * Tensor::new(b)).chunk_realize_sliced(&backend, 4000, |done| ends.push(done));
+    assert_eq!(ends, vec![4000, 8000, SIZE]);
+}
+
+#[test]
+fn tensors_built_after_a_chunked_realize_still_work() {
+    let backend = CPUBackend::new();
+    let (a, b) = operands();
+    (Tensor::new(a) + Tensor::new(b)).chunk_realize_sliced(&backend, 1000, |_| {});
+    let mut after = Tensor::new(vec![1.0, 2.0]) * Tensor::new(vec![3.0, 4.0]);
+    after.realize(&backend);
+    assert_eq!(after.buffer.get_data(&backend), vec![3.0, 8.0]);
+}
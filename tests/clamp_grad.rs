@@ -0,0 +1,36 @@
+use flamer::backends::CPUBackend;
+use flamer::optim::{SGD, clip_grad_value};
+use flamer::tensor::Tensor;
+
+#[test]
+fn clamp_grad_limits_each_element() {
+    let backend = CPUBackend::new();
+    let mut x = Tensor::new(vec![0.0; 4]);
+    x.realize(&backend);
+    x.accumulate_grad(&[5.0, -3.0, 0.5, -0.25], &backend);
+    x.clamp_grad(1.0, &backend);
+    // a step with lr 1 from zeros leaves minus the gradient
+    SGD::new(1.0).step(&backend);
+    assert_eq!(
+        x.iter_realized(&backend).collect::<Vec<_>>(),
+        vec![-1.0, 1.0, -0.5, 0.25]
+    );
+}
+
+#[test]
+fn clip_grad_value_skips_tensors_without_a_gradient() {
+    let backend = CPUBackend::new();
+    let mut x = Tensor::new(vec![0.0; 2]);
+    x.realize(&backend);
+    x.accumulate_grad(&[-4.0, 4.0], &backend);
+    let untouched = Tensor::new(vec![1.0; 2]);
+    clip_grad_value(&[x, untouched], 2.0, &backend);
+    assert_eq!(x.grad_norm(&backend), 8.0f32.sqrt());
+    assert_eq!(untouched.grad_norm(&backend), 0.0);
+}
+
+#[test]
+#[should_panic(expected = "Gradient clip value has to be non-negative, got -1")]
+fn clamp_grad_rejects_a_negative_clip() {
+    Tensor::new(vec![0.0]).clamp_grad(-1.0, &CPUBackend::new());
+}
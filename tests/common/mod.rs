@@ -0,0 +1,53 @@
+// helpers shared by the integration tests, every test file only uses some of them
+#![allow(dead_code)]
+
+use flamer::lazybuffer::{Backend, DivByZero};
+use flamer::tensor::Tensor;
+
+// central differences of f around x, one pair of evaluations per element
+pub fn numeric_grad(f: impl Fn(&[f32]) -> f32, x: &[f32], eps: f32) -> Vec<f32> {
+    (0..x.len())
+        .map(|i| {
+            let (mut plus, mut minus) = (x.to_vec(), x.to_vec());
+            plus[i] += eps;
+            minus[i] -= eps;
+            (f(&plus) - f(&minus)) / (2.0 * eps)
+        })
+        .collect()
+}
+
+// elementwise |actual - expected| <= tol, relative to the expected value once it's above 1
+pub fn assert_close(actual: &[f32], expected: &[f32], tol: f32) {
+    assert_eq!(actual.len(), expected.len(), "length mismatch");
+    for (i, (a, e)) in actual.iter().zip(expected).enumerate() {
+        assert!(
+            (a - e).abs() <= tol * e.abs().max(1.0),
+            "element {}: {} vs {} (actual {:?}, expected {:?})",
+            i,
+            a,
+            e,
+            actual,
+            expected
+        );
+    }
+}
+
+// 0 / 0, 1 / 0, -1 / 0 and 6 / 3 under every division policy, on any backend
+pub fn check_div_policies(backend: &dyn Backend) {
+    let quotients = |policy| -> Vec<f32> {
+        backend.set_div_policy(policy);
+        let numerators = Tensor::new(vec![0.0, 1.0, -1.0, 6.0]);
+        let denominators = Tensor::new(vec![0.0, 0.0, 0.0, 3.0]);
+        (numerators / denominators).iter_realized(backend).collect()
+    };
+    let nan = quotients(DivByZero::Nan);
+    assert!(nan[..3].iter().all(|q| q.is_nan()), "{:?}", nan);
+    assert_eq!(nan[3], 2.0);
+
+    let inf = quotients(DivByZero::Inf);
+    assert!(inf[0].is_nan(), "{:?}", inf);
+    assert_eq!(inf[1..], [f32::INFINITY, f32::NEG_INFINITY, 2.0]);
+
+    assert_eq!(quotients(DivByZero::Zero), vec![0.0, 0.0, 0.0, 2.0]);
+    backend.set_div_policy(DivByZero::default());
+}
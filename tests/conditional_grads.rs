@@ -0,0 +1,129 @@
+// the mask based ops route each element's gradient to one branch, a gradient that ends up in
+// the wrong branch or element still looks plausible. every op's backward is checked against
+// central differences here, ties and zeros included
+mod common;
+
+use common::{assert_close, numeric_grad};
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+const EPS: f32 = 1e-2;
+
+// sum(op(inputs) * [1, 2, 3, ..]), the weights tell a gradient in the wrong element apart
+fn weighted(op: &dyn Fn(&[Tensor]) -> Tensor, inputs: &[Tensor]) -> Tensor {
+    let out = op(inputs);
+    let size = out.buffer.get_size();
+    (out * Tensor::without_grad((1..=size).map(|i| i as f32).collect())).sum()
+}
+
+// backward_grads of every input against central differences. the inputs have to be at least
+// EPS away from points where op jumps, masks and conditions are captured by op as constants
+fn check_grads(backend: &CPUBackend, op: impl Fn(&[Tensor]) -> Tensor, data: &[Vec<f32>]) {
+    let tensors = |data: &[Vec<f32>]| -> Vec<Tensor> {
+        data.iter()
+            .map(|values| Tensor::new(values.clone()))
+            .collect()
+    };
+    let inputs = tensors(data);
+    let grads = weighted(&op, &inputs).backward_grads(&inputs, backend);
+    for (i, grad) in grads.iter().enumerate() {
+        let perturbed = |x: &[f32]| {
+            let mut data = data.to_vec();
+            data[i] = x.to_vec();
+            weighted(&op, &tensors(&data)).item(backend)
+        };
+        let expected = numeric_grad(perturbed, &data[i], EPS);
+        assert_close(grad, &expected, 1e-3);
+    }
+}
+
+fn values(tensor: &Tensor, backend: &CPUBackend) -> Vec<f32> {
+    tensor.iter_realized(backend).collect()
+}
+
+#[test]
+fn where_mask_routes_by_nonzero_condition() {
+    let backend = CPUBackend::new();
+    // -0.0 counts as zero, any other value as set
+    let cond = [1.0, 0.0, -2.0, -0.0, 1e-30, 0.0];
+    let op = |inputs: &[Tensor]| {
+        Tensor::where_mask(&Tensor::without_grad(cond.to_vec()), &inputs[0], &inputs[1])
+    };
+    let a = vec![0.5, 1.5, -2.0, 3.0, 0.25, -1.0];
+    let b = vec![-0.5, 2.5, 4.0, -3.0, 1.0, 2.0];
+    assert_eq!(
+        values(
+            &op(&[Tensor::new(a.clone()), Tensor::new(b.clone())]),
+            &backend
+        ),
+        vec![0.5, 2.5, -2.0, -3.0, 0.25, 2.0]
+    );
+    check_grads(&backend, op, &[a, b]);
+}
+
+#[test]
+fn where_positive_sends_zero_to_the_second_branch() {
+    let backend = CPUBackend::new();
+    let selector = [-1.0, 0.0, 1e-3, 2.0, -0.0];
+    let op = |inputs: &[Tensor]| {
+        Tensor::without_grad(selector.to_vec()).where_positive(&inputs[0], &inputs[1])
+    };
+    let a = vec![1.0, 2.0, 3.0, 4.0, 5.0];
+    let b = vec![-1.0, -2.0, -3.0, -4.0, -5.0];
+    assert_eq!(
+        values(
+            &op(&[Tensor::new(a.clone()), Tensor::new(b.clone())]),
+            &backend
+        ),
+        vec![-1.0, -2.0, 3.0, 4.0, -5.0]
+    );
+    check_grads(&backend, op, &[a.clone(), b.clone()]);
+
+    // the selector itself never gets a gradient, not even at 0 where the pick switches
+    let selector = Tensor::new(selector.to_vec());
+    let picked = selector.where_positive(&Tensor::new(a), &Tensor::new(b));
+    let grads = weighted(&|_| picked, &[]).backward_grads(&[selector], &backend);
+    assert_eq!(grads[0], vec![0.0; 5]);
+}
+
+#[test]
+fn masked_fill_blocks_the_gradient_of_filled_elements() {
+    let backend = CPUBackend::new();
+    let mask = [0.0, 1.0, 0.0, 1.0, -0.0];
+    let op = |inputs: &[Tensor]| inputs[0].masked_fill(&Tensor::without_grad(mask.to_vec()), 5.0);
+    let x = vec![0.5, -1.0, 2.0, 3.0, -4.0];
+    assert_eq!(
+        values(&op(&[Tensor::new(x.clone())]), &backend),
+        vec![0.5, 5.0, 2.0, 5.0, -4.0]
+    );
+    check_grads(&backend, op, &[x]);
+}
+
+#[test]
+fn gt_is_strict_and_has_no_gradient() {
+    let backend = CPUBackend::new();
+    let a = Tensor::new(vec![1.0, 2.0, -3.0, 0.0, 0.0, 1e-3]);
+    let b = Tensor::new(vec![1.0, -2.0, -1.0, -0.0, 1e-3, 0.0]);
+    let mask = a.gt(&b);
+    assert_eq!(values(&mask, &backend), vec![0.0, 1.0, 0.0, 0.0, 0.0, 1.0]);
+
+    let grads = weighted(&|_| mask, &[]).backward_grads(&[a, b], &backend);
+    assert_eq!(grads, vec![vec![0.0; 6], vec![0.0; 6]]);
+}
+
+#[test]
+fn maximum_splits_the_gradient_of_ties() {
+    let backend = CPUBackend::new();
+    let op = |inputs: &[Tensor]| inputs[0].maximum(&inputs[1]);
+    // ties at 0, 3 and 4, where max(a, b) has slope 1/2 in either input
+    let a = vec![1.0, 2.0, -3.0, 0.5, 0.0, 0.25];
+    let b = vec![1.0, -2.0, -1.0, 0.5, -0.0, 0.75];
+    assert_eq!(
+        values(
+            &op(&[Tensor::new(a.clone()), Tensor::new(b.clone())]),
+            &backend
+        ),
+        vec![1.0, 2.0, -1.0, 0.5, 0.0, 0.75]
+    );
+    check_grads(&backend, op, &[a, b]);
+}
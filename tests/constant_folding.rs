@@ -0,0 +1,29 @@
+use flamer::backends::CPUBackend;
+use flamer::inspect::tensor_info;
+use flamer::lazybuffer::{CreationType, LazyOp};
+use flamer::tensor::Tensor;
+
+#[test]
+fn op_on_constants_folds_into_one_data_buffer() {
+    let product = Tensor::ones(4) * Tensor::full(4, 3.0);
+    match product.buffer.get_op() {
+        LazyOp::Creation(CreationType::RawData(data)) => assert_eq!(&*data, &[3.0; 4]),
+        op => panic!("expected folded data, got {:?}", op),
+    }
+    assert!(!product.requires_grad);
+}
+
+#[test]
+fn folded_constant_gets_no_gradient_and_is_not_stepped() {
+    let backend = CPUBackend::new();
+    let constant = Tensor::ones(4) * Tensor::full(4, 3.0);
+    let weight = Tensor::new(vec![1.0; 4]);
+    let mut loss = (weight * constant).sum();
+    loss.apply_backward(&backend, 0.1);
+
+    assert!(!tensor_info(constant.id).has_grad);
+    assert_eq!(constant.buffer.get_data(&backend), vec![3.0; 4]);
+    // d(sum(w * c))/dw = c, so every weight moves by lr * 3
+    let expected = 1.0 - 0.1 * 3.0;
+    assert_eq!(weight.buffer.get_data(&backend), vec![expected; 4]);
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::{assert_close, numeric_grad};
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+const INPUT: [f32; 8] = [0.5, -1.0, 2.0, 0.25, -0.75, 1.5, 3.0, -2.0];
+const KERNEL: [f32; 3] = [1.0, -0.5, 0.25];
+const WEIGHTS: [f32; 3] = [1.0, -2.0, 0.5];
+
+// sliding dot products without any flipping, the last window that fits ends the output
+fn reference(input: &[f32], kernel: &[f32], stride: usize) -> Vec<f32> {
+    (0..=input.len() - kernel.len())
+        .step_by(stride)
+        .map(|start| {
+            kernel
+                .iter()
+                .enumerate()
+                .map(|(j, k)| input[start + j] * k)
+                .sum()
+        })
+        .collect()
+}
+
+// stride 2 drops the partial window at the end
+fn loss(input: &Tensor, kernel: &Tensor) -> Tensor {
+    (input.conv1d(kernel, 2) * Tensor::without_grad(WEIGHTS.to_vec())).sum()
+}
+
+#[test]
+fn conv1d_matches_a_sliding_window_reference() {
+    let backend = CPUBackend::new();
+    for stride in [1, 2, 3] {
+        let out = Tensor::new(INPUT.to_vec()).conv1d(&Tensor::new(KERNEL.to_vec()), stride);
+        assert_close(
+            &out.iter_realized(&backend).collect::<Vec<_>>(),
+            &reference(&INPUT, &KERNEL, stride),
+            1e-6,
+        );
+    }
+}
+
+#[test]
+fn conv1d_gradients_match_finite_differences() {
+    let backend = CPUBackend::new();
+    let (input, kernel) = (Tensor::new(INPUT.to_vec()), Tensor::new(KERNEL.to_vec()));
+    let grads = loss(&input, &kernel).backward_grads(&[input, kernel], &backend);
+    let input_grad = numeric_grad(
+        |d| loss(&Tensor::new(d.to_vec()), &Tensor::new(KERNEL.to_vec())).item(&backend),
+        &INPUT,
+        1e-2,
+    );
+    let kernel_grad = numeric_grad(
+        |d| loss(&Tensor::new(INPUT.to_vec()), &Tensor::new(d.to_vec())).item(&backend),
+        &KERNEL,
+        1e-2,
+    );
+    assert_close(&grads[0], &input_grad, 1e-2);
+    assert_close(&grads[1], &kernel_grad, 1e-2);
+    // the last input element is in no window
+    assert_eq!(grads[0][7], 0.0);
+}
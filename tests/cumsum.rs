@@ -0,0 +1,48 @@
+mod common;
+
+use common::{assert_close, numeric_grad};
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+// long enough that a parallel scan would have to combine several blocks
+fn data() -> Vec<f32> {
+    (0..1000)
+        .map(|i| ((i * 7 % 13) as f32 - 6.0) * 0.25)
+        .collect()
+}
+
+#[test]
+fn cumsum_matches_a_serial_running_total() {
+    let backend = CPUBackend::new();
+    let data = data();
+    let expected: Vec<f32> = data
+        .iter()
+        .scan(0.0, |total, &x| {
+            *total += x;
+            Some(*total)
+        })
+        .collect();
+    let values: Vec<f32> = Tensor::new(data)
+        .cumsum(0)
+        .iter_realized(&backend)
+        .collect();
+    assert_close(&values, &expected, 1e-4);
+}
+
+#[test]
+fn cumsum_gradient_matches_finite_differences() {
+    let backend = CPUBackend::new();
+    let data = vec![0.5, -1.0, 2.0, 0.25, -0.75];
+    let weights = Tensor::without_grad(vec![1.0, -2.0, 0.5, 3.0, 1.5]);
+    let loss = |x: &Tensor| (x.cumsum(0) * weights).sum();
+    let x = Tensor::new(data.clone());
+    let grads = loss(&x).backward_grads(&[x], &backend);
+    let expected = numeric_grad(
+        |d| loss(&Tensor::new(d.to_vec())).item(&backend),
+        &data,
+        1e-2,
+    );
+    // each element feeds every total from its own position on, so it gets their weights' sum
+    assert_eq!(grads[0], vec![4.0, 3.0, 5.0, 4.5, 1.5]);
+    assert_close(&grads[0], &expected, 1e-2);
+}
@@ -0,0 +1,8 @@
+mod common;
+
+use flamer::backends::CPUBackend;
+
+#[test]
+fn every_division_policy_on_the_cpu() {
+    common::check_div_policies(&CPUBackend::new());
+}
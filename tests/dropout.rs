@@ -0,0 +1,41 @@
+use flamer::backends::CPUBackend;
+use flamer::nn::{Dropout, Module};
+use flamer::random;
+use flamer::tensor::Tensor;
+
+#[test]
+fn training_dropout_zeroes_or_scales_every_element() {
+    let backend = CPUBackend::new();
+    random::seed(3);
+    let x = Tensor::new(vec![2.0; 1000]);
+    let out: Vec<f32> = Dropout::new(0.75)
+        .forward(&x)
+        .iter_realized(&backend)
+        .collect();
+    assert!(out.iter().all(|&v| v == 0.0 || v == 8.0));
+    let kept = out.iter().filter(|&&v| v != 0.0).count();
+    assert!((150..350).contains(&kept), "kept {}", kept);
+}
+
+#[test]
+fn evaluation_passes_the_input_through() {
+    let mut dropout = Dropout::new(0.5);
+    dropout.set_training(false);
+    let x = Tensor::new(vec![1.0, 2.0, 3.0]);
+    assert_eq!(dropout.forward(&x).buffer, x.buffer);
+
+    // and back to dropping once training again
+    dropout.set_training(true);
+    assert_ne!(dropout.forward(&x).buffer, x.buffer);
+}
+
+#[test]
+fn dropped_elements_get_no_gradient() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![1.0; 64]);
+    let out = Dropout::new(0.5).forward(&x);
+    let values: Vec<f32> = out.iter_realized(&backend).collect();
+    let grads = out.sum().backward_grads(&[x], &backend);
+    // every element's gradient is its mask value, the same as its output here
+    assert_eq!(grads[0], values);
+}
@@ -0,0 +1,38 @@
+use flamer::backends::CPUBackend;
+use flamer::nn::{Embedding, Module};
+use flamer::tensor::Tensor;
+
+// a [3, 2] table with row r holding r * 10 + [1, 2]
+fn table() -> Embedding {
+    Embedding::from_weight(Tensor::new(vec![1.0, 2.0, 11.0, 12.0, 21.0, 22.0]), 2)
+}
+
+#[test]
+fn embedding_looks_up_rows() {
+    let backend = CPUBackend::new();
+    let embedding = table();
+    let out = embedding.forward(&Tensor::without_grad(vec![2.0, 0.0, 2.0]));
+    assert_eq!(out.shape(), vec![3, 2]);
+    assert_eq!(
+        out.iter_realized(&backend).collect::<Vec<_>>(),
+        vec![21.0, 22.0, 1.0, 2.0, 21.0, 22.0]
+    );
+}
+
+#[test]
+fn embedding_gradient_adds_up_repeated_rows() {
+    let backend = CPUBackend::new();
+    let embedding = table();
+    let indices = Tensor::without_grad(vec![2.0, 0.0, 2.0]);
+    let weights = Tensor::without_grad(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).reshape(&[3, 2]);
+    let mut loss = (embedding.forward(&indices) * weights).sum();
+    let grads = loss.backward_grads(&[embedding.weight], &backend);
+    // row 1 is never looked up, row 2 gets both of its lookups
+    assert_eq!(grads[0], vec![3.0, 4.0, 0.0, 0.0, 6.0, 8.0]);
+}
+
+#[test]
+#[should_panic(expected = "doesn't split into rows of 4")]
+fn embedding_weight_must_split_into_rows() {
+    Embedding::from_weight(Tensor::new(vec![0.0; 6]), 4);
+}
@@ -0,0 +1,37 @@
+use flamer::error::FlameError;
+use flamer::tensor::Tensor;
+
+#[test]
+fn an_op_on_a_tensor_without_data_is_an_error() {
+    let data = Tensor::new(vec![1.0, 2.0]);
+    let empty = Tensor::new(vec![]);
+    assert_eq!(
+        data.try_add(&empty).unwrap_err(),
+        FlameError::EmptyOperand(empty.id)
+    );
+    assert_eq!(
+        empty.try_mul(&data).unwrap_err(),
+        FlameError::EmptyOperand(empty.id)
+    );
+    assert_eq!(
+        FlameError::EmptyOperand(empty.id).to_string(),
+        format!(
+            "tensor {:?} has no data, it can't be used in an operation",
+            empty.id
+        )
+    );
+}
+
+#[test]
+fn mismatched_sizes_are_an_error() {
+    let (a, b) = (Tensor::new(vec![1.0, 2.0]), Tensor::new(vec![1.0; 3]));
+    assert_eq!(a.try_sub(&b).unwrap_err(), FlameError::SizeMismatch(2, 3));
+    assert_eq!(b.try_div(&a).unwrap_err(), FlameError::SizeMismatch(3, 2));
+    assert!(a.try_add(&a).is_ok());
+}
+
+#[test]
+#[should_panic(expected = "has no data, it can't be used in an operation")]
+fn the_operators_panic_with_the_same_message() {
+    let _ = Tensor::new(vec![1.0]) + Tensor::new(vec![]);
+}
@@ -0,0 +1,27 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+#[test]
+fn the_same_values_and_shape_are_equal() {
+    let backend = CPUBackend::new();
+    let mut computed = Tensor::new(vec![1.0, 2.0, 3.0, 4.0]).reshape(&[2, 2]);
+    let mut loaded = Tensor::new(vec![1.0, 2.0, 3.0, 4.0]).reshape(&[2, 2]);
+    assert!(computed.equal(&mut loaded, &backend));
+    let (mut nan, mut other_nan) = (Tensor::new(vec![f32::NAN]), Tensor::new(vec![f32::NAN]));
+    assert!(nan.equal(&mut other_nan, &backend));
+}
+
+#[test]
+fn different_values_or_shapes_are_unequal() {
+    let backend = CPUBackend::new();
+    let mut a = Tensor::new(vec![1.0, 2.0, 3.0, 4.0]);
+    let mut nudged = Tensor::new(vec![1.0, 2.0, 3.0, 4.0 + f32::EPSILON * 4.0]);
+    assert!(!a.equal(&mut nudged, &backend));
+    let (mut zero, mut negative_zero) = (Tensor::new(vec![0.0]), Tensor::new(vec![-0.0]));
+    assert!(!zero.equal(&mut negative_zero, &backend));
+    // a shape mismatch is an answer, not a panic
+    let mut reshaped = a.reshape(&[2, 2]);
+    assert!(!a.equal(&mut reshaped, &backend));
+    let mut shorter = Tensor::new(vec![1.0, 2.0]);
+    assert!(!a.equal(&mut shorter, &backend));
+}
@@ -0,0 +1,47 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+fn range(n: usize) -> Vec<f32> {
+    (0..n).map(|i| i as f32).collect()
+}
+
+#[test]
+fn a_small_matrix_prints_as_aligned_rows() {
+    let backend = CPUBackend::new();
+    let mut matrix = Tensor::new(vec![1.0, -2.5, 3.0, 40.0, 5.0, 0.25]).reshape(&[2, 3]);
+    assert_eq!(
+        matrix.format(&backend),
+        "[[   1, -2.5,    3],\n [  40,    5, 0.25]]"
+    );
+}
+
+#[test]
+fn long_axes_are_elided_in_the_middle() {
+    let backend = CPUBackend::new();
+    let mut matrix = Tensor::new(range(100)).reshape(&[10, 10]);
+    let expected = [
+        "[[ 0,  1,  2, ...,  7,  8,  9],",
+        " [10, 11, 12, ..., 17, 18, 19],",
+        " [20, 21, 22, ..., 27, 28, 29],",
+        " ...,",
+        " [70, 71, 72, ..., 77, 78, 79],",
+        " [80, 81, 82, ..., 87, 88, 89],",
+        " [90, 91, 92, ..., 97, 98, 99]]",
+    ];
+    assert_eq!(matrix.format(&backend), expected.join("\n"));
+    assert_eq!(Tensor::new(range(6)).format(&backend), "[0, 1, 2, 3, 4, 5]");
+    assert_eq!(
+        Tensor::new(range(7)).format(&backend),
+        "[0, 1, 2, ..., 4, 5, 6]"
+    );
+}
+
+#[test]
+fn higher_ranks_nest_their_brackets() {
+    let backend = CPUBackend::new();
+    let mut cube = Tensor::new(range(8)).reshape(&[2, 2, 2]);
+    assert_eq!(
+        cube.format(&backend),
+        "[[[0, 1],\n  [2, 3]],\n\n [[4, 5],\n  [6, 7]]]"
+    );
+}
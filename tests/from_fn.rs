@@ -0,0 +1,28 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+fn value(i: usize) -> f32 {
+    (i as f32 * 0.37).sin() * 100.0
+}
+
+#[test]
+fn from_fn_matches_new_of_the_same_data() {
+    let backend = CPUBackend::new();
+    let len = 100_003;
+    let generated = Tensor::from_fn(len, value);
+    let collected = Tensor::new((0..len).map(value).collect());
+    assert_eq!(
+        generated.iter_realized(&backend).collect::<Vec<_>>(),
+        collected.iter_realized(&backend).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn from_fn_tensors_are_trainable_leaves() {
+    let backend = CPUBackend::new();
+    let w = Tensor::from_fn(3, |i| i as f32);
+    assert!(w.is_leaf() && w.requires_grad);
+    let mut loss = (w * w).sum();
+    loss.apply_backward(&backend, 0.25);
+    assert_eq!(w.buffer.get_data(&backend), vec![0.0, 0.5, 1.0]);
+}
@@ -0,0 +1,39 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+#[test]
+fn full_like_takes_the_shape_and_not_the_values() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).reshape(&[2, 3]);
+    for (filled, value) in [
+        (x.zeros_like(), 0.0),
+        (x.ones_like(), 1.0),
+        (x.full_like(-2.5), -2.5),
+    ] {
+        assert_eq!(filled.shape(), vec![2, 3]);
+        assert_eq!(
+            filled.iter_realized(&backend).collect::<Vec<_>>(),
+            vec![value; 6]
+        );
+    }
+}
+
+#[test]
+fn writing_to_one_fill_leaves_another_unchanged() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![1.0, 2.0, 3.0]);
+    let (mut a, mut b) = (x.zeros_like(), x.zeros_like());
+    a.realize(&backend);
+    b.realize(&backend);
+    a.accumulate_into(&x, &backend);
+    assert_eq!(a.buffer.get_data(&backend), vec![1.0, 2.0, 3.0]);
+    assert_eq!(b.buffer.get_data(&backend), vec![0.0; 3]);
+}
+
+#[test]
+fn full_like_passes_no_gradient_to_self() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![1.0, 2.0]);
+    let grads = (x * x.full_like(3.0)).sum().backward_grads(&[x], &backend);
+    assert_eq!(grads[0], vec![3.0, 3.0]);
+}
@@ -0,0 +1,34 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+#[test]
+fn grad_norm_of_a_known_gradient() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![1.0, -2.0]);
+    let mut loss = (x * Tensor::without_grad(vec![3.0, -4.0])).sum();
+    loss.backward(&backend);
+    assert_eq!(x.grad_norm(&backend), 5.0);
+
+    // the temporary buffers of one call must not clobber those of the next
+    let y = Tensor::new(vec![0.5, 0.5, 0.5, 0.5]);
+    let mut loss = (y * Tensor::without_grad(vec![1.0, 2.0, 2.0, 4.0])).sum();
+    loss.backward(&backend);
+    assert_eq!(y.grad_norm(&backend), 5.0);
+    assert_eq!(x.grad_norm(&backend), 5.0);
+}
+
+#[test]
+fn grad_norm_after_accumulate_grad() {
+    let backend = CPUBackend::new();
+    let mut x = Tensor::new(vec![0.0; 3]);
+    x.accumulate_grad(&[1.0, 2.0, 0.0], &backend);
+    x.accumulate_grad(&[1.0, 0.0, 1.0], &backend);
+    assert_eq!(x.grad_norm(&backend), 3.0);
+}
+
+#[test]
+fn grad_norm_without_a_gradient_is_zero() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![3.0, 4.0]);
+    assert_eq!(x.grad_norm(&backend), 0.0);
+}
@@ -0,0 +1,28 @@
+use flamer::backends::CPUBackend;
+use flamer::lazybuffer::{CreationType, LazyOp};
+use flamer::tensor::Tensor;
+
+#[test]
+fn device_and_generated_leaves_are_exported_with_their_values() {
+    let backend = CPUBackend::new();
+    let realized = Tensor::new(vec![1.0, 2.0, 3.0]).affine(2.0, 0.0);
+    let leaf = realized.detach_into_leaf(&backend);
+    let generated = Tensor::from_fn(3, |i| i as f32 + 0.5);
+    let root = leaf * generated;
+    let expected = root.iter_realized(&backend).collect::<Vec<_>>();
+
+    let ir = root.buffer.to_ir(&backend);
+    for node in &ir.nodes {
+        if let LazyOp::Creation(creation) = &node.op {
+            assert!(
+                matches!(creation, CreationType::RawData(_)),
+                "{:?}",
+                creation
+            );
+        }
+    }
+    let rebuilt = ir.rebuild();
+    rebuilt.realize(&backend, false);
+    assert_eq!(rebuilt.get_data(&backend), expected);
+    assert_eq!(expected, vec![1.0, 6.0, 15.0]);
+}
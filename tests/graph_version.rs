@@ -0,0 +1,17 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+#[test]
+fn a_gradient_rewritten_by_a_later_backward_is_realized_again() {
+    let backend = CPUBackend::new();
+    let w = Tensor::new(vec![1.0, 2.0]);
+    (w * w).sum().backward(&backend);
+    assert_eq!(w.grad(&backend), Some(vec![2.0, 4.0]));
+
+    // the second backward writes into the same gradient buffer, so the order realize cached
+    // for it belongs to a graph that no longer exists
+    (w * w * w).sum().backward(&backend);
+    assert_eq!(w.grad(&backend), Some(vec![3.0, 12.0]));
+    (w * Tensor::new(vec![5.0, 6.0])).sum().backward(&backend);
+    assert_eq!(w.grad(&backend), Some(vec![5.0, 6.0]));
+}
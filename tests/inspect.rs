@@ -0,0 +1,51 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+use flamer::{TensorInfo, all_tensors, tensor_info};
+
+#[test]
+fn all_tensors_lists_the_graph_in_creation_order() {
+    let x = Tensor::new(vec![1.0, 2.0, 3.0, 4.0]);
+    let w = Tensor::without_grad(vec![0.5; 4]);
+    let y = x * w;
+    let ids = all_tensors();
+    let positions: Vec<usize> = [x.id, w.id, y.id]
+        .iter()
+        .map(|id| ids.iter().position(|listed| listed == id).unwrap())
+        .collect();
+    assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn tensor_info_follows_a_realize() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![1.0, 2.0, 3.0, 4.0]).reshape(&[2, 2]);
+    let mut loss = (x * x).sum();
+    assert_eq!(
+        tensor_info(loss.id),
+        TensorInfo {
+            shape: vec![1],
+            requires_grad: true,
+            is_realized: false,
+            op_kind: "Sum".to_string(),
+            has_grad: false,
+        }
+    );
+
+    loss.realize(&backend);
+    assert!(tensor_info(loss.id).is_realized);
+    assert_eq!(tensor_info(x.id).shape, vec![2, 2]);
+}
+
+#[test]
+fn a_leaf_with_grad_reports_it_after_backward() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![1.0, 2.0]);
+    let info = tensor_info(x.id);
+    assert_eq!(
+        (info.op_kind.as_str(), info.requires_grad),
+        ("Creation", true)
+    );
+    assert!(!info.has_grad);
+    (x * x).sum().backward(&backend);
+    assert!(tensor_info(x.id).has_grad);
+}
@@ -0,0 +1,38 @@
+use flamer::backends::{CPUBackend, TrackingBackend};
+use flamer::tensor::{Tensor, set_retain_intermediate_grads};
+
+// backward through two intermediates, returns how many buffers it left alive and which tensors
+// kept a gradient. run on its own thread since the setting and the registries are per thread
+fn backward_through_intermediates(retain: bool) -> (usize, [bool; 3]) {
+    std::thread::spawn(move || {
+        set_retain_intermediate_grads(retain);
+        let backend = TrackingBackend::new(CPUBackend::new());
+        let w = Tensor::new(vec![1.0, 2.0, 3.0]);
+        let hidden = w * w;
+        let scaled = hidden * Tensor::without_grad(vec![0.5, 0.5, 0.5]);
+        let mut loss = scaled.sum();
+        loss.realize(&backend);
+        let before = backend.snapshot();
+        loss.backward(&backend);
+        let left = backend.leaked_since(&before).len();
+        assert_eq!(w.grad(&backend), Some(vec![1.0, 2.0, 3.0]));
+        let kept = [w, hidden, scaled].map(|tensor| tensor.grad(&backend).is_some());
+        (left, kept)
+    })
+    .join()
+    .unwrap()
+}
+
+#[test]
+fn intermediate_gradients_are_freed_after_backward() {
+    let (left, kept) = backward_through_intermediates(false);
+    assert_eq!(kept, [true, false, false]);
+    let (left_retained, kept_retained) = backward_through_intermediates(true);
+    assert_eq!(kept_retained, [true, true, true]);
+    assert!(
+        left < left_retained,
+        "{} buffers left without retaining, {} with",
+        left,
+        left_retained
+    );
+}
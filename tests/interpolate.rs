@@ -0,0 +1,37 @@
+mod common;
+
+use common::{assert_close, numeric_grad};
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+const DATA: [f32; 4] = [1.0, 3.0, -2.0, 4.0];
+
+#[test]
+fn interpolate_linear_keeps_the_ends_and_blends_between() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(DATA.to_vec());
+    // 7 samples over 3 intervals put one new point halfway in each interval
+    assert_eq!(
+        x.interpolate_linear(7)
+            .iter_realized(&backend)
+            .collect::<Vec<_>>(),
+        vec![1.0, 2.0, 3.0, 0.5, -2.0, 1.0, 4.0]
+    );
+    let down: Vec<f32> = x.interpolate_linear(2).iter_realized(&backend).collect();
+    assert_eq!(down, vec![1.0, 4.0]);
+}
+
+#[test]
+fn interpolate_linear_gradient_matches_finite_differences() {
+    let backend = CPUBackend::new();
+    let weights = Tensor::without_grad(vec![1.0, -2.0, 0.5, 3.0, 1.5]);
+    let loss = |x: &Tensor| (x.interpolate_linear(5) * weights).sum();
+    let x = Tensor::new(DATA.to_vec());
+    let grads = loss(&x).backward_grads(&[x], &backend);
+    let expected = numeric_grad(
+        |d| loss(&Tensor::new(d.to_vec())).item(&backend),
+        &DATA,
+        1e-2,
+    );
+    assert_close(&grads[0], &expected, 1e-3);
+}
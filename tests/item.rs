@@ -0,0 +1,17 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+#[test]
+fn the_mean_of_ones_is_one() {
+    let backend = CPUBackend::new();
+    assert_eq!(Tensor::ones(10).mean().item(&backend), 1.0);
+    let mut loss = (Tensor::new(vec![1.0, 2.0]) * Tensor::new(vec![3.0, 4.0])).sum();
+    assert_eq!(loss.item(&backend), 11.0);
+}
+
+#[test]
+#[should_panic(expected = "item needs a single element tensor, got size 3")]
+fn item_needs_a_single_element() {
+    let backend = CPUBackend::new();
+    Tensor::ones(3).item(&backend);
+}
@@ -0,0 +1,42 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+fn three() -> Vec<Tensor> {
+    vec![
+        Tensor::new(vec![1.0, 2.0]),
+        Tensor::new(vec![3.0, -4.0]),
+        Tensor::new(vec![0.5, 0.25]),
+    ]
+}
+
+#[test]
+fn sum_of_three_tensors_adds_elementwise() {
+    let backend = CPUBackend::new();
+    let total: Tensor = three().into_iter().sum();
+    assert_eq!(
+        total.iter_realized(&backend).collect::<Vec<_>>(),
+        vec![4.5, -1.75]
+    );
+}
+
+#[test]
+fn product_of_three_tensors_multiplies_elementwise() {
+    let backend = CPUBackend::new();
+    let total: Tensor = three().into_iter().product();
+    assert_eq!(
+        total.iter_realized(&backend).collect::<Vec<_>>(),
+        vec![1.5, -2.0]
+    );
+}
+
+#[test]
+#[should_panic(expected = "Cannot sum an empty iterator of tensors")]
+fn sum_of_no_tensors_panics() {
+    let _: Tensor = Vec::<Tensor>::new().into_iter().sum();
+}
+
+#[test]
+#[should_panic(expected = "Cannot multiply an empty iterator of tensors")]
+fn product_of_no_tensors_panics() {
+    let _: Tensor = Vec::<Tensor>::new().into_iter().product();
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use common::{assert_close, numeric_grad};
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+const DATA: [f32; 6] = [3.0, 4.0, 0.0, -1.0, 2.0, 2.0];
+
+#[test]
+fn l2_normalize_gives_rows_of_unit_length() {
+    let backend = CPUBackend::new();
+    let out = Tensor::new(DATA.to_vec())
+        .reshape(&[2, 3])
+        .l2_normalize(0.0);
+    assert_eq!(out.shape(), vec![2, 3]);
+    let values: Vec<f32> = out.iter_realized(&backend).collect();
+    assert_close(
+        &values,
+        &[0.6, 0.8, 0.0, -1.0 / 3.0, 2.0 / 3.0, 2.0 / 3.0],
+        1e-6,
+    );
+}
+
+#[test]
+fn l2_normalize_keeps_a_zero_row_at_zero() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![0.0, 0.0, 1.0, 0.0]).reshape(&[2, 2]);
+    let values: Vec<f32> = x.l2_normalize(1e-6).iter_realized(&backend).collect();
+    assert_close(&values, &[0.0, 0.0, 1.0, 0.0], 1e-5);
+}
+
+#[test]
+fn l2_normalize_gradient_matches_finite_differences() {
+    let backend = CPUBackend::new();
+    let weights = Tensor::without_grad(vec![1.0, -2.0, 0.5, 3.0, 1.5, -1.0]).reshape(&[2, 3]);
+    let loss = |x: &Tensor| (x.reshape(&[2, 3]).l2_normalize(1e-6) * weights).sum();
+    let x = Tensor::new(DATA.to_vec());
+    let grads = loss(&x).backward_grads(&[x], &backend);
+    let expected = numeric_grad(
+        |d| loss(&Tensor::new(d.to_vec())).item(&backend),
+        &DATA,
+        1e-2,
+    );
+    assert_close(&grads[0], &expected, 1e-2);
+}
@@ -0,0 +1,30 @@
+use flamer::backends::CPUBackend;
+use flamer::lazybuffer::Backend;
+use flamer::tensor::Tensor;
+
+#[test]
+fn the_step_leaves_intermediate_buffers_alone() {
+    let backend = CPUBackend::new();
+    let w = Tensor::new(vec![1.0, 2.0]);
+    let mut hidden = w * Tensor::new(vec![3.0, 3.0]);
+    hidden.retain();
+    let mut loss = (hidden * hidden).sum();
+    loss.apply_backward(&backend, 0.01);
+
+    // hidden got a gradient too, but its device values are still w * 3 of the old w
+    let device = hidden.buffer.get_device_handle().unwrap();
+    assert_eq!(backend.read_buffer(&device), vec![3.0, 6.0]);
+    // d/dw of sum((3w)^2) is 18w
+    assert_eq!(w.buffer.get_data(&backend), vec![1.0 - 0.18, 2.0 - 0.36]);
+}
+
+#[test]
+fn only_created_tensors_are_leaves() {
+    let parameter = Tensor::new(vec![1.0, 2.0]);
+    let target = Tensor::without_grad(vec![1.0, 2.0]);
+    let computed = parameter * target;
+    assert!(parameter.is_leaf());
+    assert!(target.is_leaf());
+    assert!(!computed.is_leaf());
+    assert!(!computed.sum().is_leaf());
+}
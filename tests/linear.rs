@@ -0,0 +1,77 @@
+mod common;
+
+use common::{assert_close, numeric_grad};
+use flamer::backends::CPUBackend;
+use flamer::nn::{Linear, Module};
+use flamer::tensor::Tensor;
+
+// x [2, 3], weight [3, 2], bias [2]
+const X: [f32; 6] = [0.5, -1.0, 2.0, 1.5, 0.25, -0.75];
+const WEIGHT: [f32; 6] = [1.0, -0.5, 0.25, 2.0, -1.5, 0.75];
+const BIAS: [f32; 2] = [0.1, -0.2];
+
+fn reference(x: &[f32]) -> Vec<f32> {
+    x.chunks(3)
+        .flat_map(|row| {
+            (0..2).map(move |out| {
+                BIAS[out] + (0..3).map(|k| row[k] * WEIGHT[k * 2 + out]).sum::<f32>()
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn linear_is_a_matmul_plus_bias() {
+    let backend = CPUBackend::new();
+    let layer = Linear::from_parameters(WEIGHT.to_vec(), BIAS.to_vec());
+    let out = layer.forward(&Tensor::without_grad(X.to_vec()).reshape(&[2, 3]));
+    assert_eq!(out.shape(), vec![2, 2]);
+    let values: Vec<f32> = out.iter_realized(&backend).collect();
+    assert_close(&values, &reference(&X), 1e-6);
+
+    // a single sample keeps its 1D shape
+    let sample = layer.forward(&Tensor::without_grad(X[..3].to_vec()));
+    assert_eq!(sample.shape(), vec![2]);
+    let values: Vec<f32> = sample.iter_realized(&backend).collect();
+    assert_close(&values, &reference(&X[..3]), 1e-6);
+}
+
+#[test]
+fn linear_gradients_reach_weight_and_bias() {
+    let backend = CPUBackend::new();
+    let weights = Tensor::without_grad(vec![1.0, -2.0, 0.5, 3.0]).reshape(&[2, 2]);
+    let loss = |layer: &Linear| {
+        (layer.forward(&Tensor::without_grad(X.to_vec()).reshape(&[2, 3])) * weights).sum()
+    };
+    let value = |w: &[f32], b: &[f32]| {
+        loss(&Linear::from_parameters(w.to_vec(), b.to_vec())).item(&backend)
+    };
+
+    let layer = Linear::from_parameters(WEIGHT.to_vec(), BIAS.to_vec());
+    let grads = loss(&layer).backward_grads(&layer.parameters(), &backend);
+    let expected_weight = numeric_grad(|w| value(w, &BIAS), &WEIGHT, 1e-2);
+    let expected_bias = numeric_grad(|b| value(&WEIGHT, b), &BIAS, 1e-2);
+    assert_close(&grads[0], &expected_weight, 1e-2);
+    assert_close(&grads[1], &expected_bias, 1e-2);
+}
+
+#[test]
+fn new_maps_between_the_given_sizes() {
+    let backend = CPUBackend::new();
+    let layer = Linear::new(4, 3);
+    assert!(
+        layer
+            .parameters()
+            .iter()
+            .all(|parameter| parameter.is_leaf())
+    );
+    let out = layer.forward(&Tensor::ones(8).reshape(&[2, 4]));
+    assert_eq!(out.shape(), vec![2, 3]);
+    assert!(out.iter_realized(&backend).all(|v| v.abs() <= 2.0));
+}
+
+#[test]
+#[should_panic(expected = "Linear weight of size 5 doesn't split into rows of 2 outputs")]
+fn weight_has_to_fit_the_bias() {
+    Linear::from_parameters(vec![0.0; 5], vec![0.0; 2]);
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use common::{assert_close, numeric_grad};
+use flamer::backends::CPUBackend;
+use flamer::lazybuffer::Activation;
+use flamer::tensor::Tensor;
+
+// x [2, 3], weight [3, 2], bias [2]
+const X: [f32; 6] = [0.5, -1.0, 2.0, 1.5, 0.25, -0.75];
+const WEIGHT: [f32; 6] = [1.0, -0.5, 0.25, 2.0, -1.5, 0.75];
+const BIAS: [f32; 2] = [0.1, -0.2];
+
+fn layer(x: &[f32], weight: &[f32], bias: &[f32], activation: Activation) -> Tensor {
+    let x = Tensor::new(x.to_vec()).reshape(&[2, 3]);
+    let weight = Tensor::new(weight.to_vec()).reshape(&[3, 2]);
+    x.linear_activation(&weight, &Tensor::new(bias.to_vec()), activation)
+}
+
+fn activate(v: f32, activation: Activation) -> f32 {
+    match activation {
+        Activation::Relu => v.max(0.0),
+        Activation::Sigmoid => 1.0 / (1.0 + (-v).exp()),
+        Activation::Tanh => v.tanh(),
+        Activation::Gelu => 0.5 * v * (1.0 + (0.797_884_6 * (v + 0.044_715 * v * v * v)).tanh()),
+        Activation::None => v,
+    }
+}
+
+#[test]
+fn linear_activation_matches_reference() {
+    let backend = CPUBackend::new();
+    for activation in [
+        Activation::Relu,
+        Activation::Sigmoid,
+        Activation::Tanh,
+        Activation::Gelu,
+        Activation::None,
+    ] {
+        let expected: Vec<f32> = (0..4)
+            .map(|i| {
+                let (row, col) = (i / 2, i % 2);
+                let dot: f32 = (0..3).map(|k| X[row * 3 + k] * WEIGHT[k * 2 + col]).sum();
+                activate(dot + BIAS[col], activation)
+            })
+            .collect();
+        let out = layer(&X, &WEIGHT, &BIAS, activation);
+        assert_eq!(out.shape(), vec![2, 2]);
+        let values: Vec<f32> = out.iter_realized(&backend).collect();
+        assert_close(&values, &expected, 1e-5);
+    }
+}
+
+#[test]
+fn linear_activation_gradients_match_finite_differences() {
+    let backend = CPUBackend::new();
+    let weights = Tensor::without_grad(vec![1.0, -2.0, 0.5, 3.0]);
+    for activation in [Activation::Tanh, Activation::Gelu] {
+        let value = |x: &[f32], w: &[f32], b: &[f32]| {
+            (layer(x, w, b, activation).flatten() * weights)
+                .sum()
+                .item(&backend)
+        };
+        let x = Tensor::new(X.to_vec());
+        let weight = Tensor::new(WEIGHT.to_vec());
+        let bias = Tensor::new(BIAS.to_vec());
+        let out = x
+            .reshape(&[2, 3])
+            .linear_activation(&weight.reshape(&[3, 2]), &bias, activation);
+        let grads = (out.flatten() * weights)
+            .sum()
+            .backward_grads(&[x, weight, bias], &backend);
+        let expected = [
+            numeric_grad(|d| value(d, &WEIGHT, &BIAS), &X, 1e-2),
+            numeric_grad(|d| value(&X, d, &BIAS), &WEIGHT, 1e-2),
+            numeric_grad(|d| value(&X, &WEIGHT, d), &BIAS, 1e-2),
+        ];
+        for (grad, expected) in grads.iter().zip(&expected) {
+            assert_close(grad, expected, 1e-2);
+        }
+    }
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use common::{assert_close, numeric_grad};
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+// per row x - ln(sum(exp(x))), in f64 so the reference doesn't overflow either
+fn reference(data: &[f32], row_len: usize) -> Vec<f32> {
+    data.chunks(row_len)
+        .flat_map(|row| {
+            let max = row.iter().fold(f64::MIN, |m, &x| m.max(x as f64));
+            let log_sum = row
+                .iter()
+                .map(|&x| (x as f64 - max).exp())
+                .sum::<f64>()
+                .ln();
+            row.iter().map(move |&x| (x as f64 - max - log_sum) as f32)
+        })
+        .collect()
+}
+
+#[test]
+fn log_softmax_stays_finite_for_large_logits() {
+    let backend = CPUBackend::new();
+    let data = vec![500.0, 499.0, 300.0, -1.0, 0.0, 1.0];
+    let out = Tensor::new(data.clone()).reshape(&[2, 3]).log_softmax();
+    let values: Vec<f32> = out.iter_realized(&backend).collect();
+    assert!(values.iter().all(|v| v.is_finite()));
+    assert_close(&values, &reference(&data, 3), 1e-5);
+}
+
+#[test]
+fn log_softmax_gradient_matches_finite_differences() {
+    let backend = CPUBackend::new();
+    let data = vec![0.5, -1.0, 2.0, 0.0, 0.25, -0.75];
+    let weights = Tensor::without_grad(vec![1.0, -2.0, 0.5, 3.0, 1.5, -1.0]);
+    let loss = |x: &Tensor| (x.reshape(&[2, 3]).log_softmax() * weights).sum();
+
+    let x = Tensor::new(data.clone());
+    let grads = loss(&x).backward_grads(&[x], &backend);
+    let expected = numeric_grad(
+        |d| loss(&Tensor::new(d.to_vec())).item(&backend),
+        &data,
+        1e-2,
+    );
+    assert_close(&grads[0], &expected, 1e-2);
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use common::{assert_close, numeric_grad};
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+// x [2, 3] and w [4, 3] in the [out, in] layout, so the layer is x @ w^T + b
+const X: [f32; 6] = [0.5, -1.0, 2.0, 1.5, 0.25, -0.75];
+const W: [f32; 12] = [
+    1.0, -0.5, 0.25, 2.0, -1.5, 0.75, 0.5, 0.5, -1.0, 0.0, 1.0, 3.0,
+];
+const B: [f32; 8] = [0.1, -0.2, 0.3, 0.0, 0.1, -0.2, 0.3, 0.0];
+
+fn layer(x: &[f32], w: &[f32], b: &[f32]) -> (Tensor, Tensor, Tensor, Tensor) {
+    let (x, w, b) = (
+        Tensor::new(x.to_vec()),
+        Tensor::new(w.to_vec()),
+        Tensor::new(b.to_vec()),
+    );
+    let out = x.reshape(&[2, 3]).matmul(&w.reshape(&[4, 3]).t()) + b.reshape(&[2, 4]);
+    (x, w, b, out)
+}
+
+#[test]
+fn a_linear_layer_from_matmul_and_t() {
+    let backend = CPUBackend::new();
+    let (_, _, _, out) = layer(&X, &W, &B);
+    let expected: Vec<f32> = (0..8)
+        .map(|i| {
+            let (row, col) = (i / 4, i % 4);
+            B[i] + (0..3).map(|k| X[row * 3 + k] * W[col * 3 + k]).sum::<f32>()
+        })
+        .collect();
+    let values: Vec<f32> = out.iter_realized(&backend).collect();
+    assert_close(&values, &expected, 1e-6);
+
+    // mm is the same product
+    let (x, w) = (Tensor::new(X.to_vec()), Tensor::new(W.to_vec()));
+    let product = x.reshape(&[2, 3]).mm(&w.reshape(&[4, 3]).t());
+    assert_eq!(product.shape(), vec![2, 4]);
+    let values: Vec<f32> = (product + Tensor::new(B.to_vec()).reshape(&[2, 4]))
+        .iter_realized(&backend)
+        .collect();
+    assert_close(&values, &expected, 1e-6);
+}
+
+#[test]
+fn the_layer_backpropagates_through_t() {
+    let backend = CPUBackend::new();
+    let weights = Tensor::without_grad((1..=8).map(|i| i as f32 * 0.5 - 2.0).collect());
+    let loss = |out: Tensor| (out * weights.reshape(&[2, 4])).sum();
+    let value = |x: &[f32], w: &[f32], b: &[f32]| loss(layer(x, w, b).3).item(&backend);
+
+    let (x, w, b, out) = layer(&X, &W, &B);
+    let grads = loss(out).backward_grads(&[x, w, b], &backend);
+    let expected = [
+        numeric_grad(|d| value(d, &W, &B), &X, 1e-2),
+        numeric_grad(|d| value(&X, d, &B), &W, 1e-2),
+        numeric_grad(|d| value(&X, &W, d), &B, 1e-2),
+    ];
+    for (grad, expected) in grads.iter().zip(&expected) {
+        assert_close(grad, expected, 1e-2);
+    }
+}
+
+#[test]
+#[should_panic(expected = "matmul")]
+fn inner_dimensions_have_to_match() {
+    let a = Tensor::new(vec![0.0; 6]).reshape(&[2, 3]);
+    a.matmul(&a);
+}
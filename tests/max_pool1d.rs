@@ -0,0 +1,41 @@
+mod common;
+
+use common::{assert_close, numeric_grad};
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+// no two elements of a window within 2 * EPS of each other, so the max doesn't switch
+const DATA: [f32; 8] = [0.5, -1.0, 2.0, 1.5, 0.25, 3.0, -0.75, 1.0];
+const EPS: f32 = 1e-2;
+
+#[test]
+fn max_pool1d_takes_the_max_of_each_window() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(DATA.to_vec());
+    let values = |kernel, stride| -> Vec<f32> {
+        x.max_pool1d(kernel, stride)
+            .iter_realized(&backend)
+            .collect()
+    };
+    assert_eq!(values(2, 2), vec![0.5, 2.0, 3.0, 1.0]);
+    // overlapping windows, the trailing partial one is dropped
+    assert_eq!(values(3, 2), vec![2.0, 2.0, 3.0]);
+}
+
+#[test]
+fn max_pool1d_gradient_matches_finite_differences() {
+    let backend = CPUBackend::new();
+    for (kernel, stride) in [(2, 2), (3, 2), (3, 1)] {
+        let out_len = (DATA.len() - kernel) / stride + 1;
+        let weights = Tensor::without_grad((1..=out_len).map(|i| i as f32).collect());
+        let loss = |x: &Tensor| (x.max_pool1d(kernel, stride) * weights).sum();
+        let x = Tensor::new(DATA.to_vec());
+        let grads = loss(&x).backward_grads(&[x], &backend);
+        let expected = numeric_grad(
+            |d| loss(&Tensor::new(d.to_vec())).item(&backend),
+            &DATA,
+            EPS,
+        );
+        assert_close(&grads[0], &expected, 1e-3);
+    }
+}
@@ -0,0 +1,42 @@
+mod common;
+
+use common::{assert_close, numeric_grad};
+use flamer::backends::CPUBackend;
+use flamer::tensor::{NormKind, Tensor};
+
+const DATA: [f32; 4] = [3.0, -4.0, 0.5, -0.25];
+
+#[test]
+fn norms_of_a_known_vector() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![3.0, -4.0]);
+    assert_eq!(x.norm(NormKind::L1).item(&backend), 7.0);
+    assert_eq!(x.norm(NormKind::L2).item(&backend), 5.0);
+}
+
+#[test]
+fn norm_gradients_match_finite_differences() {
+    let backend = CPUBackend::new();
+    for kind in [NormKind::L1, NormKind::L2] {
+        let x = Tensor::new(DATA.to_vec());
+        let grads = x.norm(kind).backward_grads(&[x], &backend);
+        let expected = numeric_grad(
+            |d| Tensor::new(d.to_vec()).norm(kind).item(&backend),
+            &DATA,
+            1e-2,
+        );
+        assert_close(&grads[0], &expected, 1e-3);
+    }
+}
+
+#[test]
+fn norm_gradients_of_the_zero_vector() {
+    let backend = CPUBackend::new();
+    let zero = Tensor::new(vec![0.0; 3]);
+    assert_eq!(zero.norm(NormKind::L2).item(&backend), 0.0);
+    // L1 takes 0 as its gradient at 0, L2 has no direction to point in
+    let l1 = zero.norm(NormKind::L1).backward_grads(&[zero], &backend);
+    assert_eq!(l1[0], vec![0.0; 3]);
+    let l2 = zero.norm(NormKind::L2).backward_grads(&[zero], &backend);
+    assert!(l2[0].iter().all(|g| g.is_nan()));
+}
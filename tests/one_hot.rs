@@ -0,0 +1,20 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+#[test]
+fn one_hot_rows_have_a_single_one() {
+    let backend = CPUBackend::new();
+    let targets = Tensor::one_hot(&[2, 0, 2], 3);
+    assert_eq!(targets.shape(), vec![3, 3]);
+    assert!(targets.is_leaf() && !targets.requires_grad);
+    assert_eq!(
+        targets.iter_realized(&backend).collect::<Vec<_>>(),
+        vec![0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0]
+    );
+}
+
+#[test]
+#[should_panic(expected = "one_hot index 3 out of range for 3 classes")]
+fn one_hot_rejects_an_index_past_the_classes() {
+    Tensor::one_hot(&[0, 3], 3);
+}
@@ -0,0 +1,36 @@
+use flamer::backends::{CPUBackend, TrackingBackend};
+use flamer::tensor::{OwnedTensor, Tensor};
+
+#[test]
+fn dropping_the_last_clone_frees_the_buffers() {
+    let backend = TrackingBackend::new(CPUBackend::new());
+    let (x, y) = (Tensor::new(vec![1.0, 2.0]), Tensor::new(vec![3.0, 4.0]));
+    let before = backend.snapshot();
+
+    let product = OwnedTensor::new(x * y, &backend);
+    let copy = product.clone();
+    drop(product);
+    // the clone still owns the storage
+    assert_eq!(
+        copy.iter_realized(&backend).collect::<Vec<_>>(),
+        vec![3.0, 8.0]
+    );
+    let buffer = copy.buffer;
+    drop(copy);
+
+    assert!(buffer.get_device_handle().is_none());
+    backend.assert_no_leaks_since(&before, &[x.buffer, y.buffer]);
+}
+
+#[test]
+fn dropping_frees_the_gradient() {
+    let backend = TrackingBackend::new(CPUBackend::new());
+    let before = backend.snapshot();
+    let mut weight = OwnedTensor::new(Tensor::new(vec![1.0, 2.0]), &backend);
+    weight.realize(&backend);
+    weight.accumulate_grad(&[0.5, 0.5], &backend);
+    assert_eq!(backend.live_buffers(), 2);
+
+    drop(weight);
+    backend.assert_no_leaks_since(&before, &[]);
+}
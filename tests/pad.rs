@@ -0,0 +1,25 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+#[test]
+fn pad_adds_zeros_on_both_sides() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![1.0, -2.0, 3.0]);
+    assert_eq!(
+        x.pad(2, 1).iter_realized(&backend).collect::<Vec<_>>(),
+        vec![0.0, 0.0, 1.0, -2.0, 3.0, 0.0]
+    );
+    assert_eq!(
+        x.pad(0, 0).iter_realized(&backend).collect::<Vec<_>>(),
+        vec![1.0, -2.0, 3.0]
+    );
+}
+
+#[test]
+fn pad_gradient_is_the_slice_of_the_tensor() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![1.0, -2.0, 3.0]);
+    let weights = Tensor::without_grad((1..=6).map(|i| i as f32).collect());
+    let grads = (x.pad(2, 1) * weights).sum().backward_grads(&[x], &backend);
+    assert_eq!(grads[0], vec![3.0, 4.0, 5.0]);
+}
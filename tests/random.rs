@@ -0,0 +1,53 @@
+use flamer::backends::CPUBackend;
+use flamer::seed;
+use flamer::tensor::Tensor;
+
+fn draw(backend: &CPUBackend) -> (Vec<f32>, Vec<f32>) {
+    let (normal, uniform) = (Tensor::randn(64), Tensor::uniform(64, -2.0, 3.0));
+    // realized in the opposite order they were created in
+    let uniform = uniform.iter_realized(backend).collect();
+    (normal.iter_realized(backend).collect(), uniform)
+}
+
+#[test]
+fn the_same_seed_gives_the_same_values() {
+    let backend = CPUBackend::new();
+    seed(7);
+    let first = draw(&backend);
+    seed(7);
+    assert_eq!(draw(&backend), first);
+    seed(8);
+    assert_ne!(draw(&backend), first);
+}
+
+#[test]
+fn uniform_stays_in_its_range() {
+    let backend = CPUBackend::new();
+    seed(1);
+    let values: Vec<f32> = Tensor::uniform(1000, -2.0, 3.0)
+        .iter_realized(&backend)
+        .collect();
+    assert!(values.iter().all(|&v| (-2.0..3.0).contains(&v)));
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    assert!((mean - 0.5).abs() < 0.2, "mean {}", mean);
+}
+
+#[test]
+#[should_panic(expected = "uniform needs low < high")]
+fn uniform_rejects_an_empty_range() {
+    Tensor::uniform(4, 1.0, 1.0);
+}
+
+#[test]
+fn the_same_seed_gives_the_same_dropout_mask() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![1.0; 256]);
+    let mask = |value| -> Vec<f32> {
+        seed(value);
+        x.dropout(0.5).iter_realized(&backend).collect()
+    };
+    let first = mask(11);
+    assert!(first.contains(&0.0) && first.contains(&2.0));
+    assert_eq!(mask(11), first);
+    assert_ne!(mask(12), first);
+}
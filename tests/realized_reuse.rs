@@ -0,0 +1,42 @@
+use flamer::backends::CPUBackend;
+use flamer::lazybuffer::Backend;
+use flamer::tensor::Tensor;
+
+// writes straight into a realized tensor's device buffer, a graph built afterwards only sees
+// these values if it reads the buffer instead of uploading or computing the tensor again
+fn overwrite(tensor: &Tensor, values: &[f32], backend: &dyn Backend) {
+    let handle = tensor.buffer.get_device_handle().unwrap();
+    backend.to_device(values, &handle);
+}
+
+#[test]
+fn a_realized_leaf_is_not_uploaded_again() {
+    let backend = CPUBackend::new();
+    let mut x = Tensor::new(vec![1.0, 2.0]);
+    x.realize(&backend);
+    overwrite(&x, &[10.0, 20.0], &backend);
+
+    let mut doubled = x + x;
+    doubled.realize(&backend);
+    assert_eq!(doubled.buffer.get_data(&backend), vec![20.0, 40.0]);
+}
+
+#[test]
+fn a_realized_intermediate_is_reused_until_a_step() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![1.0, 2.0]);
+    let mut squared = x * x;
+    squared.realize(&backend);
+    overwrite(&squared, &[5.0, 5.0], &backend);
+
+    let mut shifted = squared + x;
+    shifted.realize(&backend);
+    assert_eq!(shifted.buffer.get_data(&backend), vec![6.0, 7.0]);
+
+    // x moves to [0, 1], so squared has to be computed from it again
+    let mut loss = x.sum();
+    loss.apply_backward(&backend, 1.0);
+    let mut shifted = squared + x;
+    shifted.realize(&backend);
+    assert_eq!(shifted.buffer.get_data(&backend), vec![0.0, 2.0]);
+}
@@ -0,0 +1,31 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+#[test]
+fn every_element_is_repeated_in_a_row() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![1.0, 2.0, 3.0]);
+    assert_eq!(
+        x.repeat_interleave(2)
+            .iter_realized(&backend)
+            .collect::<Vec<_>>(),
+        vec![1.0, 1.0, 2.0, 2.0, 3.0, 3.0]
+    );
+}
+
+#[test]
+fn the_gradient_sums_each_group_of_repeats() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![1.0, 2.0]);
+    let weights = Tensor::without_grad(vec![1.0, 2.0, 3.0, 10.0, 20.0, 30.0]);
+    let grads = (x.repeat_interleave(3) * weights)
+        .sum()
+        .backward_grads(&[x], &backend);
+    assert_eq!(grads[0], vec![6.0, 60.0]);
+}
+
+#[test]
+#[should_panic(expected = "repeat_interleave needs at least one repeat")]
+fn zero_repeats_are_rejected() {
+    Tensor::new(vec![1.0]).repeat_interleave(0);
+}
@@ -0,0 +1,44 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+const DATA: [f32; 7] = [-2.5, -1.5, -0.4, 0.5, 1.5, 2.5, 2.7];
+
+fn values(tensor: &Tensor, backend: &CPUBackend) -> Vec<f32> {
+    tensor.iter_realized(backend).collect()
+}
+
+#[test]
+fn round_takes_halfway_cases_to_even() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(DATA.to_vec());
+    assert_eq!(
+        values(&x.round(), &backend),
+        vec![-2.0, -2.0, -0.0, 0.0, 2.0, 2.0, 3.0]
+    );
+    assert_eq!(
+        values(&x.floor(), &backend),
+        vec![-3.0, -2.0, -1.0, 0.0, 1.0, 2.0, 2.0]
+    );
+    assert_eq!(
+        values(&x.ceil(), &backend),
+        vec![-2.0, -1.0, -0.0, 1.0, 2.0, 3.0, 3.0]
+    );
+}
+
+#[test]
+fn rounding_has_a_zero_gradient_unless_passed_straight_through() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(DATA.to_vec());
+    let grads = (x.round() + x.floor() + x.ceil())
+        .sum()
+        .backward_grads(&[x], &backend);
+    assert_eq!(grads[0], vec![0.0; 7]);
+
+    let straight_through = Tensor::with_grad_of(&x.round(), &x);
+    assert_eq!(
+        values(&straight_through, &backend),
+        values(&x.round(), &backend)
+    );
+    let grads = straight_through.sum().backward_grads(&[x], &backend);
+    assert_eq!(grads[0], vec![1.0; 7]);
+}
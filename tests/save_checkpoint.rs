@@ -0,0 +1,45 @@
+use std::path::PathBuf;
+
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+use flamer::{load_checkpoint, save_checkpoint};
+
+fn checkpoint_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("flamer-{}-{}.ckpt", name, std::process::id()))
+}
+
+fn values(tensor: &Tensor, backend: &CPUBackend) -> Vec<u32> {
+    tensor.iter_realized(backend).map(f32::to_bits).collect()
+}
+
+#[test]
+fn parameters_come_back_bit_for_bit() {
+    let backend = CPUBackend::new();
+    let path = checkpoint_path("round-trip");
+    let w = Tensor::new(vec![0.1, -2.5e-8, 1e10, 3.0]);
+    let b = Tensor::new(vec![-0.0, 1.0 / 3.0]);
+    let saved = (values(&w, &backend), values(&b, &backend));
+    save_checkpoint(&path, &[w, b], &backend).unwrap();
+
+    // a training step moves both parameters
+    let mut loss = (w * w).sum() + b.sum();
+    let loss_before = values(&loss, &backend);
+    loss.apply_backward(&backend, 0.1);
+    assert_ne!(values(&b, &backend), saved.1);
+
+    load_checkpoint(&path, &[w, b], &backend).unwrap();
+    assert_eq!((values(&w, &backend), values(&b, &backend)), saved);
+    // graphs built on the parameters see the loaded values
+    assert_eq!(values(&loss, &backend), loss_before);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn loading_into_different_parameters_is_an_error() {
+    let backend = CPUBackend::new();
+    let path = checkpoint_path("mismatch");
+    save_checkpoint(&path, &[Tensor::new(vec![1.0, 2.0])], &backend).unwrap();
+    let error = load_checkpoint(&path, &[Tensor::new(vec![1.0; 3])], &backend).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,47 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+// values where x * factor rounds, so going through the scaled tensor would change them
+fn data() -> Vec<f32> {
+    (1..=64).map(|i| i as f32 * 0.1 + 1e-3 / i as f32).collect()
+}
+
+#[test]
+fn scale_grad_keeps_the_values_bit_for_bit() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(data());
+    for factor in [3.0, -0.7, 1e-3, 1e4] {
+        let scaled: Vec<u32> = x
+            .scale_grad(factor)
+            .iter_realized(&backend)
+            .map(f32::to_bits)
+            .collect();
+        let expected: Vec<u32> = data().into_iter().map(f32::to_bits).collect();
+        assert_eq!(scaled, expected, "factor {}", factor);
+    }
+}
+
+#[test]
+fn scale_grad_scales_the_gradient() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![1.0, -2.0, 0.5]);
+    let weights = Tensor::without_grad(vec![1.0, 2.0, 3.0]);
+    let grads = (x.scale_grad(-0.5) * weights)
+        .sum()
+        .backward_grads(&[x], &backend);
+    assert_eq!(grads[0], vec![-0.5, -1.0, -1.5]);
+}
+
+#[test]
+fn a_factor_that_overflows_the_values_leaves_the_forward_finite() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![1e30, -2.0]);
+    // x * factor is inf for the first element, only the gradient is scaled
+    let scaled = x.scale_grad(1e10);
+    assert_eq!(
+        scaled.iter_realized(&backend).collect::<Vec<_>>(),
+        vec![1e30, -2.0]
+    );
+    let grads = scaled.sum().backward_grads(&[x], &backend);
+    assert_eq!(grads[0], vec![1e10, 1e10]);
+}
@@ -0,0 +1,46 @@
+mod common;
+
+use common::{assert_close, numeric_grad};
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+const TABLE: [f32; 6] = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+// row 2 is hit twice, row 1 never
+const INDICES: [f32; 3] = [2.0, 0.0, 2.0];
+const UPDATES: [f32; 6] = [10.0, 20.0, 30.0, 40.0, 50.0, 60.0];
+
+// the leaves and table.scatter_add(INDICES, updates) over their [3, 2] views
+fn scatter(table: &[f32], updates: &[f32]) -> (Tensor, Tensor, Tensor) {
+    let (table, updates) = (Tensor::new(table.to_vec()), Tensor::new(updates.to_vec()));
+    let out = table.reshape(&[3, 2]).scatter_add(
+        &Tensor::without_grad(INDICES.to_vec()),
+        &updates.reshape(&[3, 2]),
+    );
+    (table, updates, out)
+}
+
+#[test]
+fn scatter_add_accumulates_repeated_indices() {
+    let backend = CPUBackend::new();
+    let (_, _, out) = scatter(&TABLE, &UPDATES);
+    assert_eq!(out.shape(), vec![3, 2]);
+    assert_eq!(
+        out.iter_realized(&backend).collect::<Vec<_>>(),
+        vec![31.0, 42.0, 3.0, 4.0, 65.0, 86.0]
+    );
+}
+
+#[test]
+fn scatter_add_gradients_match_finite_differences() {
+    let backend = CPUBackend::new();
+    let weights = Tensor::without_grad(vec![1.0, -2.0, 0.5, 3.0, 1.5, -1.0]).reshape(&[3, 2]);
+    let loss = |out: Tensor| (out * weights).sum();
+
+    let (table, updates, out) = scatter(&TABLE, &UPDATES);
+    let grads = loss(out).backward_grads(&[table, updates], &backend);
+    let value = |t: &[f32], u: &[f32]| loss(scatter(t, u).2).item(&backend);
+    let expected_table = numeric_grad(|t| value(t, &UPDATES), &TABLE, 1e-2);
+    let expected_updates = numeric_grad(|u| value(&TABLE, u), &UPDATES, 1e-2);
+    assert_close(&grads[0], &expected_table, 1e-2);
+    assert_close(&grads[1], &expected_updates, 1e-2);
+}
@@ -0,0 +1,46 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+#[test]
+fn reshape_views_change_only_the_shape() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new((0..6).map(|i| i as f32).collect());
+    assert_eq!(x.shape(), vec![6]);
+    let matrix = x.reshape(&[2, 3]);
+    assert_eq!(matrix.shape(), vec![2, 3]);
+    assert_eq!(matrix.unsqueeze(0).shape(), vec![1, 2, 3]);
+    assert_eq!(matrix.unsqueeze(2).shape(), vec![2, 3, 1]);
+    assert_eq!(matrix.unsqueeze(1).squeeze(1).shape(), vec![2, 3]);
+    assert_eq!(matrix.unsqueeze(2).flatten().shape(), vec![6]);
+    assert_eq!(
+        matrix.iter_realized(&backend).collect::<Vec<_>>(),
+        vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0]
+    );
+}
+
+#[test]
+fn gradients_pass_through_views_to_the_original() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![1.0, 2.0, 3.0, 4.0]);
+    let view = x.reshape(&[2, 2]).unsqueeze(0).flatten();
+    let grads = (view * view).sum().backward_grads(&[x], &backend);
+    assert_eq!(grads[0], vec![2.0, 4.0, 6.0, 8.0]);
+}
+
+#[test]
+#[should_panic(expected = "Cannot reshape tensor of size 6 into [4, 2]")]
+fn reshape_keeps_the_size() {
+    Tensor::new(vec![0.0; 6]).reshape(&[4, 2]);
+}
+
+#[test]
+#[should_panic(expected = "Cannot squeeze dim 0 of shape [2, 3]")]
+fn squeeze_needs_a_size_one_axis() {
+    Tensor::new(vec![0.0; 6]).reshape(&[2, 3]).squeeze(0);
+}
+
+#[test]
+#[should_panic(expected = "Cannot unsqueeze dim 2 of shape [6]")]
+fn unsqueeze_stays_within_the_shape() {
+    Tensor::new(vec![0.0; 6]).unsqueeze(2);
+}
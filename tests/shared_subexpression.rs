@@ -0,0 +1,42 @@
+mod common;
+
+use common::{assert_close, numeric_grad};
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+// a + b is built twice and comes back from the op cache as one node with two consumers
+fn z(a: &Tensor, b: &Tensor, c: &Tensor) -> Tensor {
+    ((*a + *b) + (*a + *b) * *c).sum()
+}
+
+#[test]
+fn a_shared_subexpression_gets_the_gradient_of_both_uses() {
+    let backend = CPUBackend::new();
+    let data = [
+        vec![0.5, -1.0, 2.0],
+        vec![1.5, 0.25, -0.5],
+        vec![2.0, -3.0, 0.5],
+    ];
+    let [a, b, c] = data.clone().map(Tensor::new);
+    assert_eq!((a + b).id, (a + b).id);
+
+    let grads = z(&a, &b, &c).backward_grads(&[a, b, c], &backend);
+    // dz/da = dz/db = 1 + c, dz/dc = a + b
+    assert_eq!(grads[0], vec![3.0, -2.0, 1.5]);
+    assert_eq!(grads[1], grads[0]);
+    assert_eq!(grads[2], vec![2.0, -0.75, 1.5]);
+
+    for (i, grad) in grads.iter().enumerate() {
+        let expected = numeric_grad(
+            |x| {
+                let mut inputs = data.clone();
+                inputs[i] = x.to_vec();
+                let [a, b, c] = inputs.map(Tensor::new);
+                z(&a, &b, &c).item(&backend)
+            },
+            &data[i],
+            1e-2,
+        );
+        assert_close(grad, &expected, 1e-2);
+    }
+}
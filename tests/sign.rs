@@ -0,0 +1,14 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+#[test]
+fn sign_of_each_element() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new(vec![-3.5, -0.0, 0.0, 1e-30, 2.0]);
+    assert_eq!(
+        x.sign().iter_realized(&backend).collect::<Vec<_>>(),
+        vec![-1.0, 0.0, 0.0, 1.0, 1.0]
+    );
+    let grads = x.sign().sum().backward_grads(&[x], &backend);
+    assert_eq!(grads[0], vec![0.0; 5]);
+}
@@ -0,0 +1,32 @@
+use flamer::backends::CPUBackend;
+use flamer::error::FlameError;
+use flamer::tensor::Tensor;
+
+#[test]
+fn reading_a_loss_after_an_update_needs_a_new_realize() {
+    let backend = CPUBackend::new();
+    let w = Tensor::new(vec![1.0, 2.0]);
+    let mut loss = (w * w).sum();
+    loss.buffer.realize(&backend, false);
+    assert_eq!(loss.buffer.try_get_data(&backend), Ok(vec![5.0]));
+
+    // the update makes the realized loss stale, reading it is an error instead of the old value
+    loss.apply_backward(&backend, 0.25);
+    assert_eq!(
+        loss.buffer.try_get_data(&backend),
+        Err(FlameError::StaleBuffer(loss.buffer))
+    );
+
+    // realizing again computes it from the updated parameters, w is now [0.5, 1.0]
+    loss.buffer.realize(&backend, false);
+    assert_eq!(loss.buffer.try_get_data(&backend), Ok(vec![1.25]));
+}
+
+#[test]
+fn parameters_themselves_are_never_stale() {
+    let backend = CPUBackend::new();
+    let w = Tensor::new(vec![2.0]);
+    let mut loss = (w * w).sum();
+    loss.apply_backward(&backend, 0.25);
+    assert_eq!(w.buffer.try_get_data(&backend), Ok(vec![1.0]));
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use common::{assert_close, numeric_grad};
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+// two-pass reference in f64
+fn reference_var(data: &[f32], unbiased: bool) -> f32 {
+    let n = data.len() as f64;
+    let mean = data.iter().map(|&x| x as f64).sum::<f64>() / n;
+    let squares = data.iter().map(|&x| (x as f64 - mean).powi(2)).sum::<f64>();
+    (squares / if unbiased { n - 1.0 } else { n }) as f32
+}
+
+#[test]
+fn var_and_std_match_reference() {
+    let backend = CPUBackend::new();
+    let data = vec![1.0, 2.0, 4.0, 7.0, -3.0];
+    let x = Tensor::new(data.clone());
+
+    let biased = x.var(false).item(&backend);
+    let unbiased = x.var(true).item(&backend);
+    let std = x.std().item(&backend);
+    assert_close(&[biased], &[reference_var(&data, false)], 1e-5);
+    assert_close(&[unbiased], &[reference_var(&data, true)], 1e-5);
+    assert_close(&[std], &[reference_var(&data, true).sqrt()], 1e-5);
+}
+
+#[test]
+fn var_gradient_matches_finite_differences() {
+    let backend = CPUBackend::new();
+    let data = vec![0.5, -1.0, 2.0, 3.5];
+    for unbiased in [false, true] {
+        let x = Tensor::new(data.clone());
+        let grads = x.var(unbiased).backward_grads(&[x], &backend);
+        let expected = numeric_grad(
+            |values| Tensor::new(values.to_vec()).var(unbiased).item(&backend),
+            &data,
+            1e-2,
+        );
+        assert_close(&grads[0], &expected, 1e-2);
+    }
+}
+
+#[test]
+#[should_panic(expected = "var of an empty tensor")]
+fn var_of_empty_tensor_panics() {
+    Tensor::new(vec![]).var(true);
+}
@@ -0,0 +1,45 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+use flamer::{Stats, reset_counters, stats};
+
+// one step of a loop that builds its graph from scratch, the way a training step does
+fn step(backend: &CPUBackend) -> f32 {
+    let x = Tensor::new(vec![1.0, 2.0, 3.0]);
+    (x * x + x).sum().item(backend)
+}
+
+#[test]
+fn counts_grow_with_every_graph_built() {
+    reset_counters();
+    let backend = CPUBackend::new();
+    assert_eq!(step(&backend), 20.0);
+    let after_one = stats();
+    assert!(after_one.tensors_created > 0 && after_one.registry_len > 0);
+
+    step(&backend);
+    let after_two = stats();
+    assert!(after_two.tensors_created > after_one.tensors_created);
+    assert!(after_two.buffers_created > after_one.buffers_created);
+}
+
+#[test]
+fn reset_counters_starts_from_a_clean_slate() {
+    step(&CPUBackend::new());
+    reset_counters();
+    assert_eq!(
+        stats(),
+        Stats {
+            tensors_created: 0,
+            buffers_created: 0,
+            registry_len: 0,
+        }
+    );
+
+    // the same work after a reset gives the same counts, and a new backend works with new ids
+    let backend = CPUBackend::new();
+    assert_eq!(step(&backend), 20.0);
+    let first = stats();
+    reset_counters();
+    assert_eq!(step(&CPUBackend::new()), 20.0);
+    assert_eq!(stats(), first);
+}
@@ -0,0 +1,18 @@
+use flamer::backends::CPUBackend;
+use flamer::lazybuffer::Backend;
+use flamer::tensor::Tensor;
+
+#[test]
+fn a_trained_tensor_keeps_its_values_on_the_new_backend() {
+    let (from, to) = (CPUBackend::new(), CPUBackend::new());
+    let mut w = Tensor::new(vec![1.0, -2.0, 4.0]);
+    (w * w).sum().apply_backward(&from, 0.1);
+    let (values, gradient) = (w.buffer.get_data(&from), w.grad(&from));
+    w.to_backend(&from, &to);
+
+    let device = w.buffer.get_device_handle().unwrap();
+    assert_eq!(to.read_buffer(&device), values);
+    assert_ne!(values, vec![1.0, -2.0, 4.0]);
+    // the gradient moves along as it was stored
+    assert_eq!(w.grad(&to), gradient);
+}
@@ -0,0 +1,66 @@
+use flamer::backends::{CPUBackend, TrackingBackend};
+use flamer::lazybuffer::{Backend, set_buffer_reuse};
+use flamer::tensor::Tensor;
+
+#[test]
+fn realized_outputs_are_not_leaks() {
+    let backend = TrackingBackend::new(CPUBackend::new());
+    let x = Tensor::new(vec![1.0, 2.0]);
+    let before = backend.snapshot();
+    let mut out = x * x;
+    out.realize(&backend);
+    backend.assert_no_leaks_since(&before, &[x.buffer, out.buffer]);
+    assert_eq!(backend.frees(), 0);
+}
+
+#[test]
+#[should_panic(expected = "1 buffers allocated since the snapshot were never freed")]
+fn a_forgotten_temporary_buffer_is_a_leak() {
+    let backend = TrackingBackend::new(CPUBackend::new());
+    let before = backend.snapshot();
+    let freed = backend.allocate_temporary_buffer(&[1.0], 1);
+    backend.free_buffer(&freed);
+    backend.allocate_temporary_buffer(&[2.0], 1);
+    assert_eq!((backend.allocations(), backend.frees()), (2, 1));
+    backend.assert_no_leaks_since(&before, &[]);
+}
+
+// squared is retained and every intermediate has the same size, so with reuse the others share
+// storage while squared keeps its own
+fn realize_chain(backend: &TrackingBackend, reuse: bool) -> (Tensor, Tensor) {
+    set_buffer_reuse(reuse);
+    let x = Tensor::new(vec![1.0, 2.0, 3.0]);
+    let mut squared = x * x;
+    squared.retain();
+    let shifted = squared + x;
+    let mut out = (shifted * shifted + x) * x;
+    out.realize(backend);
+    (squared, out)
+}
+
+#[test]
+fn buffer_reuse_allocates_fewer_buffers() {
+    let allocations = |reuse: bool| {
+        let backend = TrackingBackend::new(CPUBackend::new());
+        realize_chain(&backend, reuse);
+        assert_eq!(backend.frees(), 0);
+        backend.allocations()
+    };
+    let (plain, reused) = (allocations(false), allocations(true));
+    assert!(
+        reused < plain,
+        "{} buffers with reuse, {} without",
+        reused,
+        plain
+    );
+}
+
+#[test]
+fn a_retained_intermediate_keeps_its_own_buffer_with_reuse() {
+    let backend = TrackingBackend::new(CPUBackend::new());
+    let (squared, out) = realize_chain(&backend, true);
+    let held = squared.buffer.get_device_handle().unwrap().id;
+    assert_eq!(held, squared.buffer);
+    assert_ne!(out.buffer.get_device_handle().unwrap().id, held);
+    assert_eq!(squared.buffer.get_data(&backend), vec![1.0, 4.0, 9.0]);
+}
@@ -0,0 +1,53 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+#[test]
+fn every_batch_is_transposed_on_its_own() {
+    let backend = CPUBackend::new();
+    let data: Vec<f32> = (0..24).map(|i| i as f32).collect();
+    let transposed = Tensor::new(data.clone())
+        .reshape(&[2, 3, 4])
+        .transpose_last_two();
+    assert_eq!(transposed.shape(), vec![2, 4, 3]);
+    // each [3, 4] batch through the 2D transpose
+    let expected: Vec<f32> = data
+        .chunks(12)
+        .flat_map(|batch| {
+            Tensor::new(batch.to_vec())
+                .reshape(&[3, 4])
+                .t()
+                .iter_realized(&backend)
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    assert_eq!(
+        transposed.iter_realized(&backend).collect::<Vec<_>>(),
+        expected
+    );
+    assert_eq!(&expected[..6], [0.0, 4.0, 8.0, 1.0, 5.0, 9.0]);
+}
+
+#[test]
+fn the_gradient_is_transposed_back() {
+    let backend = CPUBackend::new();
+    let x = Tensor::new((0..24).map(|i| i as f32).collect());
+    let weights: Vec<f32> = (0..24).map(|i| i as f32 * 0.5 - 3.0).collect();
+    let out = x.reshape(&[2, 3, 4]).transpose_last_two();
+    let grads = (out * Tensor::without_grad(weights.clone()))
+        .sum()
+        .backward_grads(&[x], &backend);
+    // the weight of out[b, j, i] lands on x[b, i, j]
+    let expected: Vec<f32> = (0..24)
+        .map(|index| {
+            let (b, i, j) = (index / 12, index % 12 / 4, index % 4);
+            weights[b * 12 + j * 3 + i]
+        })
+        .collect();
+    assert_eq!(grads[0], expected);
+}
+
+#[test]
+#[should_panic(expected = "transpose_last_two needs at least 2 dimensions")]
+fn a_vector_has_no_last_two_axes() {
+    Tensor::new(vec![1.0, 2.0]).transpose_last_two();
+}
@@ -0,0 +1,57 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+const ROWS: usize = 3;
+const COLS: usize = 4;
+
+fn matrix() -> Tensor {
+    Tensor::new((1..=ROWS * COLS).map(|i| i as f32).collect())
+}
+
+// element (r, c) of a 1..=12 matrix where keep(r, c) and 0 elsewhere
+fn reference(keep: impl Fn(i32, i32) -> bool) -> Vec<f32> {
+    (0..ROWS * COLS)
+        .map(|i| {
+            let (r, c) = ((i / COLS) as i32, (i % COLS) as i32);
+            if keep(r, c) { (i + 1) as f32 } else { 0.0 }
+        })
+        .collect()
+}
+
+#[test]
+fn triu_and_tril_keep_their_side_of_the_diagonal() {
+    let backend = CPUBackend::new();
+    let x = matrix().reshape(&[ROWS, COLS]);
+    for diagonal in [-1, 0, 2] {
+        let upper: Vec<f32> = x.triu(diagonal).iter_realized(&backend).collect();
+        assert_eq!(
+            upper,
+            reference(|r, c| c - r >= diagonal),
+            "triu {}",
+            diagonal
+        );
+        let lower: Vec<f32> = x.tril(diagonal).iter_realized(&backend).collect();
+        assert_eq!(
+            lower,
+            reference(|r, c| c - r <= diagonal),
+            "tril {}",
+            diagonal
+        );
+    }
+}
+
+#[test]
+fn masked_elements_get_no_gradient() {
+    let backend = CPUBackend::new();
+    let x = matrix();
+    let grads = x
+        .reshape(&[ROWS, COLS])
+        .tril(0)
+        .sum()
+        .backward_grads(&[x], &backend);
+    let expected: Vec<f32> = reference(|r, c| c <= r)
+        .iter()
+        .map(|&v| if v == 0.0 { 0.0 } else { 1.0 })
+        .collect();
+    assert_eq!(grads[0], expected);
+}
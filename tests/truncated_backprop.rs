@@ -0,0 +1,38 @@
+use flamer::backends::CPUBackend;
+use flamer::stats;
+use flamer::tensor::Tensor;
+
+#[test]
+fn the_graph_stays_bounded_over_a_thousand_recurrent_steps() {
+    let backend = CPUBackend::new();
+    let w = Tensor::new(vec![0.5, -0.25]);
+    let x = Tensor::without_grad(vec![1.0, 2.0]);
+    let mut state = Tensor::without_grad(vec![0.0, 0.0]);
+    let mut growth = Vec::new();
+    for _ in 0..1000 {
+        let before = stats();
+        let next = (state * w + x).sin();
+        let mut loss = next.sum();
+        loss.apply_backward(&backend, 0.01);
+        // the graph behind each step's loss starts at the previous state
+        assert_eq!(loss.buffer.to_ir(&backend).nodes.len(), 7);
+        state = next.detach_into_leaf(&backend);
+        assert!(state.is_leaf());
+        let after = stats();
+        growth.push((
+            after.tensors_created - before.tensors_created,
+            after.registry_len - before.registry_len,
+        ));
+    }
+    // after the first step, which also creates the shared constants, every step adds the same
+    // number of tensors and buffers, none of the history piles up
+    let steady = growth[1];
+    if let Some(step) = growth[1..].iter().position(|step| *step != steady) {
+        panic!(
+            "step {} added {:?}, the others {:?}",
+            step + 1,
+            growth[step + 1],
+            steady
+        );
+    }
+}
@@ -0,0 +1,115 @@
+// these need a Vulkan device, on a machine without one every test returns early
+mod common;
+
+use flamer::backends::VulkanBackend;
+use flamer::lazybuffer::{Backend, get_next_buffer_id};
+use flamer::tensor::Tensor;
+use flamer::vulkan::MemoryPreference;
+
+fn vulkan(memory_preference: MemoryPreference) -> Option<VulkanBackend> {
+    std::panic::catch_unwind(|| {
+        VulkanBackend::with_memory_preference("FlameR test", memory_preference)
+    })
+    .map_err(|_| eprintln!("no Vulkan device, skipping"))
+    .ok()
+}
+
+#[test]
+fn reads_stop_at_the_logical_size_of_a_rounded_up_allocation() {
+    for preference in [MemoryPreference::DeviceLocal, MemoryPreference::HostVisible] {
+        let Some(backend) = vulkan(preference) else {
+            return;
+        };
+        // 3 floats are 12 bytes, drivers round allocations up to their alignment
+        let handle = backend.allocate_buffer(get_next_buffer_id(), 3);
+        backend.to_device(&[1.0, 2.0, 3.0], &handle);
+        assert_eq!(backend.read_buffer(&handle), vec![1.0, 2.0, 3.0]);
+        backend.free_buffer(&handle);
+
+        // a small buffer that may get the memory of a freed larger one
+        let large = backend.allocate_buffer(get_next_buffer_id(), 1000);
+        backend.to_device(&vec![7.0; 1000], &large);
+        backend.free_buffer(&large);
+        let small = backend.allocate_buffer(get_next_buffer_id(), 5);
+        backend.to_device(&[1.0; 5], &small);
+        assert_eq!(backend.read_buffer(&small), vec![1.0; 5]);
+        backend.free_buffer(&small);
+    }
+}
+
+#[test]
+fn a_tensor_larger_than_the_memory_budget_is_computed_in_chunks() {
+    let Some(backend) = vulkan(MemoryPreference::DeviceLocal) else {
+        return;
+    };
+    // four 1000 float buffers per tile fit, the tensor needs 100 tiles
+    let backend = backend.with_memory_budget(16_000);
+    let a: Vec<f32> = (0..100_000).map(|i| i as f32).collect();
+    let b = vec![2.0; 100_000];
+    let product = Tensor::new(a.clone()) * Tensor::new(b);
+    let mut tiles = 0;
+    let values = product.chunk_realize_sliced(&backend, usize::MAX, |_| tiles += 1);
+    assert_eq!(tiles, 100);
+    assert_eq!(values, a.iter().map(|x| x * 2.0).collect::<Vec<_>>());
+}
+
+#[test]
+fn unported_ops_run_on_the_cpu_fallback() {
+    let Some(backend) = vulkan(MemoryPreference::DeviceLocal) else {
+        return;
+    };
+    let backend = backend
+        .with_unported_ops(&["matmul", "elementwise"])
+        .allow_cpu_fallback(true);
+    let a = Tensor::new(vec![1.0, 2.0, 3.0, 4.0]).reshape(&[2, 2]);
+    let b = Tensor::new(vec![5.0, 6.0, 7.0, 8.0]).reshape(&[2, 2]);
+    // matmul and add on the CPU, sum with its shader on the GPU
+    let out = a.matmul(&b) + Tensor::new(vec![1.0; 4]);
+    assert_eq!(
+        out.iter_realized(&backend).collect::<Vec<_>>(),
+        vec![20.0, 23.0, 44.0, 51.0]
+    );
+    assert_eq!(out.sum().item(&backend), 138.0);
+}
+
+#[test]
+fn a_tensor_trained_on_the_cpu_reads_back_from_vulkan() {
+    let Some(backend) = vulkan(MemoryPreference::DeviceLocal) else {
+        return;
+    };
+    let cpu = flamer::backends::CPUBackend::new();
+    let mut w = Tensor::new(vec![1.0, -2.0, 4.0]);
+    (w * w).sum().apply_backward(&cpu, 0.25);
+    w.to_backend(&cpu, &backend);
+    let device = w.buffer.get_device_handle().unwrap();
+    assert_eq!(backend.read_buffer(&device), vec![0.5, -1.0, 2.0]);
+}
+
+#[test]
+fn every_division_policy_on_vulkan() {
+    let Some(backend) = vulkan(MemoryPreference::DeviceLocal) else {
+        return;
+    };
+    common::check_div_policies(&backend);
+}
+
+#[test]
+fn a_non_default_local_size_computes_the_same_results() {
+    let Some(backend) = vulkan(MemoryPreference::DeviceLocal) else {
+        return;
+    };
+    let data: Vec<f32> = (0..1000).map(|i| (i % 7) as f32).collect();
+    let expected_sum: f32 = data.iter().sum();
+    let run = |backend: &VulkanBackend| {
+        let x = Tensor::new(data.clone());
+        let doubled = x + x;
+        let values = doubled.iter_realized(backend).collect::<Vec<_>>();
+        (values, x.sum().item(backend))
+    };
+    // compiles the shaders at the default size, changing it afterwards recompiles them
+    let (values, sum) = run(&backend);
+    assert_eq!(sum, expected_sum);
+    let backend = backend.with_local_size(64);
+    assert_eq!(backend.local_size(), 64);
+    assert_eq!(run(&backend), (values, expected_sum));
+}
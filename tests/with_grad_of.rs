@@ -0,0 +1,21 @@
+use flamer::backends::CPUBackend;
+use flamer::tensor::Tensor;
+
+#[test]
+fn values_come_from_forward_and_gradients_go_to_grad_source() {
+    let backend = CPUBackend::new();
+    let forward = Tensor::new(vec![10.0, -4.0, 0.5]);
+    let grad_source = Tensor::new(vec![1.0, 2.0, 3.0]);
+    let mixed = Tensor::with_grad_of(&forward, &grad_source);
+    assert_eq!(
+        mixed.iter_realized(&backend).collect::<Vec<_>>(),
+        vec![10.0, -4.0, 0.5]
+    );
+
+    let weights = Tensor::without_grad(vec![2.0, -1.0, 0.25]);
+    let grads = (mixed * weights)
+        .sum()
+        .backward_grads(&[forward, grad_source], &backend);
+    assert_eq!(grads[0], vec![0.0; 3]);
+    assert_eq!(grads[1], vec![2.0, -1.0, 0.25]);
+}